@@ -20,11 +20,10 @@ pub fn struct_defs(
             let name = field.ident.as_ref().unwrap();
             let ty = &field.ty;
             let inner_ty = innermost_type(ty);
-            let lt = match extract_lifetime(inner_ty) {
-                Some(lt) => quote! { <#lt> },
-                None => quote! {},
-            };
-            *internal_lifetime.borrow_mut() = lt.clone();
+            // Fields without a lifetime, like a `bool`, don't take it away from the struct
+            if let Some(lt) = extract_lifetime(inner_ty) {
+                *internal_lifetime.borrow_mut() = quote! { <#lt> };
+            }
             quote! { pub #name: #ty }
         })
         .collect::<Vec<_>>();
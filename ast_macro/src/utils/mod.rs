@@ -1,2 +1,3 @@
 pub mod extract_lifetime;
 pub mod innermost_ty;
+pub mod snake_case;
@@ -2,9 +2,11 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 use syn::{Ident, Lifetime, Variant};
 
+use crate::utils::snake_case::snake_case;
+
 pub fn visitor_method(variant: &Variant, lifetime: Option<&Lifetime>, name: Ident) -> TokenStream {
     let struct_name = format_ident!("{}{}", name, &variant.ident);
-    let visitor_name = format_ident!("visit_{}", &variant.ident.to_string().to_lowercase());
+    let visitor_name = format_ident!("visit_{}", snake_case(&variant.ident.to_string()));
 
     let lt = match lifetime {
         Some(lt) => quote! { <#lt> },
@@ -25,7 +27,7 @@ pub fn visitor_method(variant: &Variant, lifetime: Option<&Lifetime>, name: Iden
 
 pub fn accept_method(variant: &Variant) -> TokenStream {
     let variant_name = format_ident!("{}", &variant.ident);
-    let visitor_name = format_ident!("visit_{}", &variant.ident.to_string().to_lowercase());
+    let visitor_name = format_ident!("visit_{}", snake_case(&variant.ident.to_string()));
 
     quote! {
         Self::#variant_name(node) => visitor.#visitor_name(node)
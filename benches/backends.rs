@@ -0,0 +1,76 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_lox::{
+    ast::Arena,
+    interpreter::{resolver::Resolver, Interpreter},
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+    vm::{compiler::Compiler, Vm},
+};
+
+const SOURCE: &str = "var sum = 0;
+for (var i = 0; i < 100000; i = i + 1) {
+  if (i / 2 == 0) continue;
+  sum = sum + i * 2;
+}";
+
+/// `vm-unoptimized` runs the bytecode without fused instructions. Measured on one machine when
+/// fusing was added: tree 240ms, vm-unoptimized 26-28ms, vm 24ms.
+///
+/// `vm-threaded` needs `--features threaded-dispatch`. With rustc 1.95 it took 28-31ms against
+/// 25-28ms for `vm`, calling through function pointers keeps the optimizer from inlining the
+/// handlers into the loop, so the `match` stays the default.
+///
+/// Testing `i < 100000` without the visitor, see `interpreter/condition.rs`, left `tree` within
+/// noise at 158ms since the body dominates. A loop of only `i = i + 1` got 15-25% faster.
+///
+/// Reaching resolved globals by slot, see `interpreter/globals.rs`, took `tree` to 79ms. Most of
+/// that is hashing the expressions with `FxHashMap` instead of SipHash.
+fn loops(c: &mut Criterion) {
+    let tokens = Scanner::new(SOURCE).scan_tokens().unwrap();
+    let arena = Arena::new();
+    let stmts = Parser::new(TokenStream::new(tokens), &arena)
+        .parse()
+        .unwrap();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&stmts).unwrap();
+    let analysis = resolver.analysis();
+    let chunk = Compiler::new().compile(&stmts).unwrap();
+    let mut unoptimized = Compiler::new();
+    unoptimized.set_optimize(false);
+    let unoptimized = unoptimized.compile(&stmts).unwrap();
+
+    let mut group = c.benchmark_group("loop");
+    group.bench_function("tree", |b| {
+        b.iter(|| {
+            let mut interpreter =
+                Interpreter::with_output(analysis.clone(), Box::new(std::io::sink()));
+            interpreter.interpret(&stmts).unwrap();
+        })
+    });
+    group.bench_function("vm", |b| {
+        b.iter(|| {
+            Vm::with_output(Box::new(std::io::sink()))
+                .run_match(&chunk)
+                .unwrap();
+        })
+    });
+    #[cfg(feature = "threaded-dispatch")]
+    group.bench_function("vm-threaded", |b| {
+        b.iter(|| {
+            Vm::with_output(Box::new(std::io::sink()))
+                .run_threaded(&chunk)
+                .unwrap();
+        })
+    });
+    group.bench_function("vm-unoptimized", |b| {
+        b.iter(|| {
+            Vm::with_output(Box::new(std::io::sink()))
+                .run_match(&unoptimized)
+                .unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, loops);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_lox::{
+    ast::Arena,
+    interpreter::{resolver::Resolver, Interpreter},
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+/// Calls of Lox functions, mostly recursion
+const FUNCTIONS: &str = "fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+fib(20);";
+
+/// Calls of bound methods and initializers
+const METHODS: &str = "class Counter {
+  init() { this.count = 0; }
+  inc() { this.count = this.count + 1; return this; }
+}
+
+var counter = Counter();
+for (var i = 0; i < 10000; i = i + 1) {
+  counter.inc();
+}";
+
+/// Calls of natives
+const NATIVES: &str = "var sum = 0;
+for (var i = 0; i < 10000; i = i + 1) {
+  sum = sum + abs(-i) + max(i, 1);
+}";
+
+fn bench_calls(c: &mut Criterion, name: &str, source: &str) {
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let arena = Arena::new();
+    let stmts = Parser::new(TokenStream::new(tokens), &arena)
+        .parse()
+        .unwrap();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&stmts).unwrap();
+    let analysis = resolver.analysis();
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            let mut interpreter =
+                Interpreter::with_output(analysis.clone(), Box::new(std::io::sink()));
+            interpreter.interpret(&stmts).unwrap();
+        })
+    });
+}
+
+fn calls(c: &mut Criterion) {
+    bench_calls(c, "function calls", FUNCTIONS);
+    bench_calls(c, "method calls", METHODS);
+    bench_calls(c, "native calls", NATIVES);
+}
+
+criterion_group!(benches, calls);
+criterion_main!(benches);
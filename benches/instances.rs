@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_lox::{
+    ast::Arena,
+    interpreter::{resolver::Resolver, Interpreter},
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+const SOURCE: &str = "class Point {
+  init(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+
+  len() { return this.x * this.x + this.y * this.y; }
+  add(other) { return Point(this.x + other.x, this.y + other.y); }
+  scale(n) { return Point(this.x * n, this.y * n); }
+  neg() { return Point(-this.x, -this.y); }
+}
+
+var sum = 0;
+for (var i = 0; i < 10000; i = i + 1) {
+  sum = sum + Point(i, i).len();
+}";
+
+fn instance_creation(c: &mut Criterion) {
+    let tokens = Scanner::new(SOURCE).scan_tokens().unwrap();
+    let arena = Arena::new();
+    let stmts = Parser::new(TokenStream::new(tokens), &arena)
+        .parse()
+        .unwrap();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&stmts).unwrap();
+    let analysis = resolver.analysis();
+
+    c.bench_function("instance creation", |b| {
+        b.iter(|| {
+            let mut interpreter =
+                Interpreter::with_output(analysis.clone(), Box::new(std::io::sink()));
+            interpreter.interpret(&stmts).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, instance_creation);
+criterion_main!(benches);
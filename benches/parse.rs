@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_lox::{
+    ast::Arena,
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+/// A script of about 100k lines, functions with a loop, a branch and nested expressions each.
+fn script() -> String {
+    let mut source = String::new();
+    for i in 0..10_000 {
+        source.push_str(&format!(
+            "fun f{i}(a, b) {{
+  var sum = 0;
+  for (var i = 0; i < a; i = i + 1) {{
+    if (i == b or (i > 3 and !false)) {{
+      sum = sum + i * (b - 1) / 2;
+    }} else {{
+      print \"skip\" + \"ped\";
+    }}
+  }}
+  return -sum;
+}}
+"
+        ));
+    }
+    source
+}
+
+/// Measured on one machine when the AST moved into an arena: scan+parse went from 131-163ms with
+/// a `Box` per node to 101-103ms, scanning alone took 38-50ms both times.
+fn parse(c: &mut Criterion) {
+    let source = script();
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.bench_function("scan", |b| {
+        b.iter(|| Scanner::new(&source).scan_tokens().unwrap())
+    });
+    group.bench_function("scan+parse", |b| {
+        b.iter(|| {
+            let tokens = Scanner::new(&source).scan_tokens().unwrap();
+            let arena = Arena::new();
+            let stmts = Parser::new(TokenStream::new(tokens), &arena)
+                .parse()
+                .unwrap();
+            stmts.len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
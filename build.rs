@@ -0,0 +1,26 @@
+use std::process::Command;
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=LOX_GIT_HASH={git_hash}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let features: Vec<_> = [("net", "CARGO_FEATURE_NET")]
+        .iter()
+        .filter(|(_, var)| std::env::var_os(var).is_some())
+        .map(|(name, _)| *name)
+        .collect();
+    let features = match features.is_empty() {
+        true => "none".to_string(),
+        false => features.join(", "),
+    };
+    println!("cargo:rustc-env=LOX_FEATURES={features}");
+}
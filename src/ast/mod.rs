@@ -7,6 +7,41 @@ use ast_macro::Ast;
 
 use crate::lex::Token;
 
+/// Owns the nodes of the syntax trees parsed with it, so a tree is a few large allocations instead
+/// of one per node. The trees borrow from it and can't outlive it.
+#[derive(Default)]
+pub struct Arena<'a> {
+    exprs: typed_arena::Arena<Expr<'a>>,
+    stmts: typed_arena::Arena<Stmt<'a>>,
+}
+
+impl<'a> Arena<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn expr(&'a self, expr: Expr<'a>) -> &'a Expr<'a> {
+        self.exprs.alloc(expr)
+    }
+
+    pub fn stmt(&'a self, stmt: Stmt<'a>) -> &'a Stmt<'a> {
+        self.stmts.alloc(stmt)
+    }
+}
+
+/// Tells nodes apart by their address instead of by what they look like, so the same `a` at the
+/// same position of two sources, e.g. two lines of a session, is two different variables. Only
+/// meaningful while the tree is borrowed, its nodes don't move and their addresses aren't reused
+/// until then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn of<T>(node: &T) -> Self {
+        Self(node as *const T as usize)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum LiteralValue {
     String(String),
@@ -33,17 +68,18 @@ pub enum Expression<'a> {
         value: LiteralValue,
     },
     Grouping {
-        value: Box<Expr<'a>>,
+        value: &'a Expr<'a>,
     },
     Logical {
-        left: Box<Expr<'a>>,
+        left: &'a Expr<'a>,
         operator: Token<'a>,
-        right: Box<Expr<'a>>,
+        right: &'a Expr<'a>,
     },
     Set {
-        object: Box<Expr<'a>>,
+        object: &'a Expr<'a>,
         name: Token<'a>,
-        value: Box<Expr<'a>>,
+        equals: Token<'a>,
+        value: &'a Expr<'a>,
     },
     Super {
         keyword: Token<'a>,
@@ -54,29 +90,76 @@ pub enum Expression<'a> {
     },
     Unary {
         operator: Token<'a>,
-        value: Box<Expr<'a>>,
+        value: &'a Expr<'a>,
     },
     Binary {
-        left: Box<Expr<'a>>,
+        left: &'a Expr<'a>,
         operator: Token<'a>,
-        right: Box<Expr<'a>>,
+        right: &'a Expr<'a>,
     },
     Call {
-        callee: Box<Expr<'a>>,
+        callee: &'a Expr<'a>,
         paren: Token<'a>,
         arguments: Vec<Expr<'a>>,
     },
     Get {
-        object: Box<Expr<'a>>,
+        object: &'a Expr<'a>,
         name: Token<'a>,
     },
     Assign {
         name: Token<'a>,
-        value: Box<Expr<'a>>,
+        equals: Token<'a>,
+        value: &'a Expr<'a>,
     },
     Variable {
         name: Token<'a>,
     },
+    /// `bracket` is the opening `[`
+    List {
+        bracket: Token<'a>,
+        elements: Vec<Expr<'a>>,
+    },
+    /// `brace` is the opening `{`, `keys[i]` maps to `values[i]`
+    Map {
+        brace: Token<'a>,
+        keys: Vec<Expr<'a>>,
+        values: Vec<Expr<'a>>,
+    },
+    /// `"a ${b} c"`. `parts` are the string segments and the expressions between them in source
+    /// order, without the empty segments. `start` is the segment before the first expression.
+    Interpolation {
+        start: Token<'a>,
+        parts: Vec<Expr<'a>>,
+    },
+    /// `bracket` is the closing `]`, runtime errors about the index point at it
+    Index {
+        object: &'a Expr<'a>,
+        bracket: Token<'a>,
+        index: &'a Expr<'a>,
+    },
+    SetIndex {
+        object: &'a Expr<'a>,
+        bracket: Token<'a>,
+        index: &'a Expr<'a>,
+        equals: Token<'a>,
+        value: &'a Expr<'a>,
+    },
+    /// `++x`, `x++`, `--x` or `x--`. `target` is a variable or a property, `prefix` tells whether
+    /// the expression evaluates to the new value or the old one.
+    Update {
+        operator: Token<'a>,
+        target: &'a Expr<'a>,
+        prefix: bool,
+    },
+    /// `target += value` and the other compound assignments of a property or an index, `operator`
+    /// is the binary operator of `equals`. The object and the index of `target` are evaluated
+    /// once, compound assignments of variables are desugared to `x = x + value`.
+    Compound {
+        target: &'a Expr<'a>,
+        equals: Token<'a>,
+        operator: Token<'a>,
+        value: &'a Expr<'a>,
+    },
 }
 
 #[derive(Ast, Debug, PartialEq)]
@@ -85,10 +168,18 @@ pub enum Statement<'a> {
     Block {
         statements: Vec<Stmt<'a>>,
     },
+    Break {
+        keyword: Token<'a>,
+    },
     Class {
         name: Token<'a>,
         superclass: Option<Expr<'a>>,
         methods: Vec<StmtFunction<'a>>,
+        /// Methods declared with a leading `class`, called on the class itself
+        class_methods: Vec<StmtFunction<'a>>,
+    },
+    Continue {
+        keyword: Token<'a>,
     },
     Expression {
         expr: Expr<'a>,
@@ -97,11 +188,14 @@ pub enum Statement<'a> {
         name: Token<'a>,
         params: Vec<Token<'a>>,
         body: Vec<Stmt<'a>>,
+        /// A method declared without a parameter list, it runs when the property is read
+        is_getter: bool,
     },
     If {
+        keyword: Token<'a>,
         condition: Expr<'a>,
-        then_branch: Box<Stmt<'a>>,
-        else_branch: Option<Box<Stmt<'a>>>,
+        then_branch: &'a Stmt<'a>,
+        else_branch: Option<&'a Stmt<'a>>,
     },
     Print {
         expr: Expr<'a>,
@@ -114,8 +208,12 @@ pub enum Statement<'a> {
         name: Token<'a>,
         initializer: Option<Expr<'a>>,
     },
+    /// `keyword` is the `for` of a desugared `for` loop, `increment` is its increment clause. It
+    /// is kept out of the body so `continue` doesn't skip it.
     While {
+        keyword: Token<'a>,
         condition: Expr<'a>,
-        body: Box<Stmt<'a>>,
+        body: &'a Stmt<'a>,
+        increment: Option<Expr<'a>>,
     },
 }
@@ -1,19 +1,50 @@
 use super::*;
 
+/// Prints expressions and statements as parenthesized prefix notation, like `(print (+ 1 2))`.
 pub struct AstPrinter;
 
 impl AstPrinter {
+    /// One statement per line.
+    pub fn print(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|stmt| stmt.accept(self))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn parenthesize(&mut self, name: &str, node: &[&Expr]) -> String {
         let mut expr_ac = Vec::new();
         for expr in node {
-            let e = (*expr).clone();
-            expr_ac.push(e.accept(self));
+            expr_ac.push(expr.accept(self));
         }
         format!("({} {})", name, expr_ac.join(" "))
     }
+
+    fn parenthesize_parts(&mut self, name: &str, parts: &[String]) -> String {
+        match parts.is_empty() {
+            true => format!("({name})"),
+            false => format!("({name} {})", parts.join(" ")),
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) -> Vec<String> {
+        statements.iter().map(|stmt| stmt.accept(self)).collect()
+    }
+
+    fn function(&mut self, node: &StmtFunction) -> String {
+        let params: Vec<_> = node.params.iter().map(|param| param.lexeme).collect();
+        let mut parts = vec![node.name.lexeme.to_string()];
+        // A getter has no parameter list at all
+        if !node.is_getter {
+            parts.push(format!("({})", params.join(" ")));
+        }
+        parts.extend(self.statements(&node.body));
+        self.parenthesize_parts("fun", &parts)
+    }
 }
 
-impl<'a> ExprVisitor<'_, 'a> for AstPrinter {
+impl<'a, 'b> ExprVisitor<'a, 'b> for AstPrinter {
     type Output = String;
 
     fn visit_literal(&mut self, node: &ExprLiteral) -> Self::Output {
@@ -26,46 +57,207 @@ impl<'a> ExprVisitor<'_, 'a> for AstPrinter {
     }
 
     fn visit_grouping(&mut self, node: &ExprGrouping) -> Self::Output {
-        self.parenthesize("group", &[&node.value])
+        self.parenthesize("group", &[node.value])
     }
 
-    fn visit_logical(&mut self, _node: &ExprLogical) -> Self::Output {
-        todo!()
+    fn visit_logical(&mut self, node: &ExprLogical) -> Self::Output {
+        self.parenthesize(node.operator.lexeme, &[node.left, node.right])
     }
 
-    fn visit_set(&mut self, _node: &ExprSet<'a>) -> Self::Output {
-        todo!()
+    fn visit_set(&mut self, node: &ExprSet<'a>) -> Self::Output {
+        let parts = [
+            node.object.accept(self),
+            node.name.lexeme.to_string(),
+            node.value.accept(self),
+        ];
+        self.parenthesize_parts("=.", &parts)
     }
 
-    fn visit_super(&mut self, _node: &ExprSuper<'a>) -> Self::Output {
-        todo!()
+    fn visit_super(&mut self, node: &ExprSuper<'a>) -> Self::Output {
+        self.parenthesize_parts("super", &[node.method.lexeme.to_string()])
     }
 
     fn visit_this(&mut self, _node: &ExprThis<'a>) -> Self::Output {
-        todo!()
+        "this".to_string()
     }
 
     fn visit_unary(&mut self, node: &ExprUnary) -> Self::Output {
-        self.parenthesize(node.operator.lexeme, &[&node.value])
+        self.parenthesize(node.operator.lexeme, &[node.value])
     }
 
     fn visit_binary(&mut self, node: &ExprBinary) -> Self::Output {
-        self.parenthesize(node.operator.lexeme, &[&node.left, &node.right])
+        self.parenthesize(node.operator.lexeme, &[node.left, node.right])
+    }
+
+    fn visit_call(&mut self, node: &ExprCall) -> Self::Output {
+        let mut parts = vec![node.callee.accept(self)];
+        parts.extend(node.arguments.iter().map(|argument| argument.accept(self)));
+        self.parenthesize_parts("call", &parts)
+    }
+
+    fn visit_get(&mut self, node: &ExprGet<'a>) -> Self::Output {
+        let parts = [node.object.accept(self), node.name.lexeme.to_string()];
+        self.parenthesize_parts(".", &parts)
+    }
+
+    fn visit_assign(&mut self, node: &ExprAssign) -> Self::Output {
+        let parts = [node.name.lexeme.to_string(), node.value.accept(self)];
+        self.parenthesize_parts("=", &parts)
+    }
+
+    fn visit_variable(&mut self, node: &ExprVariable) -> Self::Output {
+        node.name.lexeme.to_string()
+    }
+
+    fn visit_list(&mut self, node: &ExprList<'a>) -> Self::Output {
+        let parts: Vec<_> = node.elements.iter().map(|e| e.accept(self)).collect();
+        self.parenthesize_parts("list", &parts)
+    }
+
+    fn visit_map(&mut self, node: &ExprMap<'a>) -> Self::Output {
+        let mut parts = vec![];
+        for (key, value) in node.keys.iter().zip(&node.values) {
+            parts.push(key.accept(self));
+            parts.push(value.accept(self));
+        }
+        self.parenthesize_parts("map", &parts)
+    }
+
+    fn visit_interpolation(&mut self, node: &ExprInterpolation<'a>) -> Self::Output {
+        let parts: Vec<_> = node.parts.iter().map(|part| part.accept(self)).collect();
+        self.parenthesize_parts("interpolate", &parts)
+    }
+
+    fn visit_index(&mut self, node: &ExprIndex<'a>) -> Self::Output {
+        self.parenthesize("[]", &[node.object, node.index])
+    }
+
+    fn visit_set_index(&mut self, node: &ExprSetIndex<'a>) -> Self::Output {
+        self.parenthesize("=[]", &[node.object, node.index, node.value])
+    }
+
+    /// `(++ x)` for `++x` and `(post++ x)` for `x++`
+    fn visit_update(&mut self, node: &ExprUpdate<'a>) -> Self::Output {
+        let name = match node.prefix {
+            true => node.operator.lexeme.to_string(),
+            false => format!("post{}", node.operator.lexeme),
+        };
+        self.parenthesize(&name, &[node.target])
     }
 
-    fn visit_call(&mut self, _node: &ExprCall) -> Self::Output {
-        todo!()
+    /// `(+= (. o f) 1)` for `o.f += 1`
+    fn visit_compound(&mut self, node: &ExprCompound<'a>) -> Self::Output {
+        self.parenthesize(node.equals.lexeme, &[node.target, node.value])
+    }
+}
+
+impl<'a, 'b> StmtVisitor<'a, 'b> for AstPrinter {
+    type Output = String;
+
+    fn visit_block(&mut self, node: &StmtBlock<'a>) -> Self::Output {
+        let parts = self.statements(&node.statements);
+        self.parenthesize_parts("block", &parts)
+    }
+
+    fn visit_break(&mut self, _node: &StmtBreak<'a>) -> Self::Output {
+        "(break)".to_string()
+    }
+
+    fn visit_class(&mut self, node: &StmtClass<'a>) -> Self::Output {
+        let mut parts = vec![node.name.lexeme.to_string()];
+        if let Some(superclass) = &node.superclass {
+            parts.push(format!("< {}", superclass.accept(self)));
+        }
+        parts.extend(node.methods.iter().map(|method| self.function(method)));
+        parts.extend(
+            node.class_methods
+                .iter()
+                .map(|method| format!("(class {})", self.function(method))),
+        );
+        self.parenthesize_parts("class", &parts)
+    }
+
+    fn visit_continue(&mut self, _node: &StmtContinue<'a>) -> Self::Output {
+        "(continue)".to_string()
     }
 
-    fn visit_get(&mut self, _node: &ExprGet<'a>) -> Self::Output {
-        todo!()
+    fn visit_expression(&mut self, node: &StmtExpression<'a>) -> Self::Output {
+        self.parenthesize(";", &[&node.expr])
     }
 
-    fn visit_assign(&mut self, _node: &ExprAssign) -> Self::Output {
-        todo!()
+    fn visit_function(&mut self, node: &StmtFunction<'a>) -> Self::Output {
+        self.function(node)
     }
 
-    fn visit_variable(&mut self, _node: &ExprVariable) -> Self::Output {
-        todo!()
+    fn visit_if(&mut self, node: &StmtIf<'a>) -> Self::Output {
+        let mut parts = vec![node.condition.accept(self), node.then_branch.accept(self)];
+        if let Some(else_branch) = &node.else_branch {
+            parts.push(else_branch.accept(self));
+        }
+        self.parenthesize_parts("if", &parts)
+    }
+
+    fn visit_print(&mut self, node: &StmtPrint<'a>) -> Self::Output {
+        self.parenthesize("print", &[&node.expr])
+    }
+
+    fn visit_return(&mut self, node: &StmtReturn<'a>) -> Self::Output {
+        let parts: Vec<_> = node.value.iter().map(|value| value.accept(self)).collect();
+        self.parenthesize_parts("return", &parts)
+    }
+
+    fn visit_var(&mut self, node: &StmtVar<'a>) -> Self::Output {
+        let mut parts = vec![node.name.lexeme.to_string()];
+        if let Some(initializer) = &node.initializer {
+            parts.push("=".to_string());
+            parts.push(initializer.accept(self));
+        }
+        self.parenthesize_parts("var", &parts)
+    }
+
+    fn visit_while(&mut self, node: &StmtWhile<'a>) -> Self::Output {
+        let mut parts = vec![node.condition.accept(self), node.body.accept(self)];
+        if let Some(increment) = &node.increment {
+            parts.push(increment.accept(self));
+        }
+        self.parenthesize_parts("while", &parts)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+    };
+
+    use super::*;
+
+    #[test]
+    fn print_program() {
+        let input = "var a = 1;
+class B < A {
+  init(x) { this.x = x; }
+  get() { return super.get(); }
+}
+fun f(a, b) {
+  if (a and !b) print a; else return;
+}
+for (var i = 0; i < 2; i = i + 1) f(i, B(i).x);
+while (a) { if (a) break; else continue; }";
+        let arena = Arena::new();
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            AstPrinter.print(&stmts),
+            "(var a = 1)
+(class B < A (fun init (x) (; (=. this x x))) (fun get () (return (call (super get)))))
+(fun f (a b) (if (and a (! b)) (print a) (return)))
+(block (var i = 0) (while (< i 2) (; (call f i (. (call B i) x))) (= i (+ i 1))))
+(while a (block (if a (break) (continue))))"
+        );
     }
 }
@@ -0,0 +1,196 @@
+use std::{
+    fmt::Display,
+    io::{self, IsTerminal},
+};
+
+use clap::ValueEnum;
+
+use crate::{
+    lex::Span,
+    lint::{Level, Warning},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const DIM: &str = "\x1b[2m";
+
+/// When to color diagnostics, see `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Color if stderr is a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Formats errors and warnings for stderr. In color mode the first line is colored by severity
+/// and the trailing `[code]` is dimmed, the plain mode output is what the golden tests compare.
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(choice: ColorChoice) -> Self {
+        let color = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        };
+        Self { color }
+    }
+
+    pub fn plain() -> Self {
+        Self { color: false }
+    }
+
+    /// An error of any stage followed by its code, like `[line 1] Error: ... [E0120]`.
+    pub fn error(&self, error: &dyn Display, code: &str) -> String {
+        self.paint(Severity::Error, &format!("{error:#} [{code}]"))
+    }
+
+    /// The warning as reported at `level`, `None` if the lint is allowed.
+    pub fn warning(&self, warning: &Warning, level: Level) -> Option<String> {
+        let severity = match level {
+            Level::Deny => Severity::Error,
+            _ => Severity::Warning,
+        };
+        warning
+            .report(level)
+            .map(|report| self.paint(severity, &report))
+    }
+
+    /// The line of `source` that `span` starts on with the span underlined, like
+    ///
+    /// ```text
+    ///   |
+    /// 2 | print (2;
+    ///   |        ^
+    /// ```
+    ///
+    /// Spans reaching past the end of their line are underlined up to it. `None` if the span isn't
+    /// inside `source`.
+    pub fn snippet(&self, source: &str, span: Span) -> Option<String> {
+        let (begin, end) = (span.begin as usize, span.end as usize);
+        let prefix = source.get(..begin)?;
+        let underlined = source.get(begin..end)?;
+        let start = prefix.rfind('\n').map_or(0, |i| i + 1);
+        let line = source[start..].split('\n').next().unwrap_or_default();
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let number = prefix.matches('\n').count() + 1;
+
+        // Tabs are kept so that the carets line up with the code however wide tabs are shown
+        let indent: String = source[start..begin]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = underlined
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            .max(1);
+        let carets = "^".repeat(width);
+        let carets = match self.color {
+            true => format!("{BOLD_RED}{carets}{RESET}"),
+            false => carets,
+        };
+
+        let gutter = " ".repeat(number.to_string().len());
+        Some(format!(
+            "{gutter} |\n{number} | {line}\n{gutter} | {indent}{carets}"
+        ))
+    }
+
+    fn paint(&self, severity: Severity, text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+
+        let (header, rest) = match text.split_once('\n') {
+            Some((header, rest)) => (header, format!("\n{rest}")),
+            None => (text, String::new()),
+        };
+        let color = match severity {
+            Severity::Error => BOLD_RED,
+            Severity::Warning => BOLD_YELLOW,
+        };
+        match header.rsplit_once(" [") {
+            Some((message, tag)) => format!("{color}{message}{RESET} {DIM}[{tag}{RESET}{rest}"),
+            None => format!("{color}{header}{RESET}{rest}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lex::{Span, Token, TokenType},
+        lint::Lint,
+    };
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let error = "[line 1] Error: Expected ';'.";
+        let warning = Warning {
+            lint: Lint::UnusedVariable,
+            token: Token::new(TokenType::Ident, "a", 2, Span { begin: 0, end: 1 }),
+        };
+
+        let plain = Renderer::plain();
+        assert_eq!(
+            plain.error(&error, "E0101"),
+            "[line 1] Error: Expected ';'. [E0101]"
+        );
+        assert_eq!(
+            plain.warning(&warning, Level::Warn).unwrap(),
+            "[line 2] Warning: Local variable 'a' is never used. [unused-variable]"
+        );
+        assert_eq!(plain.warning(&warning, Level::Allow), None);
+
+        let color = Renderer::new(ColorChoice::Always);
+        assert_eq!(
+            color.error(&error, "E0101"),
+            "\x1b[1;31m[line 1] Error: Expected ';'.\x1b[0m \x1b[2m[E0101]\x1b[0m"
+        );
+        assert_eq!(
+            color.warning(&warning, Level::Deny).unwrap(),
+            "\x1b[1;31m[line 2] Error: Local variable 'a' is never used.\x1b[0m \x1b[2m[unused-variable]\x1b[0m"
+        );
+        assert!(!Renderer::new(ColorChoice::Never).color);
+    }
+
+    #[test]
+    fn snippet() {
+        let plain = Renderer::plain();
+        let source = "print 1;\n\tprint (2;\nvar s = \"a\nb\";";
+        assert_eq!(
+            plain.snippet(source, Span { begin: 17, end: 18 }).unwrap(),
+            "  |\n2 | \tprint (2;\n  | \t       ^"
+        );
+        // A string spanning lines is underlined to the end of its first line
+        assert_eq!(
+            plain.snippet(source, Span { begin: 28, end: 33 }).unwrap(),
+            "  |\n3 | var s = \"a\n  |         ^^"
+        );
+        // The end of the file has nothing to underline
+        assert_eq!(
+            plain.snippet("print", Span { begin: 5, end: 5 }).unwrap(),
+            "  |\n1 | print\n  |      ^"
+        );
+        assert_eq!(plain.snippet("print", Span { begin: 2, end: 9 }), None);
+    }
+}
@@ -0,0 +1,239 @@
+use std::io::Write;
+
+use crate::{
+    ast::Arena,
+    interpreter::{
+        resolver::{Analysis, Resolver},
+        Interpreter,
+    },
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+const EXPECT: &str = "// expect:";
+
+/// A fenced code block found in the `///` doc comments of a script.
+#[derive(Debug, PartialEq)]
+pub struct Example {
+    pub line: usize,
+    pub code: String,
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed {
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    Error(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+pub fn extract(source: &str) -> Vec<Example> {
+    let mut examples = vec![];
+    let mut current: Option<Example> = None;
+
+    for (i, line) in source.lines().enumerate() {
+        let Some(doc) = line.trim_start().strip_prefix("///") else {
+            continue;
+        };
+        let doc = doc.strip_prefix(' ').unwrap_or(doc);
+
+        if doc.trim_start().starts_with("```") {
+            match current.take() {
+                Some(example) => examples.push(example),
+                None => {
+                    current = Some(Example {
+                        line: i + 1,
+                        code: String::new(),
+                        expected: vec![],
+                    })
+                }
+            }
+            continue;
+        }
+
+        if let Some(example) = current.as_mut() {
+            if let Some((_, expected)) = doc.split_once(EXPECT) {
+                example.expected.push(expected.trim().to_string());
+            }
+            example.code.push_str(doc);
+            example.code.push('\n');
+        }
+    }
+
+    examples
+}
+
+/// Runs the example in a fresh interpreter after the script it documents, so examples can use
+/// everything the script defines. Only what the example itself prints is compared.
+pub fn run_example(script: &str, example: &Example) -> Outcome {
+    let mut output = vec![];
+    if let Err(e) = run(script, &example.code, &mut output) {
+        return Outcome::Error(e);
+    }
+
+    let actual: Vec<String> = String::from_utf8_lossy(&output)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if actual == example.expected {
+        Outcome::Passed
+    } else {
+        Outcome::Failed {
+            expected: example.expected.clone(),
+            actual,
+        }
+    }
+}
+
+pub fn run_all(name: &str, script: &str, out: &mut impl Write) -> std::io::Result<Summary> {
+    let mut summary = Summary::default();
+
+    for example in extract(script) {
+        match run_example(script, &example) {
+            Outcome::Passed => {
+                summary.passed += 1;
+                writeln!(out, "doctest {name}:{} ... ok", example.line)?;
+            }
+            Outcome::Failed { expected, actual } => {
+                summary.failed += 1;
+                writeln!(out, "doctest {name}:{} ... FAILED", example.line)?;
+                writeln!(out, "  expected: {expected:?}")?;
+                writeln!(out, "  actual:   {actual:?}")?;
+            }
+            Outcome::Error(e) => {
+                summary.failed += 1;
+                writeln!(out, "doctest {name}:{} ... FAILED", example.line)?;
+                writeln!(out, "  {e}")?;
+            }
+        }
+    }
+
+    writeln!(
+        out,
+        "\ndoctest result: {} passed; {} failed",
+        summary.passed, summary.failed
+    )?;
+    Ok(summary)
+}
+
+fn run(script: &str, example: &str, output: &mut Vec<u8>) -> Result<(), String> {
+    let arena = Arena::new();
+    let script_tokens = Scanner::new(script)
+        .scan_tokens()
+        .map_err(|errors| errors[0].to_string())?;
+    let script_stmts = Parser::new(TokenStream::new(script_tokens), &arena)
+        .parse()
+        .map_err(|errors| errors[0].to_string())?;
+    let example_tokens = Scanner::new(example)
+        .scan_tokens()
+        .map_err(|errors| errors[0].to_string())?;
+    let example_stmts = Parser::new(TokenStream::new(example_tokens), &arena)
+        .parse()
+        .map_err(|errors| errors[0].to_string())?;
+
+    let mut resolver = Resolver::new();
+    resolver
+        .resolve(&script_stmts)
+        .and_then(|_| resolver.resolve(&example_stmts))
+        .map_err(|e| e.to_string())?;
+
+    let mut interpreter = Interpreter::with_output(Analysis::default(), Box::new(std::io::sink()));
+    interpreter.add_analysis(resolver.analysis());
+    interpreter
+        .interpret(&script_stmts)
+        .map_err(|e| e.to_string())?;
+
+    interpreter.set_output(Box::new(output));
+    interpreter
+        .interpret(&example_stmts)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = "/// Adds two numbers.
+///
+/// ```
+/// print add(1, 2); // expect: 3
+/// print add(\"a\", \"b\"); // expect: ab
+/// ```
+fun add(a, b) {
+  return a + b;
+}
+
+/// ```
+/// print add(1, 1); // expect: 3
+/// ```
+print \"not part of any example\";
+";
+
+    #[test]
+    fn extracts_fenced_blocks() {
+        let examples = extract(SCRIPT);
+
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].line, 3);
+        assert_eq!(examples[0].expected, vec!["3", "ab"]);
+        assert_eq!(
+            examples[1].code,
+            "print add(1, 1); // expect: 3\n".to_string()
+        );
+    }
+
+    #[test]
+    fn runs_examples() {
+        let examples = extract(SCRIPT);
+
+        assert_eq!(run_example(SCRIPT, &examples[0]), Outcome::Passed);
+        assert_eq!(
+            run_example(SCRIPT, &examples[1]),
+            Outcome::Failed {
+                expected: vec!["3".to_string()],
+                actual: vec!["2".to_string()],
+            }
+        );
+    }
+
+    /// The `a` returned by `g` sits where the one returned by `f` does, but in the example.
+    #[test]
+    fn example_keeps_variables_apart() {
+        let example = Example {
+            line: 1,
+            code: "fun g( ) { return a; }\nprint f(1); print g();\n".to_string(),
+            expected: vec!["1".to_string(), "7".to_string()],
+        };
+
+        assert_eq!(
+            run_example("fun f(a) { return a; }\nvar a = 7;\n", &example),
+            Outcome::Passed
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let mut out = vec![];
+        let summary = run_all("add.lox", SCRIPT, &mut out).unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                passed: 1,
+                failed: 1
+            }
+        );
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .contains("doctest add.lox:11 ... FAILED"));
+    }
+}
@@ -0,0 +1,243 @@
+//! One error type for every stage, for callers that handle all failures the same way.
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::{
+    interpreter::error::{ResolverError, RuntimeError},
+    lex::{error::TokenError, Span, Token},
+    parser::error::ParserError,
+    source::SourceError,
+    vm::error::CompileError,
+};
+
+/// Exit code for a script that doesn't scan, parse, resolve or compile, `EX_DATAERR`.
+pub const EXIT_INVALID: i32 = 65;
+/// Exit code for a script that can't be opened, `EX_NOINPUT`.
+pub const EXIT_NO_INPUT: i32 = 66;
+/// Exit code for a script that failed while running, `EX_SOFTWARE`.
+pub const EXIT_RUNTIME: i32 = 70;
+
+/// The stage of a run that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Read,
+    Scan,
+    Parse,
+    Resolve,
+    Compile,
+    Run,
+}
+
+/// An error of one stage, detached from the source it was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub stage: Stage,
+    /// Like `[line 1] Error: Expected expression.`
+    pub message: String,
+    /// Code of the error, see `rust_lox --explain <CODE>`
+    pub code: &'static str,
+    /// 1-based, `None` if the error isn't tied to a place in the source
+    pub line: Option<u32>,
+    /// Byte offsets into the source
+    pub span: Option<Span>,
+}
+
+impl Report {
+    fn new(stage: Stage, error: &impl fmt::Display, code: &'static str) -> Self {
+        Self {
+            stage,
+            message: error.to_string(),
+            code,
+            line: None,
+            span: None,
+        }
+    }
+
+    fn at(self, token: Option<Token>) -> Self {
+        match token {
+            Some(token) => Self {
+                line: Some(token.line),
+                span: Some(token.span),
+                ..self
+            },
+            None => self,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LoxError {
+    /// The script couldn't be read.
+    #[error("{0}")]
+    Source(Report),
+
+    /// Every error the scanner or, if scanning succeeded, the parser found. Nothing ran.
+    #[error("{}", join(.0))]
+    Syntax(Vec<Report>),
+
+    /// Nothing ran.
+    #[error("{0}")]
+    Resolve(Report),
+
+    /// The bytecode compiler rejected the program. Nothing ran.
+    #[error("{0}")]
+    Compile(Report),
+
+    /// The statements before the failing one ran.
+    #[error("{0}")]
+    Runtime(Report),
+}
+
+impl LoxError {
+    /// Code of the (first) error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        self.reports().first().map_or("", |report| report.code)
+    }
+
+    pub fn reports(&self) -> &[Report] {
+        match self {
+            LoxError::Syntax(reports) => reports,
+            LoxError::Source(report)
+            | LoxError::Resolve(report)
+            | LoxError::Compile(report)
+            | LoxError::Runtime(report) => std::slice::from_ref(report),
+        }
+    }
+
+    /// The exit code of the command line tool, following sysexits.h like clox.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Source(report) if report.code == "E0006" => EXIT_NO_INPUT,
+            LoxError::Source(_)
+            | LoxError::Syntax(_)
+            | LoxError::Resolve(_)
+            | LoxError::Compile(_) => EXIT_INVALID,
+            LoxError::Runtime(_) => EXIT_RUNTIME,
+        }
+    }
+}
+
+fn join(reports: &[Report]) -> String {
+    let reports: Vec<_> = reports.iter().map(ToString::to_string).collect();
+    reports.join("\n")
+}
+
+impl From<SourceError> for LoxError {
+    fn from(error: SourceError) -> Self {
+        let mut report = Report::new(Stage::Read, &error, error.code());
+        if let SourceError::InvalidUtf8 { line, .. } = error {
+            report.line = Some(line as u32);
+        }
+        LoxError::Source(report)
+    }
+}
+
+impl From<TokenError> for Report {
+    fn from(error: TokenError) -> Self {
+        let mut report = Report::new(Stage::Scan, &error, error.code());
+        if let TokenError::NonTerminatedString { line, span, .. }
+        | TokenError::UnexpectedToken { line, span, .. }
+        | TokenError::UnterminatedComment { line, span } = error
+        {
+            report.line = Some(line);
+            report.span = Some(span);
+        }
+        report
+    }
+}
+
+impl From<ParserError<'_>> for Report {
+    fn from(error: ParserError<'_>) -> Self {
+        Report::new(Stage::Parse, &error, error.code()).at(error.token())
+    }
+}
+
+impl From<Vec<TokenError>> for LoxError {
+    fn from(errors: Vec<TokenError>) -> Self {
+        LoxError::Syntax(errors.into_iter().map(Report::from).collect())
+    }
+}
+
+impl From<Vec<ParserError<'_>>> for LoxError {
+    fn from(errors: Vec<ParserError<'_>>) -> Self {
+        LoxError::Syntax(errors.into_iter().map(Report::from).collect())
+    }
+}
+
+impl From<ResolverError<'_>> for LoxError {
+    fn from(error: ResolverError<'_>) -> Self {
+        LoxError::Resolve(Report::new(Stage::Resolve, &error, error.code()).at(error.token()))
+    }
+}
+
+impl From<CompileError<'_>> for LoxError {
+    fn from(error: CompileError<'_>) -> Self {
+        LoxError::Compile(Report::new(Stage::Compile, &error, error.code()).at(Some(error.token())))
+    }
+}
+
+impl From<RuntimeError<'_>> for LoxError {
+    fn from(error: RuntimeError<'_>) -> Self {
+        LoxError::Runtime(Report::new(Stage::Run, &error, error.code()).at(error.token()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ast::Arena, interpreter::resolver::Resolver, lex::Scanner, parser::Parser};
+
+    use super::*;
+
+    fn parse(source: &str) -> LoxError {
+        let arena = Arena::new();
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let errors = Parser::new(crate::parser::TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap_err();
+        errors.into()
+    }
+
+    #[test]
+    fn location() {
+        let error = parse("print 1;\nprint (2;");
+        assert_eq!(error.code(), "E0101");
+        assert_eq!(error.exit_code(), 65);
+        let report = &error.reports()[0];
+        assert_eq!(report.stage, Stage::Parse);
+        assert_eq!(report.line, Some(2));
+        assert_eq!(report.span, Some(Span { begin: 16, end: 17 }));
+
+        let error: LoxError = Scanner::new("\"open").scan_tokens().unwrap_err().into();
+        assert_eq!(error.reports()[0].stage, Stage::Scan);
+        assert_eq!(error.reports()[0].span, Some(Span { begin: 0, end: 5 }));
+
+        let arena = Arena::new();
+        let tokens = Scanner::new("return 1;").scan_tokens().unwrap();
+        let stmts = Parser::new(crate::parser::TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let error: LoxError = Resolver::new().resolve(&stmts).unwrap_err().into();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Can't return from top-level code. [E0203]"
+        );
+        assert_eq!(error.reports()[0].span, Some(Span { begin: 0, end: 6 }));
+    }
+
+    #[test]
+    fn exit_codes() {
+        let missing: LoxError = crate::source::read("does/not/exist.lox", false)
+            .unwrap_err()
+            .into();
+        assert_eq!(missing.exit_code(), 66);
+        assert_eq!(LoxError::from(RuntimeError::Interrupted).exit_code(), 70);
+    }
+}
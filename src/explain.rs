@@ -0,0 +1,754 @@
+//! Long explanations for the error codes printed next to every diagnostic.
+//!
+//! Codes are grouped by the stage that reports them: `E00xx` for the scanner, `E01xx` for the
+//! parser, `E02xx` for the resolver, `E03xx` for runtime errors and `E04xx` for the bytecode
+//! compiler. `E0100`, `E0200` and `E0300`
+//! are internal errors of the respective stage. Codes are never reused once assigned.
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0001",
+        "A string literal was not closed before the end of the file.
+
+    print \"hello;
+
+The error points at the line of the opening quote, as strings can span several lines. Close the
+string with a `\"`.",
+    ),
+    (
+        "E0002",
+        "The scanner found a character that can't start any token.
+
+    var a = 1 @ 2;
+
+Remove the character or replace it with a valid operator.",
+    ),
+    (
+        "E0003",
+        "A number literal doesn't follow the number syntax. Prefixed literals need at least one
+digit after `0x`/`0b`, underscores must sit between two digits and an exponent needs digits.
+
+    print 0x;
+    print 1_;
+    print 1e;
+
+Complete the literal, e.g. `0x1F`, `1_000` or `1e3`.",
+    ),
+    ("E0004", "The file ended in the middle of a token."),
+    (
+        "E0005",
+        "A `#` line is not a valid `#define`. A define names one constant and gives it a single
+number, string, boolean or nil, and each name can only be defined once.
+
+    #define PI 3.14159
+    #define GREETING \"hello\"
+",
+    ),
+    (
+        "E0006",
+        "The script file couldn't be read, because it doesn't exist, is a directory or isn't
+readable by the current user. The message includes the reason reported by the operating system.",
+    ),
+    (
+        "E0007",
+        "The script file is not valid UTF-8. The error points at the line and byte column of the
+first invalid byte, which usually means the file was saved in another encoding like Latin-1.
+
+Convert the file to UTF-8, or pass `--lossy` to replace invalid bytes with U+FFFD and run it
+anyway. A UTF-8 byte order mark at the start of the file is fine, it is skipped.",
+    ),
+    (
+        "E0008",
+        "A `/*` block comment was not closed before the end of the file. Block comments nest, so
+every `/*` inside a comment needs its own `*/`.
+
+    /* outer /* inner */
+    print 1;
+
+The error points at the line of the opening `/*`. Close the comment with `*/`.",
+    ),
+    (
+        "E0100",
+        "The parser ran past the end of its tokens. This is a bug in the interpreter, please report
+it together with the script that triggered it.",
+    ),
+    (
+        "E0101",
+        "A parenthesized expression was not closed.
+
+    print (1 + 2;
+
+Add the missing `)`.",
+    ),
+    (
+        "E0102",
+        "The condition of an `if` must be wrapped in parentheses.
+
+    if x > 1 print x;
+
+Write `if (x > 1) print x;` instead.",
+    ),
+    (
+        "E0103",
+        "The clauses of a `for` loop must be wrapped in parentheses.
+
+    for var i = 0; i < 3; i = i + 1 print i;
+
+Write `for (var i = 0; i < 3; i = i + 1) print i;` instead.",
+    ),
+    (
+        "E0104",
+        "The condition of a `while` loop must be wrapped in parentheses.
+
+    while x < 3 x = x + 1;
+
+Write `while (x < 3) x = x + 1;` instead.",
+    ),
+    (
+        "E0105",
+        "The condition of an `if` or `while` was not followed by a `)`.
+
+    if (x > 1 print x;
+
+Close the condition with `)`.",
+    ),
+    (
+        "E0106",
+        "The clauses of a `for` loop were not followed by a `)`.
+
+    for (var i = 0; i < 3; i = i + 1 print i;
+
+Close the clauses with `)`.",
+    ),
+    (
+        "E0107",
+        "The arguments of a call were not followed by a `)`.
+
+    print max(1, 2;
+
+Close the argument list with `)`.",
+    ),
+    (
+        "E0108",
+        "An expression was expected but something else was found.",
+    ),
+    (
+        "E0109",
+        "Statements must end with a semicolon.
+
+    print 1
+
+Write `print 1;` instead.",
+    ),
+    (
+        "E0110",
+        "`fun` must be followed by the name of the function.
+
+    fun (a) { return a; }
+
+Give the function a name, e.g. `fun identity(a) { return a; }`.",
+    ),
+    (
+        "E0111",
+        "The name of a function must be followed by its parameter list.
+
+    fun greet { print \"hi\"; }
+
+Write `fun greet() { print \"hi\"; }` instead.",
+    ),
+    ("E0112", "A function can't have more than 255 parameters."),
+    (
+        "E0113",
+        "Parameters must be identifiers.
+
+    fun add(a, 1) { return a; }
+
+Use a name for every parameter.",
+    ),
+    (
+        "E0114",
+        "The parameters of a function were not followed by a `)`.
+
+    fun add(a b) { return a + b; }
+
+Separate parameters with commas and close the list with `)`.",
+    ),
+    (
+        "E0115",
+        "The body of a function must be a block.
+
+    fun one() return 1;
+
+Write `fun one() { return 1; }` instead.",
+    ),
+    (
+        "E0116",
+        "The condition of a `for` loop must be followed by a semicolon.
+
+    for (var i = 0; i < 3 i = i + 1) print i;
+
+Write `for (var i = 0; i < 3; i = i + 1) print i;` instead.",
+    ),
+    (
+        "E0117",
+        "A `return` statement must end with a semicolon.
+
+    return 1
+
+Write `return 1;` instead.",
+    ),
+    (
+        "E0118",
+        "A token was found where it can't appear.
+
+    print );
+
+Remove the token or complete the expression around it.",
+    ),
+    ("E0119", "The file ended in the middle of a statement."),
+    (
+        "E0120",
+        "Only variables and properties can be assigned to.
+
+    1 = 2;
+    a + b = c;
+
+Assign to a variable (`a = 2;`) or a property (`point.x = 2;`) instead. `++` and `--` need one
+too, `i++` works but `(i)++` and `++1` don't. Also reported when `var` is not followed by a
+variable name.",
+    ),
+    ("E0121", "A call can't pass more than 255 arguments."),
+    (
+        "E0122",
+        "A block was not closed before the end of the file.
+
+    {
+      print 1;
+
+Add the missing `}`.",
+    ),
+    (
+        "E0123",
+        "`class` must be followed by the name of the class.
+
+    class { }
+
+Give the class a name, e.g. `class Point { }`.",
+    ),
+    (
+        "E0124",
+        "The name of a class must be followed by its body.
+
+    class Point;
+
+Write `class Point {}` instead.",
+    ),
+    (
+        "E0125",
+        "Class bodies can only contain methods.
+
+    class Point {
+      var x = 1;
+    }
+
+Set fields in `init` instead: `init() { this.x = 1; }`.",
+    ),
+    (
+        "E0126",
+        "A class body was not closed before the end of the file.
+
+    class Point {
+      init() {}
+
+Add the missing `}`.",
+    ),
+    (
+        "E0127",
+        "A `.` must be followed by the name of a property.
+
+    print point.;
+
+Write `print point.x;` instead.",
+    ),
+    (
+        "E0128",
+        "`<` in a class declaration must be followed by the name of the superclass.
+
+    class Circle < {}
+
+Write `class Circle < Shape {}` instead.",
+    ),
+    (
+        "E0129",
+        "`super` can only be used to access a method of the superclass.
+
+    super();
+
+Write `super.init();` instead.",
+    ),
+    (
+        "E0130",
+        "`super.` must be followed by the name of a method.
+
+    super.();
+
+Write `super.init();` instead.",
+    ),
+    (
+        "E0131",
+        "Expressions or statements are nested deeper than the parser allows, e.g. thousands of
+parentheses inside each other. The limit keeps pathological input from crashing the
+interpreter. Split the expression up using variables.",
+    ),
+    (
+        "E0132",
+        "The script has more statements than the parser was configured to accept.",
+    ),
+    (
+        "E0133",
+        "A statement starts with two names in a row, which can't be an expression. This is usually a
+misspelled keyword or one from another language.
+
+    let a = 1;
+
+Declare variables with `var`.",
+    ),
+    (
+        "E0134",
+        "The parser found a token where it expected one of several others, which the error lists.
+
+    print max(1 2);
+
+Here a `,` or a `)` has to follow the first argument.",
+    ),
+    (
+        "E0135",
+        "A list literal is missing its closing `]`.
+
+    var list = [1, 2;
+
+Elements are separated by `,`, the last one can be followed by a `,` as well. Close the list with a
+`]`.",
+    ),
+    (
+        "E0136",
+        "An index expression is missing its closing `]`.
+
+    print list[0;
+
+Close the index with a `]`: `list[0]`.",
+    ),
+    (
+        "E0137",
+        "A key in a map literal isn't followed by a `:`.
+
+    var ages = {\"ada\" 36};
+
+Separate each key from its value with a `:`: `{\"ada\": 36}`.",
+    ),
+    (
+        "E0138",
+        "A map literal is missing its closing `}`.
+
+    var ages = {\"ada\": 36, \"alan\": 41;
+
+Entries are separated by `,`, the last one can be followed by a `,` as well. Close the map with a
+`}`.",
+    ),
+    (
+        "E0139",
+        "An expression interpolated into a string isn't followed by the `}` closing it.
+
+    print \"${first last}\";
+
+A `${` in a string starts an expression that runs until the matching `}`. Only one expression
+fits in it, join several with `+` or give each its own `${}`.",
+    ),
+    (
+        "E0200",
+        "The resolver lost track of its scopes. This is a bug in the interpreter, please report it
+together with the script that triggered it.",
+    ),
+    (
+        "E0201",
+        "A local variable can't be read in its own initializer.
+
+    var a = 1;
+    {
+      var a = a + 1;
+    }
+
+Use a different name for the inner variable.",
+    ),
+    (
+        "E0202",
+        "A local scope can't declare the same variable twice.
+
+    fun f() {
+      var a = 1;
+      var a = 2;
+    }
+
+Assign to the existing variable instead: `a = 2;`. Redeclaring globals is allowed, `--strict` \
+warns about it.",
+    ),
+    (
+        "E0203",
+        "`return` can only be used inside a function.
+
+    return 1;
+",
+    ),
+    (
+        "E0204",
+        "`this` can only be used inside the methods of a class.
+
+    fun f() { print this; }
+",
+    ),
+    (
+        "E0205",
+        "`init` always returns the instance, so it can't return a value.
+
+    class Point {
+      init() { return 1; }
+    }
+
+Use a bare `return;` to leave `init` early.",
+    ),
+    (
+        "E0206",
+        "A class can't inherit from itself.
+
+    class A < A {}
+",
+    ),
+    (
+        "E0207",
+        "`super` can only be used inside the methods of a class.
+
+    fun f() { super.init(); }
+",
+    ),
+    (
+        "E0208",
+        "`super` can only be used in a class that has a superclass.
+
+    class A {
+      init() { super.init(); }
+    }
+
+Declare the superclass with `class A < B`.",
+    ),
+    (
+        "E0209",
+        "Keywords like `this`, `class` or `nil` can't name a variable, parameter, function or class.
+
+    var this = 1;
+    fun f(class) {}
+
+Choose another name.",
+    ),
+    (
+        "E0210",
+        "`this` always refers to the instance a method was called on and can't be assigned.
+
+    class A {
+      reset() { this = A(); }
+    }
+
+Assign to fields of `this` instead, like `this.x = 0;`.",
+    ),
+    (
+        "E0211",
+        "`break` and `continue` can only be used inside a `while` or `for` loop.
+
+    fun stop() {
+      break;
+    }
+
+They also can't leave a function that is declared inside a loop, use `return` there instead.",
+    ),
+    (
+        "E0300",
+        "The interpreter reached an inconsistent state. This is a bug in the interpreter, please
+report it together with the script that triggered it.",
+    ),
+    (
+        "E0301",
+        "The operand of unary `-` must be a number.
+
+    print -\"a\";
+",
+    ),
+    (
+        "E0302",
+        "Both operands of `-`, `*`, `/`, `<`, `<=`, `>` and `>=` must be numbers.
+
+    print \"a\" * 2;
+",
+    ),
+    (
+        "E0303",
+        "`+` adds two numbers or concatenates two strings, but can't mix them.
+
+    print \"a\" + 1;
+",
+    ),
+    (
+        "E0304",
+        "A variable was used before it was declared.
+
+    print a;
+    var a = 1;
+
+Declare the variable first.",
+    ),
+    (
+        "E0305",
+        "Only functions and classes can be called.
+
+    var a = 1;
+    a();
+",
+    ),
+    (
+        "E0306",
+        "A function or class was called with the wrong number of arguments.
+
+    fun add(a, b) { return a + b; }
+    add(1);
+",
+    ),
+    (
+        "E0307",
+        "Printing failed because the output could not be written to, e.g. because stdout was
+closed.",
+    ),
+    (
+        "E0308",
+        "The program embedding the interpreter cancelled the script, e.g. because it ran for too
+long.",
+    ),
+    (
+        "E0309",
+        "Only numbers, strings, booleans, nil and instances whose class defines a `hash()` method
+can be used as map keys. Functions, lists, maps and other instances can't be hashed.
+
+    class Point {
+      init(x, y) { this.x = x; this.y = y; }
+      hash() { return this.x * 31 + this.y; }
+    }
+    var names = {};
+    names[Point(1, 2)] = \"a\";",
+    ),
+    (
+        "E0310",
+        "Only instances and, for their class methods, classes have properties.
+
+    var a = 1;
+    print a.x;
+",
+    ),
+    (
+        "E0311",
+        "An instance has neither a field nor a method with this name, or a class has no class
+method with this name.
+
+    class Point {}
+    print Point().x;
+
+Set the field before reading it, e.g. in `init`. Methods declared with a leading `class` are only
+found on the class, the others only on its instances.",
+    ),
+    (
+        "E0312",
+        "A class can only inherit from another class.
+
+    var a = 1;
+    class B < a {}
+",
+    ),
+    (
+        "E0313",
+        "An instance already has as many fields as `--max-fields` allows, so a new field can't be
+created. Assigning to existing fields still works.",
+    ),
+    (
+        "E0314",
+        "With `--strict-fields`, fields can only be created while the instance's `init` runs.
+Assigning to an unknown field anywhere else is most likely a typo.
+
+    class Person {
+      init(name) { this.name = name; }
+    }
+    var person = Person(\"Ada\");
+    person.nmae = \"Grace\";
+
+Fix the field name, or create the field in `init`.",
+    ),
+    (
+        "E0320",
+        "A native function was called with an argument of the wrong type.
+
+    print len(1);
+",
+    ),
+    (
+        "E0321",
+        "A string index is past the end of the string.
+
+    print char_at(\"abc\", 3);
+",
+    ),
+    (
+        "E0322",
+        "`to_json` can only convert numbers, strings, booleans, nil and instances. Functions,
+classes, NaN and infinities have no JSON representation.
+
+    print to_json(clock);
+",
+    ),
+    (
+        "E0323",
+        "`to_json` can't convert instances that contain themselves.
+
+    class Node {}
+    var node = Node();
+    node.next = node;
+    print to_json(node);
+",
+    ),
+    (
+        "E0324",
+        "`from_json` was given a string that is not valid JSON, or JSON it can't represent.",
+    ),
+    (
+        "E0325",
+        "A native function failed, e.g. because a network request could not be sent.",
+    ),
+    (
+        "E0326",
+        "An `assert_eq` or `assert_raises` in a test did not hold.
+
+    assert_eq(1 + 1, 3);
+",
+    ),
+    (
+        "E0330",
+        "A variable was looked up in an environment that is no longer available.
+
+Functions capture the environment they are declared in and keep using it after the block or call
+that created it has finished:
+
+    fun makeCounter() {
+      var i = 0;
+      fun count() { i = i + 1; return i; }
+      return count;
+    }
+
+Environments aren't owned by the closures that capture them yet. If one of them is gone when the
+closure runs, the closure can't reach its variables anymore and the error names the function that
+captured the environment. Please report the script, as a workaround move the captured variables to
+a scope that lives long enough, e.g. a global or a field of an instance.",
+    ),
+    (
+        "E0331",
+        "A variable declared without a value was read before anything was assigned to it. This is
+only an error with `--strict`, otherwise the variable is `nil`.
+
+    var a;
+    print a;
+
+Give the variable a value when declaring it, `var a = nil;` if `nil` is intended.",
+    ),
+    (
+        "E0340",
+        "A value other than a list or map was indexed with `[]`.
+
+    var name = \"lox\";
+    print name[0];
+
+Only lists and maps support `[]`. Use `char_at(name, 0)` for the characters of a string and `.` for the
+fields of an instance.",
+    ),
+    (
+        "E0341",
+        "A list was indexed with something other than a whole number.
+
+    var list = [1, 2];
+    print list[\"0\"];
+    print list[0.5];
+
+Indices are numbers without a fractional part, starting at `0`.",
+    ),
+    (
+        "E0342",
+        "A list index is negative or not smaller than the length of the list.
+
+    var list = [1, 2];
+    print list[2];
+
+Valid indices run from `0` to `len(list) - 1`. Use `push(list, value)` to add an element at the
+end.",
+    ),
+    (
+        "E0343",
+        "A map was indexed with a key it doesn't contain.
+
+    var ages = {\"ada\": 36};
+    print ages[\"alan\"];
+
+Check for the key with `has(map, key)` first. Assigning to a missing key adds it:
+`ages[\"alan\"] = 41;`.",
+    ),
+    (
+        "E0344",
+        "An instance was used as a map key, but its `hash()` method didn't return a number.
+
+    class Name {
+      init(name) { this.name = name; }
+      hash() { return this.name; }
+    }
+    var ages = {Name(\"ada\"): 36};
+
+Return a number computed from the fields that make two instances equal, like `len(this.name)`.",
+    ),
+    (
+        "E0400",
+        "The program uses something the bytecode backend (`--backend vm`) can't compile yet.
+
+The vm backend only supports expressions, variables, `print`, blocks, `if`, loops, `break` and
+`continue` so far. Functions, calls, classes and fields need the tree-walking backend, which is
+the default:
+
+    rust_lox --backend tree script.lox",
+    ),
+];
+
+/// Returns the long explanation for an error code like `E0109`.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_sorted() {
+        for pair in EXPLANATIONS.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} is out of order", pair[1].0);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert!(explain("E0109").unwrap().contains("semicolon"));
+        assert!(explain("e0109").is_some());
+        assert_eq!(explain("E9999"), None);
+    }
+}
@@ -1,4 +1,6 @@
-use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use smallvec::SmallVec;
 
 use crate::ast::StmtFunction;
 
@@ -10,25 +12,78 @@ use super::{
     Interpreter,
 };
 
+/// Where a call collects its arguments before passing them as a slice. Most calls pass a handful,
+/// those stay inline instead of allocating.
+pub type Arguments<'a> = SmallVec<[Value<'a>; 4]>;
+
+/// The number of arguments a callable accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// Inclusive on both ends
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => count == n,
+            Arity::AtLeast(min) => count >= min,
+            Arity::Between(min, max) => (min..=max).contains(&count),
+        }
+    }
+}
+
+impl Default for Arity {
+    fn default() -> Self {
+        Arity::Exact(0)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{n}"),
+            Arity::AtLeast(min) => write!(f, "at least {min}"),
+            Arity::Between(min, max) => write!(f, "{min} to {max}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
 pub enum CallType {
     Class,
     Function,
+    Native,
 }
 
+impl fmt::Display for CallType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallType::Class => write!(f, "class"),
+            CallType::Function => write!(f, "function"),
+            CallType::Native => write!(f, "native"),
+        }
+    }
+}
+
+/// Anything that can be called. Natives, including ones registered by embedders, implement it
+/// and are stored as `Callable::Native`.
 pub trait LoxCallable<'a>: 'a {
+    /// Callers check `arguments` against `arity` first, so natives can index them. Natives reach
+    /// the output, the clock, the random numbers and the interrupt flag through
+    /// `interpreter.context()`. The interpreter owns the context and natives like `assert_raises`
+    /// call back into it, so the context isn't passed as an argument of its own.
     fn call(
         &self,
         interpreter: &mut Interpreter<'a>,
-        arguments: VecDeque<Value<'a>>,
+        arguments: &[Value<'a>],
     ) -> Result<Value<'a>, RuntimeError<'a>>;
-    fn arity(&self) -> usize;
+    fn arity(&self) -> Arity;
+    /// The name used in error messages
+    fn name(&self) -> &str;
     fn to_string(&self) -> String;
-    fn call_type(&self) -> CallType {
-        CallType::Function
-    }
-    fn clone_as_class(&self) -> Option<Rc<LoxClass<'a>>> {
-        None
-    }
 }
 
 impl<'a> fmt::Debug for dyn LoxCallable<'a> {
@@ -37,6 +92,78 @@ impl<'a> fmt::Debug for dyn LoxCallable<'a> {
     }
 }
 
+/// A callable value. Functions and classes are dispatched with a match, only natives go through a
+/// trait object.
+#[derive(Debug, Clone)]
+pub enum Callable<'a> {
+    Function(Rc<LoxFunction<'a>>),
+    Class(Rc<LoxClass<'a>>),
+    Native(Rc<dyn LoxCallable<'a>>),
+}
+
+impl<'a> Callable<'a> {
+    pub fn native(native: impl LoxCallable<'a>) -> Self {
+        Callable::Native(Rc::new(native))
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        match self {
+            Callable::Function(function) => function.call(interpreter, arguments),
+            Callable::Class(class) => class.call(interpreter, arguments),
+            Callable::Native(native) => native.call(interpreter, arguments),
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        match self {
+            Callable::Function(function) => function.arity(),
+            Callable::Class(class) => class.arity(),
+            Callable::Native(native) => native.arity(),
+        }
+    }
+
+    pub fn call_type(&self) -> CallType {
+        match self {
+            Callable::Class(_) => CallType::Class,
+            Callable::Function(_) => CallType::Function,
+            Callable::Native(_) => CallType::Native,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Callable::Function(function) => function.name(),
+            Callable::Class(class) => class.name,
+            Callable::Native(native) => native.name(),
+        }
+    }
+
+    /// Whether both refer to the same function, class or native
+    pub fn ptr_eq(&self, other: &Callable<'a>) -> bool {
+        match (self, other) {
+            (Callable::Function(l), Callable::Function(r)) => Rc::ptr_eq(l, r),
+            (Callable::Class(l), Callable::Class(r)) => Rc::ptr_eq(l, r),
+            (Callable::Native(l), Callable::Native(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Callable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Callable::Function(function) => function.to_string(),
+            Callable::Class(class) => class.to_string(),
+            Callable::Native(native) => native.to_string(),
+        };
+        write!(f, "{name}")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LoxFunction<'a> {
     pub declaration: &'a StmtFunction<'a>,
@@ -70,6 +197,27 @@ impl<'a: 'b, 'b> LoxFunction<'a> {
         }
     }
 
+    /// The instance a method was bound to. Only valid for functions created by `bind`/`bind_rc`.
+    fn bound_this(&self) -> Result<Value<'a>, RuntimeError<'a>> {
+        // The closure of a bound method is never freed, other bound copies may still use it
+        let this = unsafe { (*self.closure).get_at(0, "this") };
+        this.flatten().ok_or(RuntimeError::EnvironmentUnavailable {
+            token: self.declaration.name,
+            function: Some(self.declaration.name),
+        })
+    }
+
+    /// Binds a class method, `this` in it is the class.
+    pub fn bind_class(&self, class: Rc<LoxClass<'a>>) -> Self {
+        let mut environment = Environment::new(Some(self.closure));
+        environment.define("this", Some(Value::Callable(Callable::Class(class))));
+        Self {
+            declaration: self.declaration,
+            closure: Box::into_raw(Box::new(environment)),
+            is_initializer: false,
+        }
+    }
+
     pub fn bind_rc(&self, instance: Rc<RefCell<LoxInstance<'a>>>) -> Self {
         let mut environment = Environment::new(Some(self.closure));
         environment.define("this", Some(Value::Instance(instance)));
@@ -85,8 +233,9 @@ impl<'a> LoxCallable<'a> for LoxFunction<'a> {
     fn call(
         &self,
         interpreter: &mut Interpreter<'a>,
-        mut arguments: VecDeque<Value<'a>>,
+        arguments: &[Value<'a>],
     ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let mut arguments = arguments.iter();
         let mut environment = Environment::new(Some(self.closure));
 
         for i in 0..self.declaration.params.len() {
@@ -97,42 +246,72 @@ impl<'a> LoxCallable<'a> for LoxFunction<'a> {
                 .ok_or(CallableError::ParamNotFound)?
                 .lexeme;
 
-            let argument = arguments.pop_front().ok_or(CallableError::InternalError)?;
+            let argument = arguments.next().ok_or(CallableError::InternalError)?;
 
-            environment.define(lexeme, Some(argument));
+            environment.define(lexeme, Some(argument.clone()));
         }
 
-        let res = match interpreter.execute_block(&self.declaration.body, environment) {
-            Ok(_) => Ok(Value::Nil),
-            Err(err) => match err {
-                RuntimeError::Return(value) => {
-                    if self.is_initializer {
-                        unsafe {
-                            let mut closure = Box::from_raw(self.closure);
-                            return Ok(closure.get_at(0, "this"));
-                        }
-                    }
-                    Ok(value.value)
-                }
-                _ => Err(err),
-            },
+        // An initializer always evaluates to its instance, also when `init` is invoked explicitly
+        let this = match self.is_initializer {
+            true => Some(self.bound_this()?),
+            false => None,
         };
 
-        if self.is_initializer {
-            unsafe {
-                let mut closure = Box::from_raw(self.closure);
-                return Ok(closure.get_at(0, "this"));
+        // Remember whose initializer is running, strict mode only allows creating fields there
+        let initializing = match &this {
+            Some(Value::Instance(instance)) => {
+                Some(Rc::as_ptr(&instance.borrow().fields) as *const ())
             }
+            _ => None,
+        };
+        if let Some(id) = initializing {
+            interpreter.initializing.push(id);
+        }
+
+        interpreter.functions.push(self.declaration.name);
+        let result = interpreter.execute_block(&self.declaration.body, environment);
+        interpreter.functions.pop();
+
+        if initializing.is_some() {
+            interpreter.initializing.pop();
         }
 
-        res
+        let value = match result {
+            Ok(_) => Value::Nil,
+            Err(RuntimeError::Return(value)) => value.value,
+            Err(err) => return Err(err),
+        };
+
+        Ok(this.unwrap_or(value))
     }
 
-    fn arity(&self) -> usize {
-        self.declaration.params.len()
+    fn arity(&self) -> Arity {
+        Arity::Exact(self.declaration.params.len())
+    }
+
+    fn name(&self) -> &str {
+        self.declaration.name.lexeme
     }
 
     fn to_string(&self) -> String {
         format!("<fn {}>", self.declaration.name.lexeme)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arity() {
+        assert!(Arity::Exact(2).accepts(2));
+        assert!(!Arity::Exact(2).accepts(3));
+        assert!(Arity::AtLeast(1).accepts(5));
+        assert!(!Arity::AtLeast(1).accepts(0));
+        assert!(Arity::Between(1, 3).accepts(3));
+        assert!(!Arity::Between(1, 3).accepts(4));
+
+        assert_eq!(Arity::AtLeast(1).to_string(), "at least 1");
+        assert_eq!(Arity::Between(1, 3).to_string(), "1 to 3");
+    }
+}
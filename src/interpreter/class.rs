@@ -1,11 +1,12 @@
-use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt, rc::Rc};
 
 use crate::lex::Token;
 
 use super::{
-    callable::{CallType, LoxCallable, LoxFunction},
+    callable::{Arity, Callable, LoxCallable, LoxFunction},
     error::{ClassError, RuntimeError},
     value::Value,
+    Interpreter,
 };
 
 #[derive(Debug, Clone)]
@@ -13,6 +14,8 @@ pub struct LoxClass<'a> {
     pub name: &'a str,
     pub superclass: Option<Rc<LoxClass<'a>>>,
     pub methods: HashMap<&'a str, LoxFunction<'a>>,
+    /// Declared with a leading `class`, looked up on the class instead of its instances
+    pub class_methods: HashMap<&'a str, LoxFunction<'a>>,
 }
 
 impl<'a> LoxClass<'a> {
@@ -25,6 +28,7 @@ impl<'a> LoxClass<'a> {
             name,
             methods,
             superclass,
+            class_methods: HashMap::new(),
         }
     }
 
@@ -39,14 +43,34 @@ impl<'a> LoxClass<'a> {
 
         None
     }
-}
 
-impl<'a> LoxCallable<'a> for LoxClass<'a> {
-    fn call(
-        &self,
-        interpreter: &mut super::Interpreter<'a>,
-        arguments: std::collections::VecDeque<super::value::Value<'a>>,
-    ) -> Result<super::value::Value<'a>, super::error::RuntimeError<'a>> {
+    pub fn find_class_method(&self, name: &str) -> Option<&LoxFunction<'a>> {
+        match self.class_methods.get(name) {
+            Some(method) => Some(method),
+            None => self.superclass.as_ref()?.find_class_method(name),
+        }
+    }
+
+    /// `Class.name`, a class method bound to this class. Inherited class methods are bound to it
+    /// too, not to the superclass that declared them.
+    pub fn get(self: &Rc<Self>, name: Token<'a>) -> Result<Value<'a>, RuntimeError<'a>> {
+        let method = self
+            .find_class_method(name.lexeme)
+            .ok_or(RuntimeError::ClassError(ClassError::UndefinedProperty {
+                token: name,
+            }))?;
+        Ok(Value::Callable(Callable::Function(Rc::new(
+            method.bind_class(self.clone()),
+        ))))
+    }
+
+    /// Creates an instance and runs `init` on it. Takes the `Rc` so instances share the class
+    /// instead of copying its method table.
+    pub fn call(
+        self: &Rc<Self>,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
         let instance_rc = Rc::new(RefCell::new(LoxInstance::new(self.clone())));
 
         if let Some(initializer) = self.find_method("init") {
@@ -58,35 +82,29 @@ impl<'a> LoxCallable<'a> for LoxClass<'a> {
         Ok(Value::Instance(instance_rc))
     }
 
-    fn arity(&self) -> usize {
+    pub fn arity(&self) -> Arity {
         let inizializer = self.find_method("init");
         if let Some(initializer) = inizializer {
             return initializer.arity();
         }
-        0
-    }
-
-    fn to_string(&self) -> String {
-        self.name.to_string()
-    }
-
-    fn call_type(&self) -> super::callable::CallType {
-        CallType::Class
+        Arity::Exact(0)
     }
+}
 
-    fn clone_as_class(&self) -> Option<Rc<LoxClass<'a>>> {
-        Some(Rc::new(self.clone()))
+impl fmt::Display for LoxClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
     }
 }
 
 #[derive(Clone)]
 pub struct LoxInstance<'a> {
-    pub class: LoxClass<'a>,
-    pub fields: Rc<RefCell<HashMap<&'a str, Value<'a>>>>,
+    pub class: Rc<LoxClass<'a>>,
+    pub fields: Rc<RefCell<HashMap<Cow<'a, str>, Value<'a>>>>,
 }
 
 impl<'a> LoxInstance<'a> {
-    pub fn new(class: LoxClass<'a>) -> Self {
+    pub fn new(class: Rc<LoxClass<'a>>) -> Self {
         Self {
             class,
             fields: Rc::new(RefCell::new(HashMap::new())),
@@ -100,8 +118,9 @@ impl<'a> LoxInstance<'a> {
 
         let method = self.class.find_method(name.lexeme);
         if let Some(method) = method {
-            // TODO: Clone
-            return Ok(Value::Callable(Rc::new(method.bind(self.clone()).clone())));
+            return Ok(Value::Callable(Callable::Function(Rc::new(
+                method.bind(self.clone()),
+            ))));
         }
 
         Err(RuntimeError::ClassError(ClassError::UndefinedProperty {
@@ -109,8 +128,18 @@ impl<'a> LoxInstance<'a> {
         }))
     }
 
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.borrow().contains_key(name)
+    }
+
+    pub fn field_count(&self) -> usize {
+        self.fields.borrow().len()
+    }
+
     pub fn set(&mut self, name: Token<'a>, value: Value<'a>) {
-        self.fields.borrow_mut().insert(name.lexeme, value);
+        self.fields
+            .borrow_mut()
+            .insert(Cow::Borrowed(name.lexeme), value);
     }
 }
 
@@ -0,0 +1,144 @@
+use rustc_hash::FxHashMap;
+
+use crate::{
+    ast::{Expr, LiteralValue, NodeId},
+    lex::{Token, TokenType},
+};
+
+use super::{error::RuntimeError, value::Value, Binding, Interpreter};
+
+/// A loop condition comparing variables and number literals, like `i < n`. Where its variables
+/// live is looked up once per loop, evaluating the condition through the visitor looks up the
+/// binding of each variable expression on every iteration.
+#[derive(Debug, PartialEq)]
+pub struct Condition<'a> {
+    left: Operand<'a>,
+    operator: TokenType,
+    right: Operand<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Operand<'a> {
+    Number(f64),
+    /// `None` if the variable wasn't resolved, it's looked up by name then
+    Variable(Token<'a>, Option<Binding>),
+}
+
+impl<'a> Condition<'a> {
+    /// `None` if `expr` isn't a comparison of variables and number literals.
+    pub fn compile(expr: &Expr<'a>, bindings: &FxHashMap<NodeId, Binding>) -> Option<Self> {
+        let Expr::Binary(binary) = expr else {
+            return None;
+        };
+        let operator = binary.operator.kind;
+        if !matches!(
+            operator,
+            TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual
+        ) {
+            return None;
+        }
+        Some(Self {
+            left: Operand::compile(binary.left, bindings)?,
+            operator,
+            right: Operand::compile(binary.right, bindings)?,
+        })
+    }
+
+    /// `None` if an operand isn't a number, evaluating the whole condition reports the error.
+    pub fn test(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+    ) -> Result<Option<bool>, RuntimeError<'a>> {
+        let (Value::Number(left), Value::Number(right)) = (
+            self.left.value(interpreter)?,
+            self.right.value(interpreter)?,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(match self.operator {
+            TokenType::Less => left < right,
+            TokenType::LessEqual => left <= right,
+            TokenType::Greater => left > right,
+            _ => left >= right,
+        }))
+    }
+}
+
+impl<'a> Operand<'a> {
+    fn compile(expr: &Expr<'a>, bindings: &FxHashMap<NodeId, Binding>) -> Option<Self> {
+        match expr {
+            Expr::Literal(literal) => match literal.value {
+                LiteralValue::F64(n) => Some(Operand::Number(n)),
+                _ => None,
+            },
+            Expr::Variable(variable) => Some(Operand::Variable(
+                variable.name,
+                bindings.get(&NodeId::of(variable)).copied(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// An undefined variable is an error like when it's evaluated, an uninitialized one is nil.
+    fn value(&self, interpreter: &mut Interpreter<'a>) -> Result<Value<'a>, RuntimeError<'a>> {
+        let value = match *self {
+            Operand::Number(n) => return Ok(Value::Number(n)),
+            Operand::Variable(name, Some(Binding::Local(distance))) => {
+                interpreter.get_at(distance, name)?
+            }
+            Operand::Variable(name, Some(Binding::Global(slot))) => {
+                interpreter.globals.get_slot(slot, name)?
+            }
+            Operand::Variable(name, None) => interpreter.globals.get(name)?,
+        };
+        Ok(value.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::{Arena, Stmt},
+        interpreter::resolver::Resolver,
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+    };
+
+    use super::*;
+
+    /// Whether the condition of the last `while` loop in `source` compiles, the loop may be the
+    /// last statement of a block.
+    fn compiles(source: &str) -> bool {
+        let arena = Arena::new();
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts).unwrap();
+        let bindings = resolver
+            .analysis()
+            .locals
+            .into_iter()
+            .map(|(expr, distance)| (expr, Binding::Local(distance)))
+            .collect();
+        let stmt = match stmts.last() {
+            Some(Stmt::Block(block)) => block.statements.last(),
+            stmt => stmt,
+        };
+        let Some(Stmt::While(stmt)) = stmt else {
+            panic!("expected a while loop last");
+        };
+        Condition::compile(&stmt.condition, &bindings).is_some()
+    }
+
+    #[test]
+    fn compile() {
+        assert!(compiles("var i = 0; while (i < 10) {}"));
+        assert!(compiles("var i = 0; var n = 1; while (n >= i) {}"));
+        assert!(compiles("{ var i = 0; while (1 > i) {} }"));
+        assert!(!compiles("var i = 0; while (i == 10) {}"));
+        assert!(!compiles("var i = 0; while (i + 1 < 10) {}"));
+        assert!(!compiles("var i = 0; while (i < \"10\") {}"));
+    }
+}
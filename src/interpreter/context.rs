@@ -0,0 +1,113 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::error::RuntimeError;
+
+/// Everything from the outside world a script can reach: the output, the time, randomness and the
+/// interrupt flag. Natives get these from the interpreter's context instead of going to the OS
+/// themselves, so hosts can capture output, fake time or seed randomness, and so long-running
+/// natives notice when the script is cancelled.
+pub struct NativeContext<'a> {
+    out: Box<dyn Write + 'a>,
+    interrupt: Arc<AtomicBool>,
+    clock: Box<dyn Fn() -> Duration + 'a>,
+    rng_state: u64,
+}
+
+impl<'a> NativeContext<'a> {
+    pub fn new(out: Box<dyn Write + 'a>) -> Self {
+        let clock = || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+        };
+        // The seed only needs to differ between runs, xorshift just can't start at zero
+        let seed = clock().as_nanos() as u64 | 1;
+
+        Self {
+            out,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            clock: Box::new(clock),
+            rng_state: seed,
+        }
+    }
+
+    pub fn out(&mut self) -> &mut dyn Write {
+        &mut self.out
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write + 'a>) {
+        self.out = out;
+    }
+
+    /// A handle that interrupts the script when set to `true`, also from another thread.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    /// Fails once the script was interrupted. Natives that block or loop should call this between
+    /// steps.
+    pub fn check_interrupt<'e>(&self) -> Result<(), RuntimeError<'e>> {
+        match self.interrupt.load(Ordering::Relaxed) {
+            true => Err(RuntimeError::Interrupted),
+            false => Ok(()),
+        }
+    }
+
+    /// Time since the unix epoch.
+    pub fn now(&self) -> Duration {
+        (self.clock)()
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Fn() -> Duration + 'a>) {
+        self.clock = clock;
+    }
+
+    /// A pseudo-random number in `[0, 1)`. Not suitable for cryptography.
+    pub fn random(&mut self) -> f64 {
+        // xorshift64*
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (bits >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.rng_state = seed.max(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_random() {
+        let mut a = NativeContext::new(Box::new(std::io::sink()));
+        let mut b = NativeContext::new(Box::new(std::io::sink()));
+        a.seed(42);
+        b.seed(42);
+
+        for _ in 0..100 {
+            let n = a.random();
+            assert_eq!(n, b.random());
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+
+    #[test]
+    fn interrupt() {
+        let context = NativeContext::new(Box::new(std::io::sink()));
+        assert_eq!(context.check_interrupt(), Ok(()));
+
+        context.interrupt_handle().store(true, Ordering::Relaxed);
+        assert_eq!(context.check_interrupt(), Err(RuntimeError::Interrupted));
+    }
+}
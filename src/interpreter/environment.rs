@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::lex::Token;
 
-use super::{error::RuntimeError, Value};
+use super::Value;
 
 #[derive(Clone, Debug)]
 pub struct Environment<'a> {
@@ -26,53 +26,40 @@ impl<'a> Environment<'a> {
         }
     }
 
-    pub fn get(&self, name: Token<'a>) -> Result<Value<'a>, RuntimeError<'a>> {
-        match self.values.get(name.lexeme) {
-            Some(value) => match value {
-                Some(value) => Ok(value.clone()),
-                None => Ok(Value::Nil),
-            },
-            None => match self.enclosing {
-                Some(enclosing) => unsafe { return (*enclosing).get(name) },
-                _ => Err(RuntimeError::UndefinedVariable { name }),
-            },
-        }
-    }
-
-    pub fn get_at(&mut self, distance: usize, name: &'a str) -> Value<'a> {
-        // The Resolver already found the variable before, so we know it's there
-        self.ancestor(distance)
-            .values
-            .get(name)
-            .expect("AAAA")
-            .clone()
-            .expect("BBBB")
+    /// `None` if the environment the Resolver found the variable in isn't reachable anymore,
+    /// `Some(None)` if the variable was never assigned.
+    pub fn get_at(&mut self, distance: usize, name: &'a str) -> Option<Option<Value<'a>>> {
+        self.ancestor(distance)?.values.get(name).cloned()
     }
 
-    pub fn assign_at(&mut self, distance: usize, name: Token<'a>, value: Value<'a>) {
-        self.ancestor(distance).define(name.lexeme, Some(value));
+    pub fn assign_at(&mut self, distance: usize, name: Token<'a>, value: Value<'a>) -> Option<()> {
+        self.ancestor(distance)?.define(name.lexeme, Some(value));
+        Some(())
     }
 
-    fn ancestor(&mut self, distance: usize) -> &mut Environment<'a> {
+    fn ancestor(&mut self, distance: usize) -> Option<&mut Environment<'a>> {
         let mut environment = self;
         for _ in 0..distance {
-            environment = unsafe { &mut (*environment.enclosing.unwrap()) };
+            environment = unsafe { &mut (*environment.enclosing?) };
         }
-        environment
+        Some(environment)
     }
+}
 
-    pub fn assign(&mut self, name: Token<'a>, value: Value<'a>) -> Result<(), RuntimeError<'a>> {
-        match self.values.contains_key(name.lexeme) {
-            true => {
-                self.values
-                    .entry(name.lexeme)
-                    .and_modify(|v| *v = Some(value));
-                Ok(())
-            }
-            false => match self.enclosing {
-                Some(enclosing) => unsafe { (*enclosing).assign(name, value) },
-                _ => Err(RuntimeError::UndefinedVariable { name }),
-            },
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get_at() {
+        let mut outer = Environment::new(None);
+        outer.define("a", Some(Value::Number(1.0)));
+        outer.define("b", None);
+        let mut inner = Environment::new(Some(&mut outer as *mut Environment));
+
+        assert_eq!(inner.get_at(1, "a"), Some(Some(Value::Number(1.0))));
+        assert_eq!(inner.get_at(1, "b"), Some(None));
+        assert_eq!(inner.get_at(0, "a"), None);
+        assert_eq!(inner.get_at(2, "a"), None);
     }
 }
@@ -2,38 +2,107 @@ use thiserror::Error;
 
 use crate::lex::Token;
 
-use super::value::Value;
+use super::{
+    callable::{Arity, CallType},
+    value::Value,
+};
 
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
 pub enum RuntimeError<'a> {
-    #[error("[line {}] Operand must be a number.", operator.line)]
-    NumberOperand { operator: Token<'a> },
+    /// `operand` is the operand as described by `Value::describe`
+    #[error("[line {}] Operand must be a number, got {}.", operator.line, operand)]
+    NumberOperand {
+        operator: Token<'a>,
+        operand: String,
+    },
 
-    #[error("[line {}] Operands must be a number.", operator.line)]
-    MutlipleNumberOperands { operator: Token<'a> },
+    #[error("[line {}] Operands must be a number, got {} and {}.", operator.line, left, right)]
+    MutlipleNumberOperands {
+        operator: Token<'a>,
+        left: String,
+        right: String,
+    },
 
-    #[error("[line {}] Operands must be two numbers or two strings.", operator.line)]
-    NumberOrStringOperands { operator: Token<'a> },
+    #[error("[line {}] Operands must be two numbers or two strings, got {} and {}.", operator.line, left, right)]
+    NumberOrStringOperands {
+        operator: Token<'a>,
+        left: String,
+        right: String,
+    },
 
     #[error("[line {}] Undefined Variable '{}'.", name.line, name.lexeme)]
     UndefinedVariable { name: Token<'a> },
 
+    #[error("[line {}] Variable '{}' used before assignment.", name.line, name.lexeme)]
+    UninitializedVariable { name: Token<'a> },
+
     #[error("[line {}] Can only call functions and classes.", token.line)]
     NotCallable { token: Token<'a> },
 
-    #[error("[line {}] Expected {} arguments but got {}.", token.line, expected_arity, given_len)]
+    #[error("[line {}] {} '{}' {}", token.line, kind, name, arity_mismatch(expected_arity, *given_len))]
     ArgumentCount {
         token: Token<'a>,
-        expected_arity: usize,
+        kind: CallType,
+        name: String,
+        expected_arity: Arity,
         given_len: usize,
     },
 
-    #[error("Internal Error: Error while creating environments.")]
-    EnvironmentCreationError,
+    /// `function` is the declaration of the innermost running function, `None` at the top level
+    #[error("[line {}] Can't access '{}', {}", token.line, token.lexeme, environment_unavailable(function))]
+    EnvironmentUnavailable {
+        token: Token<'a>,
+        function: Option<Token<'a>>,
+    },
+
+    #[error("Internal Error: Failed to write output.")]
+    OutputError,
+
+    #[error("Script was interrupted.")]
+    Interrupted,
+
+    #[error("[line {}] Can't use {} as a map key.", token.line, unhashable(type_name))]
+    Unhashable {
+        token: Token<'a>,
+        type_name: &'static str,
+    },
+
+    /// `token` is the name of the `hash` method, `value` is what it returned as described by
+    /// `Value::describe`
+    #[error("[line {}] hash() must return a number, got {}.", token.line, value)]
+    InvalidHash { token: Token<'a>, value: String },
+
+    /// `value` is the indexed value as described by `Value::describe`
+    #[error("[line {}] Only lists and maps can be indexed, got {}.", bracket.line, value)]
+    NotIndexable { bracket: Token<'a>, value: String },
+
+    #[error("[line {}] List index must be a whole number, got {}.", bracket.line, index)]
+    InvalidIndex { bracket: Token<'a>, index: String },
+
+    #[error("[line {}] Index {} is out of bounds for a list of length {}.", bracket.line, index, len)]
+    IndexOutOfBounds {
+        bracket: Token<'a>,
+        index: f64,
+        len: usize,
+    },
+
+    /// `key` is the key as described by `Value::describe`
+    #[error("[line {}] The map has no entry for {}.", bracket.line, key)]
+    MissingKey { bracket: Token<'a>, key: String },
 
     #[error("{0}")]
     CallableError(#[from] CallableError),
 
+    #[error("{0}")]
+    NativeError(#[from] NativeError),
+
+    /// A `NativeError` located at the call that caused it
+    #[error("[line {}] {}", token.line, error)]
+    NativeCall {
+        token: Token<'a>,
+        error: NativeError,
+    },
+
     #[error("{0}")]
     ClassError(ClassError<'a>),
 
@@ -43,6 +112,39 @@ pub enum RuntimeError<'a> {
     // Not an actual Error, but rather a special type to unwind the interpreter to the call method of LoxCallable when a value is returned
     #[error("Internal Error: Unhandled return.")]
     Return(Return<'a>),
+
+    // Unwind the interpreter to the innermost loop, like `Return`
+    #[error("Internal Error: Unhandled break.")]
+    Break,
+
+    #[error("Internal Error: Unhandled continue.")]
+    Continue,
+}
+
+fn arity_mismatch(expected: &Arity, given: usize) -> String {
+    let plural = match *expected {
+        Arity::Exact(0) => return format!("takes no arguments but got {given}."),
+        Arity::Exact(n) | Arity::AtLeast(n) | Arity::Between(_, n) => n != 1,
+    };
+    let arguments = if plural { "arguments" } else { "argument" };
+    format!("expects {expected} {arguments} but got {given}.")
+}
+
+fn unhashable(type_name: &str) -> String {
+    match type_name {
+        "instance" => "an instance without a hash() method".to_string(),
+        type_name => format!("a {type_name}"),
+    }
+}
+
+fn environment_unavailable(function: &Option<Token>) -> String {
+    match function {
+        Some(function) => format!(
+            "the environment captured by '{}' on line {} is no longer available.",
+            function.lexeme, function.line
+        ),
+        None => "its environment is no longer available.".to_string(),
+    }
 }
 
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
@@ -54,6 +156,41 @@ pub enum CallableError {
     ParamNotFound,
 }
 
+#[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
+pub enum NativeError {
+    #[error("{native}: expected {expected} argument but got {got}.")]
+    InvalidArgument {
+        native: &'static str,
+        expected: &'static str,
+        got: String,
+    },
+
+    #[error("{native}: index {index} is out of bounds for a string of length {len}.")]
+    IndexOutOfBounds {
+        native: &'static str,
+        index: usize,
+        len: usize,
+    },
+
+    #[error("to_json: can't convert {0} to JSON.")]
+    NotSerializable(String),
+
+    #[error("to_json: can't convert a cyclic structure to JSON.")]
+    CyclicStructure,
+
+    #[error("from_json: {0}.")]
+    InvalidJson(String),
+
+    #[error("{native}: {message}.")]
+    Failed {
+        native: &'static str,
+        message: String,
+    },
+
+    #[error("{0}.")]
+    AssertionFailed(String),
+}
+
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
 pub enum ClassError<'a> {
     #[error("[line {}] Only instances have properties.", token.line)]
@@ -64,6 +201,12 @@ pub enum ClassError<'a> {
 
     #[error("[line {}] Superclass must be a class.", token.line)]
     SuperclassNotAClass { token: Token<'a> },
+
+    #[error("[line {}] Can't create field '{}', instances can't have more than {} fields.", token.line, token.lexeme, max)]
+    TooManyFields { token: Token<'a>, max: usize },
+
+    #[error("[line {}] Can't create field '{}' outside of 'init' in strict mode.", token.line, token.lexeme)]
+    UndeclaredField { token: Token<'a> },
 }
 
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
@@ -94,9 +237,157 @@ pub enum ResolverError<'a> {
 
     #[error("[line {}] Can't use 'super' in a class with no superclass.", token.line)]
     SuperInClassWithoutSuperclass { token: Token<'a> },
+
+    #[error("[line {}] Can't use the reserved word '{}' as a name.", token.line, token.lexeme)]
+    ReservedName { token: Token<'a> },
+
+    #[error("[line {}] Can't assign to 'this'.", token.line)]
+    AssignToThis { token: Token<'a> },
+
+    /// `token` is the `break` or `continue`
+    #[error("[line {}] Can't use '{}' outside of a loop.", token.line, token.lexeme)]
+    LoopControlOutsideLoop { token: Token<'a> },
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct Return<'a> {
     pub value: Value<'a>,
 }
+
+impl<'a> RuntimeError<'a> {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::NumberOperand { .. } => "E0301",
+            RuntimeError::MutlipleNumberOperands { .. } => "E0302",
+            RuntimeError::NumberOrStringOperands { .. } => "E0303",
+            RuntimeError::UndefinedVariable { .. } => "E0304",
+            RuntimeError::NotCallable { .. } => "E0305",
+            RuntimeError::ArgumentCount { .. } => "E0306",
+            RuntimeError::OutputError => "E0307",
+            RuntimeError::Interrupted => "E0308",
+            RuntimeError::Unhashable { .. } => "E0309",
+            RuntimeError::EnvironmentUnavailable { .. } => "E0330",
+            RuntimeError::UninitializedVariable { .. } => "E0331",
+            RuntimeError::NotIndexable { .. } => "E0340",
+            RuntimeError::InvalidIndex { .. } => "E0341",
+            RuntimeError::IndexOutOfBounds { .. } => "E0342",
+            RuntimeError::MissingKey { .. } => "E0343",
+            RuntimeError::InvalidHash { .. } => "E0344",
+            RuntimeError::ClassError(e) => e.code(),
+            RuntimeError::NativeError(e) => e.code(),
+            RuntimeError::NativeCall { error, .. } => error.code(),
+            RuntimeError::ResolverError(e) => e.code(),
+            RuntimeError::CallableError(_)
+            | RuntimeError::Return(_)
+            | RuntimeError::Break
+            | RuntimeError::Continue => "E0300",
+        }
+    }
+
+    /// The token the error was found at, `None` for errors that aren't tied to a place in the
+    /// source, like a failed write.
+    pub fn token(&self) -> Option<Token<'a>> {
+        match self {
+            RuntimeError::NumberOperand { operator, .. }
+            | RuntimeError::MutlipleNumberOperands { operator, .. }
+            | RuntimeError::NumberOrStringOperands { operator, .. } => Some(*operator),
+            RuntimeError::UndefinedVariable { name }
+            | RuntimeError::UninitializedVariable { name } => Some(*name),
+            RuntimeError::NotCallable { token }
+            | RuntimeError::ArgumentCount { token, .. }
+            | RuntimeError::EnvironmentUnavailable { token, .. }
+            | RuntimeError::Unhashable { token, .. }
+            | RuntimeError::InvalidHash { token, .. }
+            | RuntimeError::NativeCall { token, .. } => Some(*token),
+            RuntimeError::NotIndexable { bracket, .. }
+            | RuntimeError::InvalidIndex { bracket, .. }
+            | RuntimeError::IndexOutOfBounds { bracket, .. }
+            | RuntimeError::MissingKey { bracket, .. } => Some(*bracket),
+            RuntimeError::ClassError(e) => Some(e.token()),
+            RuntimeError::ResolverError(e) => e.token(),
+            RuntimeError::OutputError
+            | RuntimeError::Interrupted
+            | RuntimeError::CallableError(_)
+            | RuntimeError::NativeError(_)
+            | RuntimeError::Return(_)
+            | RuntimeError::Break
+            | RuntimeError::Continue => None,
+        }
+    }
+}
+
+impl<'a> ClassError<'a> {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClassError::InvalidPropertyAccess { .. } => "E0310",
+            ClassError::UndefinedProperty { .. } => "E0311",
+            ClassError::SuperclassNotAClass { .. } => "E0312",
+            ClassError::TooManyFields { .. } => "E0313",
+            ClassError::UndeclaredField { .. } => "E0314",
+        }
+    }
+
+    /// The token the error was found at.
+    pub fn token(&self) -> Token<'a> {
+        match self {
+            ClassError::InvalidPropertyAccess { token, .. }
+            | ClassError::UndefinedProperty { token, .. }
+            | ClassError::SuperclassNotAClass { token, .. }
+            | ClassError::TooManyFields { token, .. }
+            | ClassError::UndeclaredField { token, .. } => *token,
+        }
+    }
+}
+
+impl NativeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NativeError::InvalidArgument { .. } => "E0320",
+            NativeError::IndexOutOfBounds { .. } => "E0321",
+            NativeError::NotSerializable(_) => "E0322",
+            NativeError::CyclicStructure => "E0323",
+            NativeError::InvalidJson(_) => "E0324",
+            NativeError::Failed { .. } => "E0325",
+            NativeError::AssertionFailed(_) => "E0326",
+        }
+    }
+}
+
+impl<'a> ResolverError<'a> {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResolverError::InternalResolverError => "E0200",
+            ResolverError::VariableInOwnInitializer { .. } => "E0201",
+            ResolverError::SameNameVariableInLocalScope { .. } => "E0202",
+            ResolverError::TopLevelReturn { .. } => "E0203",
+            ResolverError::ThisOutsideClass { .. } => "E0204",
+            ResolverError::ReturnInConstructor { .. } => "E0205",
+            ResolverError::InheritanceCycle { .. } => "E0206",
+            ResolverError::SuperOutsideClass { .. } => "E0207",
+            ResolverError::SuperInClassWithoutSuperclass { .. } => "E0208",
+            ResolverError::ReservedName { .. } => "E0209",
+            ResolverError::AssignToThis { .. } => "E0210",
+            ResolverError::LoopControlOutsideLoop { .. } => "E0211",
+        }
+    }
+
+    /// The token the error was found at.
+    pub fn token(&self) -> Option<Token<'a>> {
+        match self {
+            ResolverError::VariableInOwnInitializer { token }
+            | ResolverError::SameNameVariableInLocalScope { token }
+            | ResolverError::TopLevelReturn { token }
+            | ResolverError::ThisOutsideClass { token }
+            | ResolverError::ReturnInConstructor { token }
+            | ResolverError::InheritanceCycle { token }
+            | ResolverError::SuperOutsideClass { token }
+            | ResolverError::SuperInClassWithoutSuperclass { token }
+            | ResolverError::ReservedName { token }
+            | ResolverError::AssignToThis { token }
+            | ResolverError::LoopControlOutsideLoop { token } => Some(*token),
+            ResolverError::InternalResolverError => None,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::lex::Token;
+
+use super::{error::RuntimeError, Value};
+
+/// The global variables. Each name gets a slot the first time it is defined or the interpreter
+/// learns from an analysis that an expression refers to it, resolved expressions then reach the
+/// value by index instead of hashing the name. Natives and definitions the resolver didn't see
+/// still work by name.
+#[derive(Debug, Default)]
+pub struct Globals<'a> {
+    slots: HashMap<&'a str, usize>,
+    /// `None` if the variable isn't defined (yet), `Some(None)` if it was never assigned
+    values: Vec<Option<Option<Value<'a>>>>,
+    names: Vec<&'a str>,
+}
+
+impl<'a> Globals<'a> {
+    /// The slot of `name`, a new one if the name has none yet.
+    pub fn slot(&mut self, name: &'a str) -> usize {
+        *self.slots.entry(name).or_insert_with(|| {
+            self.values.push(None);
+            self.names.push(name);
+            self.values.len() - 1
+        })
+    }
+
+    pub fn define(&mut self, name: &'a str, value: Option<Value<'a>>) {
+        let slot = self.slot(name);
+        self.values[slot] = Some(value);
+    }
+
+    /// `None` if the variable was declared without a value and never assigned.
+    pub fn get(&self, name: Token<'a>) -> Result<Option<Value<'a>>, RuntimeError<'a>> {
+        match self.slots.get(name.lexeme) {
+            Some(&slot) => self.get_slot(slot, name),
+            None => Err(RuntimeError::UndefinedVariable { name }),
+        }
+    }
+
+    /// Like `get`, `slot` has to be the one of `name`.
+    pub fn get_slot(
+        &self,
+        slot: usize,
+        name: Token<'a>,
+    ) -> Result<Option<Value<'a>>, RuntimeError<'a>> {
+        match &self.values[slot] {
+            Some(value) => Ok(value.clone()),
+            None => Err(RuntimeError::UndefinedVariable { name }),
+        }
+    }
+
+    pub fn assign(&mut self, name: Token<'a>, value: Value<'a>) -> Result<(), RuntimeError<'a>> {
+        match self.slots.get(name.lexeme) {
+            Some(&slot) => self.assign_slot(slot, name, value),
+            None => Err(RuntimeError::UndefinedVariable { name }),
+        }
+    }
+
+    /// Like `assign`, `slot` has to be the one of `name`.
+    pub fn assign_slot(
+        &mut self,
+        slot: usize,
+        name: Token<'a>,
+        value: Value<'a>,
+    ) -> Result<(), RuntimeError<'a>> {
+        match &mut self.values[slot] {
+            Some(old) => {
+                *old = Some(value);
+                Ok(())
+            }
+            None => Err(RuntimeError::UndefinedVariable { name }),
+        }
+    }
+
+    /// The value of a defined global, nil if it was never assigned.
+    pub fn lookup(&self, name: &str) -> Option<Value<'a>> {
+        let value = self.values[*self.slots.get(name)?].clone()?;
+        Some(value.unwrap_or_default())
+    }
+
+    /// The defined globals sorted by name. The value is `None` if the variable was never assigned.
+    pub fn bindings(&self) -> Vec<(&'a str, Option<Value<'a>>)> {
+        let mut bindings: Vec<_> = self
+            .names
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(&name, value)| Some((name, value.clone()?)))
+            .collect();
+        bindings.sort_by_key(|&(name, _)| name);
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lex::{Span, TokenType};
+
+    use super::*;
+
+    fn token(name: &str) -> Token<'_> {
+        Token::new(TokenType::Ident, name, 1, Span { begin: 0, end: 0 })
+    }
+
+    #[test]
+    fn slots() {
+        let mut globals = Globals::default();
+        // Referenced before it's defined, like a global a function uses
+        let a = globals.slot("a");
+        assert!(matches!(
+            globals.get_slot(a, token("a")),
+            Err(RuntimeError::UndefinedVariable { .. })
+        ));
+        assert_eq!(globals.bindings(), []);
+
+        globals.define("b", None);
+        globals.define("a", Some(Value::Number(1.0)));
+        assert_eq!(globals.slot("a"), a);
+        assert_eq!(
+            globals.get_slot(a, token("a")).unwrap(),
+            Some(Value::Number(1.0))
+        );
+        assert_eq!(globals.get(token("b")).unwrap(), None);
+
+        globals.assign(token("b"), Value::Boolean(true)).unwrap();
+        assert_eq!(globals.lookup("b"), Some(Value::Boolean(true)));
+        assert_eq!(
+            globals.bindings(),
+            [
+                ("a", Some(Value::Number(1.0))),
+                ("b", Some(Value::Boolean(true)))
+            ]
+        );
+        assert!(globals.assign(token("c"), Value::Nil).is_err());
+    }
+}
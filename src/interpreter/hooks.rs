@@ -0,0 +1,148 @@
+use std::io::{self, Write};
+
+use crate::ast::{printer::AstPrinter, Stmt};
+
+use super::{callable::Callable, error::RuntimeError, options::Options, value::Value};
+
+/// Callbacks the interpreter invokes while it runs, so tracers, profilers, debuggers or coverage
+/// tools can observe a script without touching the interpreter loop. Every callback does nothing
+/// by default, implementors only override what they need.
+pub trait Hooks<'a> {
+    /// Called before each statement is executed.
+    fn on_statement(&mut self, _stmt: &Stmt<'a>) {}
+
+    /// Called before a function, method, class or native is called.
+    fn on_call(&mut self, _callee: &Callable<'a>, _arguments: &[Value<'a>]) {}
+
+    /// Called after a call returned successfully.
+    fn on_return(&mut self, _callee: &Callable<'a>, _value: &Value<'a>) {}
+
+    /// Called once when a runtime error aborts `Interpreter::interpret`.
+    fn on_error(&mut self, _error: &RuntimeError<'a>) {}
+}
+
+/// Logs each statement to stderr before it is executed, see `--trace`. Long statements are cut
+/// off like printed values.
+pub struct Trace<'w> {
+    out: Box<dyn Write + 'w>,
+    options: Options,
+}
+
+impl<'w> Trace<'w> {
+    pub fn new(options: Options) -> Self {
+        Self::with_output(Box::new(io::stderr()), options)
+    }
+
+    pub fn with_output(out: Box<dyn Write + 'w>, options: Options) -> Self {
+        Self { out, options }
+    }
+}
+
+impl<'a> Hooks<'a> for Trace<'_> {
+    fn on_statement(&mut self, stmt: &Stmt<'a>) {
+        let text = self.options.truncate(stmt.accept(&mut AstPrinter));
+        // Like eprintln!, a trace that can't be written doesn't stop the script
+        let _ = writeln!(self.out, "[trace] {text}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        ast::Arena,
+        interpreter::{resolver::Resolver, Interpreter},
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+    };
+
+    use super::*;
+
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl<'a> Hooks<'a> for Recorder {
+        fn on_statement(&mut self, stmt: &Stmt<'a>) {
+            let kind = match stmt {
+                Stmt::Function(_) => "function",
+                Stmt::Print(_) => "print",
+                Stmt::Return(_) => "return",
+                _ => "other",
+            };
+            self.0.borrow_mut().push(format!("statement {kind}"));
+        }
+
+        fn on_call(&mut self, callee: &Callable<'a>, arguments: &[Value<'a>]) {
+            self.0
+                .borrow_mut()
+                .push(format!("call {callee} {arguments:?}"));
+        }
+
+        fn on_return(&mut self, callee: &Callable<'a>, value: &Value<'a>) {
+            self.0.borrow_mut().push(format!("return {callee} {value}"));
+        }
+
+        fn on_error(&mut self, error: &RuntimeError<'a>) {
+            self.0.borrow_mut().push(format!("error {error}"));
+        }
+    }
+
+    #[test]
+    fn hooks_are_called() {
+        let input = "fun inc(a) { return a + 1; }
+print inc(1);
+print nil + 1;";
+        let arena = Arena::new();
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts).unwrap();
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut interpreter =
+            Interpreter::with_output(resolver.analysis(), Box::new(std::io::sink()));
+        interpreter.set_hooks(Box::new(Recorder(events.clone())));
+        assert!(interpreter.interpret(&stmts).is_err());
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "statement function",
+                "statement print",
+                "call <fn inc> [Number(1.0)]",
+                "statement return",
+                "return <fn inc> 2",
+                "statement print",
+                "error [line 3] Operands must be two numbers or two strings, got nil and number 1.",
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_truncates() {
+        let input = "print \"a long string\";\nprint 1;";
+        let arena = Arena::new();
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let options = Options {
+            max_print_len: Some(12),
+            ..Default::default()
+        };
+
+        let mut output = vec![];
+        let mut interpreter =
+            Interpreter::with_output(Default::default(), Box::new(std::io::sink()));
+        interpreter.set_hooks(Box::new(Trace::with_output(Box::new(&mut output), options)));
+        interpreter.interpret(&stmts).unwrap();
+        drop(interpreter);
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[trace] (print a lon... (9 more characters)\n[trace] (print 1)\n"
+        );
+    }
+}
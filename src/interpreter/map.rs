@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use super::value::{HashableValue, Value};
+
+/// The entries of a map value in insertion order. `index` maps each key to its position in
+/// `entries`, so lookups don't scan the entries. `by_hash` groups the instance keys by the hash
+/// their `hash()` returned, an instance that is `equals()` to one of them is looked up as that key.
+#[derive(Debug, Clone, Default)]
+pub struct LoxMap<'a> {
+    entries: Vec<(HashableValue<'a>, Value<'a>)>,
+    index: HashMap<HashableValue<'a>, usize>,
+    by_hash: HashMap<u64, Vec<HashableValue<'a>>>,
+}
+
+impl<'a> LoxMap<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &HashableValue<'a>) -> Option<&Value<'a>> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &HashableValue<'a>) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Replacing the value of a key keeps its position.
+    pub fn insert(&mut self, key: HashableValue<'a>, value: Value<'a>) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                if let HashableValue::Instance { hash, .. } = key {
+                    self.by_hash.entry(hash).or_default().push(key.clone());
+                }
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    /// Removes the entry and returns its value. The entries after it move up one position.
+    pub fn remove(&mut self, key: &HashableValue<'a>) -> Option<Value<'a>> {
+        let i = self.index.remove(key)?;
+        if let HashableValue::Instance { hash, .. } = key {
+            if let Some(keys) = self.by_hash.get_mut(hash) {
+                keys.retain(|other| other != key);
+            }
+        }
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            if let Some(position) = self.index.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// The instance keys whose `hash()` returned `hash`.
+    pub fn instance_keys(&self, hash: u64) -> Vec<HashableValue<'a>> {
+        self.by_hash.get(&hash).cloned().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&HashableValue<'a>, &Value<'a>)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(s: &str) -> HashableValue<'static> {
+        HashableValue::String(s.to_string())
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut map = LoxMap::new();
+        map.insert(key("b"), Value::Number(1.0));
+        map.insert(key("a"), Value::Number(2.0));
+        map.insert(HashableValue::Number(3.0), Value::Nil);
+        map.insert(key("b"), Value::Number(4.0));
+
+        let keys: Vec<_> = map.iter().map(|(key, _)| key.clone()).collect();
+        assert_eq!(keys, vec![key("b"), key("a"), HashableValue::Number(3.0)]);
+        assert_eq!(map.get(&key("b")), Some(&Value::Number(4.0)));
+
+        assert_eq!(map.remove(&key("b")), Some(Value::Number(4.0)));
+        assert_eq!(map.remove(&key("b")), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&key("a")), Some(&Value::Number(2.0)));
+        assert_eq!(map.get(&HashableValue::Number(3.0)), Some(&Value::Nil));
+    }
+}
@@ -1,46 +1,147 @@
 pub mod callable;
 pub mod class;
+mod condition;
+pub mod context;
 pub mod environment;
 pub mod error;
+pub mod globals;
+pub mod hooks;
+pub mod map;
 pub mod native_fun;
+pub mod options;
 pub mod resolver;
 pub mod value;
 
 use std::{
-    collections::{HashMap, VecDeque},
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
     rc::Rc,
 };
 
-use callable::LoxFunction;
-use class::LoxClass;
+use callable::{Arguments, Callable, LoxFunction};
+use class::{LoxClass, LoxInstance};
+use condition::Condition;
+use context::NativeContext;
 use environment::Environment;
-use error::{ClassError, Return, RuntimeError};
-use native_fun::clock::Clock;
-use value::Value;
+use error::{CallableError, ClassError, Return, RuntimeError};
+use globals::Globals;
+use hooks::Hooks;
+use map::LoxMap;
+use options::Options;
+use resolver::Analysis;
+use rustc_hash::FxHashMap;
+use value::{HashableValue, ListRef, Value};
 
 use crate::{
     ast::*,
     lex::{Token, TokenType},
 };
 
-#[derive(Clone)]
+/// Where a variable expression finds its variable, from the analyses of the resolver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    /// How many scopes up
+    Local(usize),
+    /// The slot in `Globals`
+    Global(usize),
+}
+
 pub struct Interpreter<'a> {
     environment: *mut Environment<'a>,
-    globals: Box<Environment<'a>>,
-    locals: HashMap<Expr<'a>, usize>,
+    /// The scope of the top level, variables defined in it go to `globals`
+    root: Box<Environment<'a>>,
+    globals: Globals<'a>,
+    /// Looked up for every variable access, with a cheaper hash than the default one
+    bindings: FxHashMap<NodeId, Binding>,
+    context: NativeContext<'a>,
+    options: Options,
+    hooks: Option<Box<dyn Hooks<'a> + 'a>>,
+    /// Fields of the instances whose `init` is currently running
+    initializing: Vec<*const ()>,
+    /// Declarations of the running functions, innermost last
+    functions: Vec<Token<'a>>,
 }
 
 impl<'a, 'b: 'a> Interpreter<'a> {
-    pub fn new(locals: HashMap<Expr<'a>, usize>) -> Self {
-        let mut globals = Box::new(Environment::new(None));
-        globals.define("clock", Some(Value::Callable(Rc::new(Clock::new()))));
+    pub fn new(analysis: Analysis<'a>) -> Self {
+        Self::with_output(analysis, Box::new(io::stdout()))
+    }
 
-        let globals_ptr = &mut *globals as *mut Environment;
+    pub fn with_output(analysis: Analysis<'a>, out: Box<dyn Write + 'a>) -> Self {
+        let mut globals = Globals::default();
+        native_fun::define_globals(&mut globals);
 
-        Interpreter {
+        let mut root = Box::new(Environment::new(None));
+        let root_ptr = &mut *root as *mut Environment;
+
+        let mut interpreter = Interpreter {
+            root,
             globals,
-            environment: globals_ptr,
-            locals,
+            environment: root_ptr,
+            bindings: FxHashMap::default(),
+            context: NativeContext::new(out),
+            options: Options::default(),
+            hooks: None,
+            initializing: vec![],
+            functions: vec![],
+        };
+        interpreter.add_analysis(analysis);
+        interpreter
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write + 'a>) {
+        self.context.set_output(out);
+    }
+
+    pub fn context(&mut self) -> &mut NativeContext<'a> {
+        &mut self.context
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        #[cfg(feature = "net")]
+        if options.allow_net && !self.options.allow_net {
+            native_fun::define_net_globals(&mut self.globals);
+        }
+        self.options = options;
+    }
+
+    pub fn set_hooks(&mut self, hooks: Box<dyn Hooks<'a> + 'a>) {
+        self.hooks = Some(hooks);
+    }
+
+    /// Adds the analysis of more statements, e.g. the next line of a session. Their nodes are
+    /// new, an entry that is already there would change the variable an earlier tree reads.
+    pub fn add_analysis(&mut self, analysis: Analysis<'a>) {
+        for (id, distance) in analysis.locals {
+            let replaced = self.bindings.insert(id, Binding::Local(distance));
+            debug_assert!(replaced.is_none(), "{id:?} was resolved twice");
+        }
+        for (id, name) in analysis.globals {
+            let slot = self.globals.slot(name);
+            let replaced = self.bindings.insert(id, Binding::Global(slot));
+            debug_assert!(replaced.is_none(), "{id:?} was resolved twice");
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value<'a>> {
+        self.globals.lookup(name)
+    }
+
+    /// All globals, natives included, sorted by name.
+    pub fn globals(&self) -> Vec<(&'a str, Option<Value<'a>>)> {
+        self.globals.bindings()
+    }
+
+    /// Defines a variable in the current scope, a global at the top level.
+    fn define(&mut self, name: &'a str, value: Option<Value<'a>>) {
+        match std::ptr::eq(self.environment, &*self.root) {
+            true => self.globals.define(name, value),
+            false => self.get_mut_environment().define(name, value),
         }
     }
 
@@ -58,14 +159,53 @@ impl<'a, 'b: 'a> Interpreter<'a> {
     }
 
     pub fn interpret(&mut self, stmts: &'b [Stmt<'a>]) -> Result<(), RuntimeError<'a>> {
-        stmts.iter().try_for_each(|stmt| self.execute(stmt))?;
-        Ok(())
+        let result = stmts.iter().try_for_each(|stmt| self.execute(stmt));
+        if let (Err(e), Some(hooks)) = (&result, self.hooks.as_mut()) {
+            hooks.on_error(e);
+        }
+        result
+    }
+
+    /// Executes `stmts` one at a time, as the returned iterator is advanced. Each item is the value
+    /// of an expression statement or `None` for any other statement, so hosts like a REPL can show
+    /// results and run their own logic between top-level statements. Stopping after an error is up
+    /// to the caller.
+    pub fn interpret_iter<'s>(
+        &'s mut self,
+        stmts: &'b [Stmt<'a>],
+    ) -> impl Iterator<Item = Result<Option<Value<'a>>, RuntimeError<'a>>> + 's
+    where
+        'b: 's,
+    {
+        stmts.iter().map(move |stmt| {
+            let result = match stmt {
+                Stmt::Expression(node) => self
+                    .start_statement(stmt)
+                    .and_then(|_| self.evaluate(&node.expr))
+                    .map(Some),
+                _ => self.execute(stmt).map(|_| None),
+            };
+            if let (Err(e), Some(hooks)) = (&result, self.hooks.as_mut()) {
+                hooks.on_error(e);
+            }
+            result
+        })
     }
 
     fn execute(&mut self, stmt: &'b Stmt<'a>) -> Result<(), RuntimeError<'a>> {
+        self.start_statement(stmt)?;
         stmt.accept(self)
     }
 
+    /// Stops an interrupted script before the statement runs and tells the hooks about it.
+    fn start_statement(&mut self, stmt: &Stmt<'a>) -> Result<(), RuntimeError<'a>> {
+        self.context.check_interrupt()?;
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_statement(stmt);
+        }
+        Ok(())
+    }
+
     fn execute_block(
         &mut self,
         statements: &'b [Stmt<'a>],
@@ -101,6 +241,29 @@ impl<'a, 'b: 'a> Interpreter<'a> {
         expr.accept(self)
     }
 
+    fn check_new_field(
+        &self,
+        instance: &LoxInstance<'a>,
+        name: Token<'a>,
+    ) -> Result<(), RuntimeError<'a>> {
+        let id = Rc::as_ptr(&instance.fields) as *const ();
+        if self.options.strict_fields && !self.initializing.contains(&id) {
+            return Err(RuntimeError::ClassError(ClassError::UndeclaredField {
+                token: name,
+            }));
+        }
+
+        match self.options.max_fields {
+            Some(max) if instance.field_count() >= max => {
+                Err(RuntimeError::ClassError(ClassError::TooManyFields {
+                    token: name,
+                    max,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn check_number_operand(
         &mut self,
         value: &Value,
@@ -108,10 +271,26 @@ impl<'a, 'b: 'a> Interpreter<'a> {
     ) -> Result<(), RuntimeError<'a>> {
         match value {
             Value::Number(_) => Ok(()),
-            _ => Err(RuntimeError::NumberOperand { operator }),
+            _ => Err(RuntimeError::NumberOperand {
+                operator,
+                operand: value.describe(),
+            }),
         }
     }
 
+    /// The value `++` or `--` turns `old` into.
+    fn step(
+        &mut self,
+        operator: Token<'a>,
+        old: &Value<'a>,
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        self.check_number_operand(old, operator)?;
+        Ok(match operator.kind {
+            TokenType::PlusPlus => old.clone() + Value::Number(1.0),
+            _ => old.clone() - Value::Number(1.0),
+        })
+    }
+
     fn check_number_operands(
         &mut self,
         left: &Value,
@@ -120,19 +299,287 @@ impl<'a, 'b: 'a> Interpreter<'a> {
     ) -> Result<(), RuntimeError<'a>> {
         match (left, right) {
             (Value::Number(_), Value::Number(_)) => Ok(()),
-            _ => Err(RuntimeError::MutlipleNumberOperands { operator }),
+            _ => Err(RuntimeError::MutlipleNumberOperands {
+                operator,
+                left: left.describe(),
+                right: right.describe(),
+            }),
+        }
+    }
+
+    /// The operators of binary expressions, which compound assignments use too.
+    fn binary(
+        &mut self,
+        left: Value<'a>,
+        operator: Token<'a>,
+        right: Value<'a>,
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        match operator.kind {
+            TokenType::Minus => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(left - right)
+            }
+            TokenType::Slash => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(left / right)
+            }
+            TokenType::Star => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(left * right)
+            }
+            TokenType::Percent => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(left % right)
+            }
+            TokenType::Plus => {
+                if let (Value::String(_), Value::String(_)) = (&left, &right) {
+                    return Ok(left + right);
+                }
+                if let (Value::Number(_), Value::Number(_)) = (&left, &right) {
+                    return Ok(left + right);
+                }
+                Err(RuntimeError::NumberOrStringOperands {
+                    operator,
+                    left: left.describe(),
+                    right: right.describe(),
+                })
+            }
+            TokenType::Greater => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(Value::Boolean(left > right))
+            }
+            TokenType::Less => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(Value::Boolean(left < right))
+            }
+            TokenType::GreaterEqual => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(Value::Boolean(left >= right))
+            }
+            TokenType::LessEqual => {
+                self.check_number_operands(&left, &right, operator)?;
+                Ok(Value::Boolean(left <= right))
+            }
+            TokenType::EqualEqual => Ok(Value::Boolean(self.values_equal(left, right, operator)?)),
+            TokenType::BangEqual => Ok(Value::Boolean(!self.values_equal(left, right, operator)?)),
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    /// `==` for two values. An instance whose class defines `equals(other)` decides itself.
+    fn values_equal(
+        &mut self,
+        left: Value<'a>,
+        right: Value<'a>,
+        operator: Token<'a>,
+    ) -> Result<bool, RuntimeError<'a>> {
+        let Value::Instance(instance) = &left else {
+            return Ok(left == right);
+        };
+        let Some(equals) = instance.borrow().class.find_method("equals").cloned() else {
+            return Ok(left == right);
+        };
+        Ok(self
+            .call_method(instance, equals, &[right], operator)?
+            .is_truthy())
+    }
+
+    /// Calls a method the interpreter calls on its own, like `equals`. `token` locates an error
+    /// about the number of arguments.
+    fn call_method(
+        &mut self,
+        instance: &Rc<RefCell<LoxInstance<'a>>>,
+        method: LoxFunction<'a>,
+        arguments: &[Value<'a>],
+        token: Token<'a>,
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let method = Callable::Function(Rc::new(method.bind_rc(instance.clone())));
+        if !method.arity().accepts(arguments.len()) {
+            return Err(RuntimeError::ArgumentCount {
+                token,
+                kind: method.call_type(),
+                name: method.name().to_string(),
+                expected_arity: method.arity(),
+                given_len: arguments.len(),
+            });
+        }
+        method.call(self, arguments)
+    }
+
+    /// Converts `value` into a key of `map`, `token` locates the error if it can't be one.
+    pub fn map_key(
+        &mut self,
+        map: &RefCell<LoxMap<'a>>,
+        value: Value<'a>,
+        token: Token<'a>,
+    ) -> Result<HashableValue<'a>, RuntimeError<'a>> {
+        let value = match HashableValue::try_from(value) {
+            Ok(key) => return Ok(key),
+            Err(value) => value,
+        };
+        if let Value::Instance(instance) = &value {
+            if let Some(key) = self.instance_key(instance)? {
+                return self.find_key(map, key);
+            }
+        }
+        Err(RuntimeError::Unhashable {
+            token,
+            type_name: value.type_name(),
+        })
+    }
+
+    /// The key of `map` that the instance key `key` is `equals()` to, `key` itself if there's
+    /// none or its class doesn't define `equals()`.
+    pub fn find_key(
+        &mut self,
+        map: &RefCell<LoxMap<'a>>,
+        key: HashableValue<'a>,
+    ) -> Result<HashableValue<'a>, RuntimeError<'a>> {
+        let HashableValue::Instance { instance, hash } = &key else {
+            return Ok(key);
+        };
+        let (instance, hash) = (instance.clone(), *hash);
+        let Some(equals) = instance.borrow().class.find_method("equals").cloned() else {
+            return Ok(key);
+        };
+        let name = equals.declaration.name;
+        // Collected up front, `equals()` may change the map
+        let candidates = map.borrow().instance_keys(hash);
+        for candidate in candidates {
+            if candidate == key {
+                return Ok(candidate);
+            }
+            let other = Value::from(candidate.clone());
+            if self
+                .call_method(&instance, equals.clone(), &[other], name)?
+                .is_truthy()
+            {
+                return Ok(candidate);
+            }
         }
+        Ok(key)
+    }
+
+    /// The key of an instance whose class defines `hash()`, `None` if it doesn't. Errors about the
+    /// method point at its declaration, keys are also made by natives that don't know where they
+    /// were called from.
+    pub fn instance_key(
+        &mut self,
+        instance: &Rc<RefCell<LoxInstance<'a>>>,
+    ) -> Result<Option<HashableValue<'a>>, RuntimeError<'a>> {
+        let Some(hash) = instance.borrow().class.find_method("hash").cloned() else {
+            return Ok(None);
+        };
+        let name = hash.declaration.name;
+        match self.call_method(instance, hash, &[], name)? {
+            Value::Number(n) => Ok(Some(HashableValue::instance(instance.clone(), n))),
+            value => Err(RuntimeError::InvalidHash {
+                token: name,
+                value: value.describe(),
+            }),
+        }
+    }
+
+    /// The position in `list` that `index` points at, `bracket` locates errors.
+    fn list_position(
+        &self,
+        list: &ListRef<'a>,
+        index: Value<'a>,
+        bracket: Token<'a>,
+    ) -> Result<usize, RuntimeError<'a>> {
+        let index = match index {
+            Value::Number(n) if n.fract() == 0.0 => n,
+            index => {
+                return Err(RuntimeError::InvalidIndex {
+                    bracket,
+                    index: index.describe(),
+                })
+            }
+        };
+
+        let len = list.borrow().len();
+        if index < 0.0 || index >= len as f64 {
+            return Err(RuntimeError::IndexOutOfBounds {
+                bracket,
+                index,
+                len,
+            });
+        }
+        Ok(index as usize)
     }
 
     fn lookup_variable(
         &mut self,
         name: Token<'a>,
-        expr: &Expr<'a>,
+        id: NodeId,
     ) -> Result<Value<'a>, RuntimeError<'a>> {
-        let distance = self.locals.get(expr);
-        match distance {
-            Some(&d) => Ok(self.get_mut_environment().get_at(d, name.lexeme)),
-            None => self.globals.get(name),
+        let value = match self.bindings.get(&id) {
+            Some(&Binding::Local(distance)) => self.get_at(distance, name)?,
+            Some(&Binding::Global(slot)) => self.globals.get_slot(slot, name)?,
+            None => self.globals.get(name)?,
+        };
+        match value {
+            Some(value) => Ok(value),
+            None if self.options.strict_variables => {
+                Err(RuntimeError::UninitializedVariable { name })
+            }
+            None => Ok(Value::Nil),
+        }
+    }
+
+    /// Assigns to the variable the expression `id` refers to.
+    fn assign_variable(
+        &mut self,
+        name: Token<'a>,
+        id: NodeId,
+        value: Value<'a>,
+    ) -> Result<(), RuntimeError<'a>> {
+        match self.bindings.get(&id) {
+            Some(&Binding::Local(distance)) => self
+                .get_mut_environment()
+                .assign_at(distance, name, value)
+                .ok_or_else(|| self.environment_unavailable(name)),
+            Some(&Binding::Global(slot)) => self.globals.assign_slot(slot, name, value),
+            None => self.globals.assign(name, value),
+        }
+    }
+
+    /// `None` if the variable was declared without a value and never assigned.
+    fn get_at(
+        &mut self,
+        distance: usize,
+        name: Token<'a>,
+    ) -> Result<Option<Value<'a>>, RuntimeError<'a>> {
+        self.get_mut_environment()
+            .get_at(distance, name.lexeme)
+            .ok_or_else(|| self.environment_unavailable(name))
+    }
+
+    /// A property read from an instance or class, a getter runs instead of being returned as a
+    /// bound method.
+    fn run_getter(&mut self, property: Value<'a>) -> Result<Value<'a>, RuntimeError<'a>> {
+        let getter = match property {
+            Value::Callable(Callable::Function(function)) if function.declaration.is_getter => {
+                Callable::Function(function)
+            }
+            property => return Ok(property),
+        };
+
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_call(&getter, &[]);
+        }
+        let value = getter.call(self, &[])?;
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_return(&getter, &value);
+        }
+        Ok(value)
+    }
+
+    /// A closure outlived the environment it captured, point at the function that captured it.
+    fn environment_unavailable(&self, token: Token<'a>) -> RuntimeError<'a> {
+        RuntimeError::EnvironmentUnavailable {
+            token,
+            function: self.functions.last().copied(),
         }
     }
 }
@@ -145,11 +592,11 @@ impl<'a, 'b> ExprVisitor<'a, 'b> for Interpreter<'a> {
     }
 
     fn visit_grouping(&mut self, node: &ExprGrouping<'a>) -> Self::Output {
-        self.evaluate(&node.value)
+        self.evaluate(node.value)
     }
 
     fn visit_logical(&mut self, node: &ExprLogical<'a>) -> Self::Output {
-        let left = self.evaluate(&node.left)?;
+        let left = self.evaluate(node.left)?;
 
         if (node.operator.kind == TokenType::Or && left.is_truthy())
             || (node.operator.kind == TokenType::And && !left.is_truthy())
@@ -157,11 +604,11 @@ impl<'a, 'b> ExprVisitor<'a, 'b> for Interpreter<'a> {
             return Ok(left);
         }
 
-        self.evaluate(&node.right)
+        self.evaluate(node.right)
     }
 
     fn visit_set(&mut self, node: &'b ExprSet<'a>) -> Self::Output {
-        let object = self.evaluate(&node.object)?;
+        let object = self.evaluate(node.object)?;
 
         let Value::Instance(instance) = object else {
             return Err(RuntimeError::ClassError(
@@ -169,61 +616,66 @@ impl<'a, 'b> ExprVisitor<'a, 'b> for Interpreter<'a> {
             ));
         };
 
-        let value = self.evaluate(&node.value)?;
+        let value = self.evaluate(node.value)?;
+        if !instance.borrow().has_field(node.name.lexeme) {
+            self.check_new_field(&instance.borrow(), node.name)?;
+        }
         // TODO: Clone
         instance.borrow_mut().set(node.name, value.clone());
         Ok(value)
     }
 
     fn visit_super(&mut self, node: &'b ExprSuper<'a>) -> Self::Output {
-        let distance = self
-            .locals
-            .get(&Expr::Super(node.clone()))
-            .cloned()
-            .unwrap();
+        let Some(&Binding::Local(distance)) = self.bindings.get(&NodeId::of(node)) else {
+            unreachable!("the resolver finds super in the scope around the methods");
+        };
 
-        let superclass = self.get_mut_environment().get_at(distance, "super");
-        let object = self.get_mut_environment().get_at(distance - 1, "this");
+        let superclass = self.get_at(distance, node.keyword)?.unwrap_or_default();
+        let this = Token::new(
+            TokenType::This,
+            "this",
+            node.keyword.line,
+            node.keyword.span,
+        );
+        let object = self.get_at(distance - 1, this)?.unwrap_or_default();
 
         let superclass = match superclass {
-            Value::Callable(callable) => callable.clone_as_class().ok_or(
-                RuntimeError::ClassError(ClassError::SuperclassNotAClass {
-                    token: node.keyword,
-                }),
-            )?,
+            Value::Callable(Callable::Class(class)) => class,
             _ => {
                 return Err(RuntimeError::ClassError(ClassError::SuperclassNotAClass {
                     token: node.keyword,
                 }))
             }
         };
-        let object = match object {
-            Value::Instance(instance) => instance,
-            _ => {
-                return Err(RuntimeError::ClassError(ClassError::SuperclassNotAClass {
-                    token: node.keyword,
-                }))
-            }
+        let undefined =
+            RuntimeError::ClassError(ClassError::UndefinedProperty { token: node.method });
+        // `this` is bound to an instance whenever `super` can be used, or to the class in class
+        // methods
+        let method = match object {
+            // Bound to the same instance as `this`, so the method sees later changes to its
+            // fields. Like `object.method`, every evaluation binds a new method.
+            Value::Instance(object) => superclass
+                .find_method(node.method.lexeme)
+                .ok_or(undefined)?
+                .bind_rc(object),
+            // Still bound to the subclass, so `this` in the inherited class method is the class
+            // it was called on
+            Value::Callable(Callable::Class(class)) => superclass
+                .find_class_method(node.method.lexeme)
+                .ok_or(undefined)?
+                .bind_class(class),
+            _ => return Err(RuntimeError::CallableError(CallableError::InternalError)),
         };
-
-        let method = superclass
-            .find_method(node.method.lexeme)
-            .ok_or(RuntimeError::ClassError(ClassError::UndefinedProperty {
-                token: node.method,
-            }))?;
-
-        let a = method.bind_rc(object);
-        Ok(Value::Callable(Rc::new(a)))
+        self.run_getter(Value::Callable(Callable::Function(Rc::new(method))))
     }
 
     fn visit_this(&mut self, node: &'b ExprThis<'a>) -> Self::Output {
-        // TODO: Clone
-        self.lookup_variable(node.keyword, &Expr::This(node.clone()))
+        self.lookup_variable(node.keyword, NodeId::of(node))
     }
 
     fn visit_unary(&mut self, node: &ExprUnary<'a>) -> Self::Output {
         let operator = node.operator;
-        let right = self.evaluate(&node.value)?;
+        let right = self.evaluate(node.value)?;
 
         match node.operator.kind {
             TokenType::Minus => {
@@ -231,112 +683,249 @@ impl<'a, 'b> ExprVisitor<'a, 'b> for Interpreter<'a> {
                 Ok(-right)
             }
             TokenType::Bang => Ok(!right),
-            _ => Err(RuntimeError::NumberOperand { operator }),
+            _ => Err(RuntimeError::NumberOperand {
+                operator,
+                operand: right.describe(),
+            }),
         }
     }
 
     fn visit_binary(&mut self, node: &ExprBinary<'a>) -> Self::Output {
-        let operator = node.operator;
-        let left = self.evaluate(&node.left)?;
-        let right = self.evaluate(&node.right)?;
-
-        match node.operator.kind {
-            TokenType::Minus => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(left - right)
-            }
-            TokenType::Slash => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(left / right)
-            }
-            TokenType::Star => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(left * right)
-            }
-            TokenType::Plus => {
-                if let (Value::String(_), Value::String(_)) = (&left, &right) {
-                    return Ok(left + right);
-                }
-                if let (Value::Number(_), Value::Number(_)) = (&left, &right) {
-                    return Ok(left + right);
-                }
-                Err(RuntimeError::NumberOrStringOperands { operator })
-            }
-            TokenType::Greater => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(Value::Boolean(left > right))
-            }
-            TokenType::Less => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(Value::Boolean(left < right))
-            }
-            TokenType::GreaterEqual => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(Value::Boolean(left >= right))
-            }
-            TokenType::LessEqual => {
-                self.check_number_operands(&left, &right, operator)?;
-                Ok(Value::Boolean(left <= right))
-            }
-            TokenType::EqualEqual => Ok(Value::Boolean(left == right)),
-            TokenType::BangEqual => Ok(Value::Boolean(left != right)),
-            _ => Ok(Value::Nil),
-        }
+        let left = self.evaluate(node.left)?;
+        let right = self.evaluate(node.right)?;
+        self.binary(left, node.operator, right)
     }
 
     fn visit_call(&mut self, node: &ExprCall<'a>) -> Self::Output {
-        let callee = self.evaluate(&node.callee)?;
+        let callee = self.evaluate(node.callee)?;
 
-        let arguments: VecDeque<Value<'a>> = node
+        let arguments: Arguments<'a> = node
             .arguments
             .iter()
             .map(|argument| self.evaluate(argument))
-            .collect::<Result<VecDeque<_>, _>>()?;
+            .collect::<Result<Arguments<'a>, _>>()?;
 
         let Value::Callable(function) = callee else {
             return Err(RuntimeError::NotCallable { token: node.paren });
         };
 
-        if arguments.len() != function.arity() {
+        if !function.arity().accepts(arguments.len()) {
             return Err(RuntimeError::ArgumentCount {
                 token: node.paren,
+                kind: function.call_type(),
+                name: function.name().to_string(),
                 expected_arity: function.arity(),
                 given_len: arguments.len(),
             });
         }
 
-        function.call(self, arguments)
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_call(&function, &arguments);
+        }
+        // Natives don't know where they were called from, so their errors get the call site here
+        let value = function.call(self, &arguments).map_err(|e| match e {
+            RuntimeError::NativeError(error) => RuntimeError::NativeCall {
+                token: node.paren,
+                error,
+            },
+            e => e,
+        })?;
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_return(&function, &value);
+        }
+        Ok(value)
     }
 
     fn visit_get(&mut self, node: &'b ExprGet<'a>) -> Self::Output {
-        let object = self.evaluate(&node.object)?;
-
-        if let Value::Instance(instance) = object {
-            return instance.borrow().get(node.name);
-        }
+        let object = self.evaluate(node.object)?;
 
-        Err(RuntimeError::ClassError(
-            ClassError::InvalidPropertyAccess { token: node.name },
-        ))
+        let property = match object {
+            Value::Instance(instance) => instance.borrow().get(node.name)?,
+            Value::Callable(Callable::Class(class)) => class.get(node.name)?,
+            _ => {
+                return Err(RuntimeError::ClassError(
+                    ClassError::InvalidPropertyAccess { token: node.name },
+                ))
+            }
+        };
+        self.run_getter(property)
     }
 
     fn visit_assign(&mut self, node: &ExprAssign<'a>) -> Self::Output {
-        let value = self.evaluate(&node.value)?;
+        let value = self.evaluate(node.value)?;
 
-        let distance = self.locals.get(&Expr::Assign(node.clone())).cloned();
-        match distance {
-            Some(d) => {
-                self.get_mut_environment()
-                    .assign_at(d, node.name, value.clone());
+        self.assign_variable(node.name, NodeId::of(node), value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_variable(&mut self, node: &ExprVariable<'a>) -> Self::Output {
+        self.lookup_variable(node.name, NodeId::of(node))
+    }
+
+    fn visit_update(&mut self, node: &'b ExprUpdate<'a>) -> Self::Output {
+        let (old, new) = match node.target {
+            Expr::Variable(variable) => {
+                let old = self.evaluate(node.target)?;
+                let new = self.step(node.operator, &old)?;
+                self.assign_variable(variable.name, NodeId::of(variable), new.clone())?;
+                (old, new)
+            }
+            Expr::Get(get) => {
+                let Value::Instance(instance) = self.evaluate(get.object)? else {
+                    return Err(RuntimeError::ClassError(
+                        ClassError::InvalidPropertyAccess { token: get.name },
+                    ));
+                };
+                let property = instance.borrow().get(get.name)?;
+                let old = self.run_getter(property)?;
+                let new = self.step(node.operator, &old)?;
+                if !instance.borrow().has_field(get.name.lexeme) {
+                    self.check_new_field(&instance.borrow(), get.name)?;
+                }
+                instance.borrow_mut().set(get.name, new.clone());
+                (old, new)
+            }
+            _ => unreachable!("the parser only builds updates of variables and properties"),
+        };
+
+        Ok(if node.prefix { new } else { old })
+    }
+
+    /// Reads the target, applies the operator with the value and writes the result back. Unlike
+    /// `x = x + 1` the object and the index are evaluated once.
+    fn visit_compound(&mut self, node: &'b ExprCompound<'a>) -> Self::Output {
+        match node.target {
+            Expr::Get(get) => {
+                let Value::Instance(instance) = self.evaluate(get.object)? else {
+                    return Err(RuntimeError::ClassError(
+                        ClassError::InvalidPropertyAccess { token: get.name },
+                    ));
+                };
+                let property = instance.borrow().get(get.name)?;
+                let old = self.run_getter(property)?;
+                let value = self.evaluate(node.value)?;
+                let new = self.binary(old, node.operator, value)?;
+                if !instance.borrow().has_field(get.name.lexeme) {
+                    self.check_new_field(&instance.borrow(), get.name)?;
+                }
+                instance.borrow_mut().set(get.name, new.clone());
+                Ok(new)
             }
-            None => self.globals.assign(node.name, value.clone())?,
+            Expr::Index(target) => {
+                let object = self.evaluate(target.object)?;
+                let index = self.evaluate(target.index)?;
+                match object {
+                    Value::List(list) => {
+                        let position = self.list_position(&list, index.clone(), target.bracket)?;
+                        let old = list.borrow()[position].clone();
+                        let value = self.evaluate(node.value)?;
+                        let new = self.binary(old, node.operator, value)?;
+                        // Evaluating the value may have shortened the list
+                        let position = self.list_position(&list, index, target.bracket)?;
+                        list.borrow_mut()[position] = new.clone();
+                        Ok(new)
+                    }
+                    Value::Map(map) => {
+                        let key = self.map_key(&map, index, target.bracket)?;
+                        let old = map.borrow().get(&key).cloned();
+                        let old = old.ok_or_else(|| RuntimeError::MissingKey {
+                            bracket: target.bracket,
+                            key: Value::from(key.clone()).describe(),
+                        })?;
+                        let value = self.evaluate(node.value)?;
+                        let new = self.binary(old, node.operator, value)?;
+                        map.borrow_mut().insert(key, new.clone());
+                        Ok(new)
+                    }
+                    object => Err(RuntimeError::NotIndexable {
+                        bracket: target.bracket,
+                        value: object.describe(),
+                    }),
+                }
+            }
+            _ => unreachable!(
+                "the parser only builds compound assignments of properties and indexes"
+            ),
         }
+    }
 
-        Ok(value)
+    fn visit_list(&mut self, node: &'b ExprList<'a>) -> Self::Output {
+        let elements = node
+            .elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<_, _>>()?;
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
     }
 
-    fn visit_variable(&mut self, node: &ExprVariable<'a>) -> Self::Output {
-        self.lookup_variable(node.name, &Expr::Variable(node.clone()))
+    fn visit_map(&mut self, node: &'b ExprMap<'a>) -> Self::Output {
+        let map = Rc::new(RefCell::new(LoxMap::new()));
+        for (key, value) in node.keys.iter().zip(&node.values) {
+            let key = self.evaluate(key)?;
+            let key = self.map_key(&map, key, node.brace)?;
+            let value = self.evaluate(value)?;
+            map.borrow_mut().insert(key, value);
+        }
+        Ok(Value::Map(map))
+    }
+
+    /// The parts are converted to text like `print` does.
+    fn visit_interpolation(&mut self, node: &'b ExprInterpolation<'a>) -> Self::Output {
+        let mut string = String::new();
+        for part in &node.parts {
+            string.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(Value::String(string))
+    }
+
+    fn visit_index(&mut self, node: &'b ExprIndex<'a>) -> Self::Output {
+        let object = self.evaluate(node.object)?;
+        let index = self.evaluate(node.index)?;
+
+        match object {
+            Value::List(list) => {
+                let position = self.list_position(&list, index, node.bracket)?;
+                let value = list.borrow()[position].clone();
+                Ok(value)
+            }
+            Value::Map(map) => {
+                let key = self.map_key(&map, index, node.bracket)?;
+                let value = map.borrow().get(&key).cloned();
+                value.ok_or_else(|| RuntimeError::MissingKey {
+                    bracket: node.bracket,
+                    key: Value::from(key).describe(),
+                })
+            }
+            object => Err(RuntimeError::NotIndexable {
+                bracket: node.bracket,
+                value: object.describe(),
+            }),
+        }
+    }
+
+    fn visit_set_index(&mut self, node: &'b ExprSetIndex<'a>) -> Self::Output {
+        let object = self.evaluate(node.object)?;
+        let index = self.evaluate(node.index)?;
+        let value = self.evaluate(node.value)?;
+
+        // TODO: Clone
+        match object {
+            Value::List(list) => {
+                let position = self.list_position(&list, index, node.bracket)?;
+                list.borrow_mut()[position] = value.clone();
+            }
+            Value::Map(map) => {
+                let key = self.map_key(&map, index, node.bracket)?;
+                map.borrow_mut().insert(key, value.clone());
+            }
+            object => {
+                return Err(RuntimeError::NotIndexable {
+                    bracket: node.bracket,
+                    value: object.describe(),
+                })
+            }
+        }
+        Ok(value)
     }
 }
 
@@ -348,23 +937,26 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
         Ok(())
     }
 
+    fn visit_break(&mut self, _node: &StmtBreak<'a>) -> Self::Output {
+        Err(RuntimeError::Break)
+    }
+
     fn visit_class(&mut self, node: &'b StmtClass<'a>) -> Self::Output {
         let mut superclass = None;
         let mut superclass_value = None;
         if let Some(sc) = &node.superclass {
             superclass_value = Some(self.evaluate(sc)?);
             superclass = match superclass_value.as_ref().unwrap() {
-                Value::Callable(callable) => {
-                    let class = callable.clone_as_class().ok_or(RuntimeError::ClassError(
-                        ClassError::SuperclassNotAClass { token: node.name },
-                    ))?;
-                    Some(class)
+                Value::Callable(Callable::Class(class)) => Some(class.clone()),
+                _ => {
+                    return Err(RuntimeError::ClassError(ClassError::SuperclassNotAClass {
+                        token: node.name,
+                    }))
                 }
-                _ => None,
             };
         }
 
-        self.get_mut_environment().define(node.name.lexeme, None);
+        self.define(node.name.lexeme, None);
 
         if node.superclass.is_some() {
             self.environment = Box::into_raw(Box::new(Environment::new(Some(self.environment))));
@@ -381,18 +973,36 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
             methods.insert(method.name.lexeme, function);
         });
 
-        let class = LoxClass::new(node.name.lexeme, superclass, methods);
+        let class_methods = node
+            .class_methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction::new(method, self.get_ptr_environment(), false);
+                (method.name.lexeme, function)
+            })
+            .collect();
+
+        let class = LoxClass {
+            class_methods,
+            ..LoxClass::new(node.name.lexeme, superclass, methods)
+        };
 
         if node.superclass.is_some() {
             self.environment = self.get_mut_environment().enclosing.unwrap();
         }
 
-        self.get_mut_environment()
-            .assign(node.name, Value::Callable(Rc::new(class)))?;
+        self.define(
+            node.name.lexeme,
+            Some(Value::Callable(Callable::Class(Rc::new(class)))),
+        );
 
         Ok(())
     }
 
+    fn visit_continue(&mut self, _node: &StmtContinue<'a>) -> Self::Output {
+        Err(RuntimeError::Continue)
+    }
+
     fn visit_expression(&mut self, node: &StmtExpression<'a>) -> Self::Output {
         self.evaluate(&node.expr)?;
         Ok(())
@@ -401,8 +1011,10 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
     fn visit_function(&mut self, node: &'b StmtFunction<'a>) -> Self::Output {
         let function = LoxFunction::new(node, self.get_ptr_environment(), false);
 
-        self.get_mut_environment()
-            .define(node.name.lexeme, Some(Value::Callable(Rc::new(function))));
+        self.define(
+            node.name.lexeme,
+            Some(Value::Callable(Callable::Function(Rc::new(function)))),
+        );
 
         Ok(())
     }
@@ -410,7 +1022,7 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
     fn visit_if(&mut self, node: &'b StmtIf<'a>) -> Self::Output {
         let condition = self.evaluate(&node.condition)?;
         if condition.is_truthy() {
-            self.execute(&node.then_branch)?;
+            self.execute(node.then_branch)?;
         } else if let Some(stmt) = &node.else_branch {
             self.execute(stmt)?;
         }
@@ -420,15 +1032,16 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
 
     fn visit_print(&mut self, node: &StmtPrint<'a>) -> Self::Output {
         let value = self.evaluate(&node.expr)?;
-        println!("{}", value);
+        let text = self.options.truncate(value.to_string());
+        writeln!(self.context.out(), "{}", text).map_err(|_| RuntimeError::OutputError)?;
         Ok(())
     }
 
     fn visit_return(&mut self, node: &'b StmtReturn<'a>) -> Self::Output {
         let mut value = Value::Nil;
 
-        if node.value.is_some() {
-            value = self.evaluate(node.value.as_ref().unwrap())?;
+        if let Some(expr) = &node.value {
+            value = self.evaluate(expr)?;
         }
 
         Err(RuntimeError::Return(Return { value }))
@@ -439,13 +1052,28 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
         if let Some(initializer) = &node.initializer {
             value = Some(self.evaluate(initializer)?);
         }
-        self.get_mut_environment().define(node.name.lexeme, value);
+        self.define(node.name.lexeme, value);
         Ok(())
     }
 
     fn visit_while(&mut self, node: &'b StmtWhile<'a>) -> Self::Output {
-        while self.evaluate(&node.condition)?.is_truthy() {
-            self.execute(&node.body)?;
+        let condition = Condition::compile(&node.condition, &self.bindings);
+        loop {
+            let truthy = match condition.as_ref().map(|c| c.test(self)).transpose()? {
+                Some(Some(truthy)) => truthy,
+                _ => self.evaluate(&node.condition)?.is_truthy(),
+            };
+            if !truthy {
+                break;
+            }
+            match self.execute(node.body) {
+                Ok(()) | Err(RuntimeError::Continue) => (),
+                Err(RuntimeError::Break) => break,
+                Err(e) => return Err(e),
+            }
+            if let Some(increment) = &node.increment {
+                self.evaluate(increment)?;
+            }
         }
 
         Ok(())
@@ -454,13 +1082,16 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Interpreter<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::lex::{Span, Token};
+    use crate::{
+        ast::Arena,
+        lex::{Scanner, Span, Token},
+        parser::{Parser, TokenStream},
+    };
 
-    use super::*;
+    use super::{resolver::Resolver, *};
 
-    fn setup() -> Interpreter<'static> {
-        let locals = HashMap::new();
-        Interpreter::new(locals)
+    fn setup<'a>() -> Interpreter<'a> {
+        Interpreter::new(Analysis::default())
     }
 
     #[test]
@@ -475,11 +1106,10 @@ mod test {
 
     #[test]
     fn grouping() {
+        let one = Expr::Literal(ExprLiteral::new(LiteralValue::F64(1.0)));
         let mut interpreter = setup();
 
-        let expr = Expr::Grouping(ExprGrouping::new(Box::new(Expr::Literal(
-            ExprLiteral::new(LiteralValue::F64(1.0)),
-        ))));
+        let expr = Expr::Grouping(ExprGrouping::new(&one));
         let result = interpreter.evaluate(&expr).unwrap();
 
         assert_eq!(result, Value::Number(1.0));
@@ -487,33 +1117,276 @@ mod test {
 
     #[test]
     fn unary() {
+        let one = Expr::Literal(ExprLiteral::new(LiteralValue::F64(1.0)));
         let mut interpreter = setup();
 
         let span = Span { begin: 0, end: 1 };
         let token = Token::new(TokenType::Minus, "-", 1, span);
-        let expr = Expr::Unary(ExprUnary::new(
-            token,
-            Box::new(Expr::Literal(ExprLiteral::new(LiteralValue::F64(1.0)))),
-        ));
+        let expr = Expr::Unary(ExprUnary::new(token, &one));
         let result = interpreter.evaluate(&expr).unwrap();
 
         assert_eq!(result, Value::Number(-1.0));
     }
 
+    fn run(source: &str, options: Options) -> Result<String, String> {
+        let arena = Arena::new();
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts).unwrap();
+
+        let mut output = vec![];
+        let mut interpreter = Interpreter::with_output(resolver.analysis(), Box::new(&mut output));
+        interpreter.set_options(options);
+        let result = interpreter.interpret(&stmts).map_err(|e| e.to_string());
+        drop(interpreter);
+        result.map(|_| String::from_utf8(output).unwrap())
+    }
+
+    const PERSON: &str = "class Person {
+  init(name) {
+    this.name = name;
+    this.greet();
+  }
+  greet() { print \"hi \" + this.name; }
+}
+var person = Person(\"Ada\");
+person.name = \"Grace\";
+";
+
+    #[test]
+    fn strict_fields() {
+        let strict = Options {
+            strict_fields: true,
+            ..Default::default()
+        };
+
+        assert_eq!(run(PERSON, strict.clone()), Ok("hi Ada\n".to_string()));
+        assert_eq!(
+            run(&format!("{PERSON}person.nmae = 1;"), strict),
+            Err(
+                "[line 10] Can't create field 'nmae' outside of 'init' in strict mode.".to_string()
+            )
+        );
+        assert!(run(&format!("{PERSON}person.nmae = 1;"), Options::default()).is_ok());
+    }
+
+    #[test]
+    fn strict_variables() {
+        let strict = Options {
+            strict_variables: true,
+            ..Default::default()
+        };
+        let source = "var a;
+var b = nil;
+print b;
+fun f() {
+  var c;
+  c = 1;
+  print c;
+}
+f();
+print a;";
+
+        assert_eq!(
+            run(source, strict),
+            Err("[line 10] Variable 'a' used before assignment.".to_string())
+        );
+        assert_eq!(
+            run(source, Options::default()),
+            Ok("nil\n1\nnil\n".to_string())
+        );
+    }
+
+    #[test]
+    fn max_fields() {
+        let options = Options {
+            max_fields: Some(1),
+            ..Default::default()
+        };
+
+        assert!(run(PERSON, options.clone()).is_ok());
+        assert_eq!(
+            run(&format!("{PERSON}person.age = 1;"), options),
+            Err(
+                "[line 10] Can't create field 'age', instances can't have more than 1 fields."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn new_fields_through_updates() {
+        // A getter shadowed by the field the update creates
+        let source = "class Counter {
+  init() {}
+  count { return 0; }
+}
+var c = Counter();
+";
+        let strict = Options {
+            strict_fields: true,
+            ..Default::default()
+        };
+        let no_fields = Options {
+            max_fields: Some(0),
+            ..Default::default()
+        };
+
+        for update in ["c.count++;", "++c.count;", "c.count += 1;"] {
+            let source = format!("{source}{update}");
+            assert_eq!(
+                run(&source, strict.clone()),
+                Err(
+                    "[line 6] Can't create field 'count' outside of 'init' in strict mode."
+                        .to_string()
+                ),
+                "{update}"
+            );
+            assert_eq!(
+                run(&source, no_fields.clone()),
+                Err(
+                    "[line 6] Can't create field 'count', instances can't have more than 0 fields."
+                        .to_string()
+                ),
+                "{update}"
+            );
+            assert!(run(&source, Options::default()).is_ok(), "{update}");
+        }
+    }
+
+    #[test]
+    fn interpret_iter() {
+        let arena = Arena::new();
+        let tokens = Scanner::new("var a = 1; a + 1; print a; nil + 1; a;")
+            .scan_tokens()
+            .unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_output(Analysis::default(), Box::new(io::sink()));
+
+        let results: Vec<_> = interpreter
+            .interpret_iter(&stmts)
+            .map(|result| result.map_err(|e| e.to_string()))
+            .collect();
+
+        assert_eq!(
+            results,
+            vec![
+                Ok(None),
+                Ok(Some(Value::Number(2.0))),
+                Ok(None),
+                Err(
+                    "[line 1] Operands must be two numbers or two strings, got nil and number 1."
+                        .to_string()
+                ),
+                Ok(Some(Value::Number(1.0))),
+            ]
+        );
+    }
+
+    #[test]
+    fn native_errors_have_call_site() {
+        assert_eq!(
+            run(
+                "fun f(s) {\n  return char_at(s, 0);\n}\nf(1);",
+                Options::default()
+            ),
+            Err("[line 2] char_at: expected a string argument but got number.".to_string())
+        );
+    }
+
+    #[test]
+    fn interrupt() {
+        let arena = Arena::new();
+        let tokens = Scanner::new("print 1;").scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_output(Analysis::default(), Box::new(io::sink()));
+
+        let interrupt = interpreter.context().interrupt_handle();
+        interrupt.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        assert_eq!(
+            interpreter.interpret(&stmts),
+            Err(RuntimeError::Interrupted)
+        );
+    }
+
+    #[test]
+    fn interrupt_iter() {
+        let arena = Arena::new();
+        let tokens = Scanner::new("1; 2;").scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut interpreter = Interpreter::with_output(Analysis::default(), Box::new(io::sink()));
+        let interrupt = interpreter.context().interrupt_handle();
+
+        let mut results = interpreter.interpret_iter(&stmts);
+        assert_eq!(results.next(), Some(Ok(Some(Value::Number(1.0)))));
+        interrupt.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(results.next(), Some(Err(RuntimeError::Interrupted)));
+    }
+
+    /// Sources resolved one at a time, like the lines of a REPL or the loads of `--serve`. The `a`
+    /// read by `g` sits where the parameter of `f` does, but in another source.
+    #[test]
+    fn analyses_keep_variables_apart() {
+        let sources = [
+            "fun f(a) { return a; }",
+            "var a = 7;",
+            "fun g( ) { return a; }",
+            "print f(1); print g();",
+        ];
+        let arenas: Vec<Arena> = sources.iter().map(|_| Arena::new()).collect();
+        let programs: Vec<_> = sources
+            .iter()
+            .zip(&arenas)
+            .map(|(source, arena)| {
+                let tokens = Scanner::new(source).scan_tokens().unwrap();
+                Parser::new(TokenStream::new(tokens), arena)
+                    .parse()
+                    .unwrap()
+            })
+            .collect();
+
+        let mut output = vec![];
+        let mut interpreter = Interpreter::with_output(Analysis::default(), Box::new(&mut output));
+        for stmts in &programs {
+            let mut resolver = Resolver::new();
+            resolver.resolve(stmts).unwrap();
+            interpreter.add_analysis(resolver.analysis());
+            interpreter.interpret(stmts).unwrap();
+        }
+        drop(interpreter);
+        assert_eq!(output, b"1\n7\n");
+    }
+
     #[test]
     fn error() {
+        let one = Expr::Literal(ExprLiteral::new(LiteralValue::String("1".to_string())));
         let mut interpreter = setup();
 
         let span = Span { begin: 0, end: 1 };
         let token = Token::new(TokenType::Minus, "-", 1, span);
-        let expr = Expr::Unary(ExprUnary::new(
-            token,
-            Box::new(Expr::Literal(ExprLiteral::new(LiteralValue::String(
-                "1".to_string(),
-            )))),
-        ));
+        let expr = Expr::Unary(ExprUnary::new(token, &one));
         let result = interpreter.evaluate(&expr);
 
-        assert_eq!(result, Err(RuntimeError::NumberOperand { operator: token }));
+        assert_eq!(
+            result,
+            Err(RuntimeError::NumberOperand {
+                operator: token,
+                operand: "string \"1\"".to_string()
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "[line 1] Operand must be a number, got string \"1\"."
+        );
     }
 }
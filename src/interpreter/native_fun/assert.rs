@@ -0,0 +1,157 @@
+use super::{callable_argument, string_argument};
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::{NativeError, RuntimeError},
+    Interpreter, Value,
+};
+
+// Assertions for tests written in Lox. Failures are ordinary runtime errors, so they stop the
+// script and report the line of the failing call.
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AssertEq {
+    arity: Arity,
+}
+
+impl AssertEq {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for AssertEq {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let left = &arguments[0];
+        let right = &arguments[1];
+
+        match left == right {
+            true => Ok(Value::Nil),
+            false => Err(NativeError::AssertionFailed(diff(left, right)).into()),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "assert_eq"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// Describes how two unequal values differ. Multi-line strings point at the first differing line
+/// instead of printing both strings.
+fn diff(left: &Value, right: &Value) -> String {
+    if let (Value::String(l), Value::String(r)) = (left, right) {
+        if l.contains('\n') || r.contains('\n') {
+            let (line, (l, r)) = l
+                .split('\n')
+                .map(Some)
+                .chain(std::iter::repeat(None))
+                .zip(r.split('\n').map(Some).chain(std::iter::repeat(None)))
+                .enumerate()
+                .find(|(_, (l, r))| l != r)
+                .unwrap_or_default();
+            let show = |s: Option<&str>| s.map_or("<missing>".to_string(), |s| format!("\"{s}\""));
+            return format!(
+                "assert_eq: strings differ at line {}, left: {}, right: {}",
+                line + 1,
+                show(l),
+                show(r)
+            );
+        }
+    }
+
+    let show = |value: &Value| match value {
+        Value::String(s) => format!("\"{s}\" (string)"),
+        value => format!("{value} ({})", value.type_name()),
+    };
+    format!("assert_eq: left: {}, right: {}", show(left), show(right))
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AssertRaises {
+    arity: Arity,
+}
+
+impl AssertRaises {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for AssertRaises {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let callable = callable_argument("assert_raises", &arguments[0])?;
+        let expected = string_argument("assert_raises", &arguments[1])?;
+
+        if !callable.arity().accepts(0) {
+            return Err(NativeError::InvalidArgument {
+                native: "assert_raises",
+                expected: "a function without parameters",
+                got: format!("one taking {}", callable.arity()),
+            }
+            .into());
+        }
+
+        let message = match callable.call(interpreter, &[]) {
+            Ok(_) => "assert_raises: no error was raised".to_string(),
+            // Cancelling the script is not something a test can expect
+            Err(RuntimeError::Interrupted) => return Err(RuntimeError::Interrupted),
+            Err(error) if error.to_string().contains(&expected) => return Ok(Value::Nil),
+            Err(error) => format!(
+                "assert_raises: expected an error containing \"{expected}\" but got \"{error}\""
+            ),
+        };
+        Err(NativeError::AssertionFailed(message).into())
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "assert_raises"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_multiline_strings() {
+        let left = Value::String("a\nb\nc".to_string());
+        let right = Value::String("a\nx\nc".to_string());
+        assert_eq!(
+            diff(&left, &right),
+            "assert_eq: strings differ at line 2, left: \"b\", right: \"x\""
+        );
+
+        let shorter = Value::String("a\nb".to_string());
+        assert_eq!(
+            diff(&left, &shorter),
+            "assert_eq: strings differ at line 3, left: \"c\", right: <missing>"
+        );
+    }
+}
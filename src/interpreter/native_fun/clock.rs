@@ -1,36 +1,39 @@
-use std::collections::VecDeque;
-
-use crate::interpreter::{callable::LoxCallable, error::RuntimeError, Interpreter, Value};
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::RuntimeError,
+    Interpreter, Value,
+};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Clock {
-    arity: usize,
+    arity: Arity,
 }
 
 impl Clock {
     pub fn new() -> Self {
-        Self { arity: 0 }
+        Self {
+            arity: Arity::Exact(0),
+        }
     }
 }
 
 impl<'a> LoxCallable<'a> for Clock {
     fn call(
         &self,
-        _interpreter: &mut Interpreter<'a>,
-        _arguments: VecDeque<Value<'a>>,
+        interpreter: &mut Interpreter<'a>,
+        _arguments: &[Value<'a>],
     ) -> Result<Value<'a>, RuntimeError<'a>> {
-        Ok(Value::Number(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs_f64(),
-        ))
+        Ok(Value::Number(interpreter.context().now().as_secs_f64()))
     }
 
-    fn arity(&self) -> usize {
+    fn arity(&self) -> Arity {
         self.arity
     }
 
+    fn name(&self) -> &str {
+        "clock"
+    }
+
     fn to_string(&self) -> String {
         String::from("<native fn>")
     }
@@ -0,0 +1,269 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::string_argument;
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    class::{LoxClass, LoxInstance},
+    error::{NativeError, RuntimeError},
+    Interpreter, Value,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HttpGet {
+    arity: Arity,
+}
+
+impl HttpGet {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for HttpGet {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let url = string_argument("http_get", &arguments[0])?;
+        interpreter.context().check_interrupt()?;
+        Ok(response("http_get", ureq::get(&url).call())?)
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "http_get"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HttpPost {
+    arity: Arity,
+}
+
+impl HttpPost {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for HttpPost {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let url = string_argument("http_post", &arguments[0])?;
+        let body = string_argument("http_post", &arguments[1])?;
+        interpreter.context().check_interrupt()?;
+        Ok(response("http_post", ureq::post(&url).send_string(&body))?)
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "http_post"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+// Error statuses still carry a response the script may want to look at, so only transport
+// failures become runtime errors.
+fn response<'a>(
+    native: &'static str,
+    result: Result<ureq::Response, ureq::Error>,
+) -> Result<Value<'a>, NativeError> {
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => {
+            return Err(NativeError::Failed {
+                native,
+                message: e.to_string(),
+            })
+        }
+    };
+
+    let status = response.status();
+    let body = response.into_string().map_err(|e| NativeError::Failed {
+        native,
+        message: e.to_string(),
+    })?;
+
+    let instance = LoxInstance::new(Rc::new(LoxClass::new("Response", None, HashMap::new())));
+    {
+        let mut fields = instance.fields.borrow_mut();
+        fields.insert(Cow::Borrowed("status"), Value::Number(status.into()));
+        fields.insert(Cow::Borrowed("body"), Value::String(body));
+    }
+    Ok(Value::Instance(Rc::new(RefCell::new(instance))))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+    use crate::interpreter::{options::Options, resolver::Analysis};
+
+    fn call(
+        native: &dyn LoxCallable<'static>,
+        arguments: &[Value<'static>],
+    ) -> Result<Value<'static>, RuntimeError<'static>> {
+        native.call(&mut Interpreter::new(Analysis::default()), arguments)
+    }
+
+    /// A URL nothing listens on, the port was free a moment ago.
+    fn refused_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    }
+
+    /// Serves one request with `response` and returns the URL and the request it got.
+    fn serve_once(response: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            stream.write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (url, handle)
+    }
+
+    fn field(value: &Value<'static>, name: &str) -> Value<'static> {
+        let Value::Instance(instance) = value else {
+            panic!("expected an instance, got {value:?}");
+        };
+        let fields = instance.borrow().fields.borrow().clone();
+        fields[name].clone()
+    }
+
+    #[test]
+    fn only_defined_when_allowed() {
+        let mut interpreter = Interpreter::new(Analysis::default());
+        assert_eq!(interpreter.get_global("http_get"), None);
+        assert_eq!(interpreter.get_global("http_post"), None);
+
+        interpreter.set_options(Options {
+            allow_net: true,
+            ..Default::default()
+        });
+        assert!(interpreter.get_global("http_get").is_some());
+        assert!(interpreter.get_global("http_post").is_some());
+    }
+
+    #[test]
+    fn arguments_must_be_strings() {
+        assert_eq!(
+            call(&HttpGet::new(), &[Value::Number(1.0)]),
+            Err(RuntimeError::NativeError(NativeError::InvalidArgument {
+                native: "http_get",
+                expected: "a string",
+                got: "number".to_string(),
+            }))
+        );
+        assert_eq!(
+            call(
+                &HttpPost::new(),
+                &[Value::String("http://localhost/".to_string()), Value::Nil]
+            ),
+            Err(RuntimeError::NativeError(NativeError::InvalidArgument {
+                native: "http_post",
+                expected: "a string",
+                got: "nil".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn connection_failure_is_an_error() {
+        for (native, arguments) in [
+            (
+                &HttpGet::new() as &dyn LoxCallable,
+                vec![Value::String(refused_url())],
+            ),
+            (
+                &HttpPost::new(),
+                vec![
+                    Value::String(refused_url()),
+                    Value::String("{}".to_string()),
+                ],
+            ),
+        ] {
+            match call(native, &arguments) {
+                Err(RuntimeError::NativeError(NativeError::Failed {
+                    native: name,
+                    message,
+                })) => {
+                    assert_eq!(name, native.name());
+                    assert!(!message.is_empty());
+                }
+                other => panic!("{}: expected a failure, got {other:?}", native.name()),
+            }
+        }
+    }
+
+    #[test]
+    fn error_status_is_a_response() {
+        let (url, server) =
+            serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\n\r\nmissing");
+        let response = call(&HttpGet::new(), &[Value::String(url)]).unwrap();
+        assert_eq!(field(&response, "status"), Value::Number(404.0));
+        assert_eq!(
+            field(&response, "body"),
+            Value::String("missing".to_string())
+        );
+        assert!(server.join().unwrap().starts_with("GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn post_sends_the_body() {
+        let (url, server) = serve_once("HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok");
+        let response = call(
+            &HttpPost::new(),
+            &[Value::String(url), Value::String("{\"a\": 1}".to_string())],
+        )
+        .unwrap();
+        assert_eq!(field(&response, "status"), Value::Number(201.0));
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"a\": 1}"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::{Map, Number};
+
+use super::string_argument;
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::{NativeError, RuntimeError},
+    map::LoxMap,
+    value::HashableValue,
+    Interpreter, Value,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ToJson {
+    arity: Arity,
+}
+
+impl ToJson {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for ToJson {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let json = value_to_json(&arguments[0])?;
+        Ok(Value::String(json.to_string()))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "to_json"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FromJson {
+    arity: Arity,
+}
+
+impl FromJson {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for FromJson {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let text = string_argument("from_json", &arguments[0])?;
+
+        let json: serde_json::Value =
+            serde_json::from_str(&text).map_err(|e| NativeError::InvalidJson(e.to_string()))?;
+        Ok(value_from_json(json)?)
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "from_json"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+pub fn value_to_json(value: &Value) -> Result<serde_json::Value, NativeError> {
+    to_json(value, &mut vec![])
+}
+
+// `seen` holds the field tables of the instances, the lists and the maps being serialized. One
+// showing up again while its own contents are still being visited means the structure is cyclic.
+fn to_json(value: &Value, seen: &mut Vec<*const ()>) -> Result<serde_json::Value, NativeError> {
+    match value {
+        Value::Nil => Ok(serde_json::Value::Null),
+        Value::Boolean(b) => Ok(serde_json::Value::Bool(*b)),
+        Value::Number(n) => number_to_json(*n)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| NativeError::NotSerializable(n.to_string())),
+        Value::String(s) => Ok(serde_json::Value::String(s.clone())),
+        Value::Instance(instance) => {
+            let fields = instance.borrow().fields.clone();
+            let id = Rc::as_ptr(&fields) as *const ();
+            if seen.contains(&id) {
+                return Err(NativeError::CyclicStructure);
+            }
+
+            seen.push(id);
+            let mut object = Map::new();
+            for (name, field) in fields.borrow().iter() {
+                object.insert(name.to_string(), to_json(field, seen)?);
+            }
+            seen.pop();
+
+            Ok(serde_json::Value::Object(object))
+        }
+        Value::List(list) => {
+            let id = Rc::as_ptr(list) as *const ();
+            if seen.contains(&id) {
+                return Err(NativeError::CyclicStructure);
+            }
+
+            seen.push(id);
+            let array = list
+                .borrow()
+                .iter()
+                .map(|element| to_json(element, seen))
+                .collect::<Result<_, _>>()?;
+            seen.pop();
+
+            Ok(serde_json::Value::Array(array))
+        }
+        Value::Map(map) => {
+            let id = Rc::as_ptr(map) as *const ();
+            if seen.contains(&id) {
+                return Err(NativeError::CyclicStructure);
+            }
+
+            seen.push(id);
+            let mut object = Map::new();
+            // JSON keys are strings, other keys are written the way `print` shows them
+            for (key, value) in map.borrow().iter() {
+                let key = Value::from(key.clone()).to_string();
+                object.insert(key, to_json(value, seen)?);
+            }
+            seen.pop();
+
+            Ok(serde_json::Value::Object(object))
+        }
+        Value::Callable(callable) => Err(NativeError::NotSerializable(callable.to_string())),
+    }
+}
+
+fn number_to_json(n: f64) -> Option<Number> {
+    // Lox only has doubles, but `1` reads better than `1.0` in the output
+    if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 {
+        return Some(Number::from(n as i64));
+    }
+    Number::from_f64(n)
+}
+
+pub fn value_from_json<'a>(json: serde_json::Value) -> Result<Value<'a>, NativeError> {
+    match json {
+        serde_json::Value::Null => Ok(Value::Nil),
+        serde_json::Value::Bool(b) => Ok(Value::Boolean(b)),
+        serde_json::Value::Number(n) => Ok(Value::Number(n.as_f64().unwrap_or(f64::NAN))),
+        serde_json::Value::String(s) => Ok(Value::String(s)),
+        serde_json::Value::Object(object) => {
+            let mut map = LoxMap::new();
+            for (key, value) in object {
+                map.insert(HashableValue::String(key), value_from_json(value)?);
+            }
+            Ok(Value::Map(Rc::new(RefCell::new(map))))
+        }
+        serde_json::Value::Array(array) => {
+            let list = array
+                .into_iter()
+                .map(value_from_json)
+                .collect::<Result<_, _>>()?;
+            Ok(Value::List(Rc::new(RefCell::new(list))))
+        }
+    }
+}
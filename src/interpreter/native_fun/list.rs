@@ -0,0 +1,402 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use super::{callable_argument, list_argument};
+use crate::interpreter::{
+    callable::{Arity, Callable, LoxCallable},
+    error::{NativeError, RuntimeError},
+    Interpreter, Value,
+};
+
+// Lists are shared like instances, `push` and `pop` change the list in place. Their length is
+// `len(list)`. `sort`, `sort_by`, `map` and `filter` return a new list.
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Push {
+    arity: Arity,
+}
+
+impl Push {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Push {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("push", &arguments[0])?;
+        list.borrow_mut().push(arguments[1].clone());
+        Ok(Value::Nil)
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Pop {
+    arity: Arity,
+}
+
+impl Pop {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Pop {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("pop", &arguments[0])?;
+        let value = list.borrow_mut().pop();
+        value.ok_or_else(|| {
+            NativeError::Failed {
+                native: "pop",
+                message: "the list is empty".to_string(),
+            }
+            .into()
+        })
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "pop"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Sort {
+    arity: Arity,
+}
+
+impl Sort {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Sort {
+    /// Sorts numbers by value and strings by their bytes, a list mixing the two or holding
+    /// anything else can't be sorted.
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("sort", &arguments[0])?;
+        let elements = list.borrow().clone();
+        let sorted = merge_sort(elements, &mut |a, b| match (a, b) {
+            (Value::Number(a), Value::Number(b)) => Ok(a.total_cmp(b)),
+            (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+            (a, b) => Err(RuntimeError::from(NativeError::Failed {
+                native: "sort",
+                message: format!("can't compare {} with {}", a.describe(), b.describe()),
+            })),
+        })?;
+        Ok(Value::List(Rc::new(RefCell::new(sorted))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "sort"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SortBy {
+    arity: Arity,
+}
+
+impl SortBy {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for SortBy {
+    /// `comparator(a, b)` returns a negative number if `a` goes first, a positive one if `b` does
+    /// and `0` if they're equal, those keep their order. An error in the comparator stops the
+    /// sort and is the error of the call.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("sort_by", &arguments[0])?;
+        let comparator = callback_argument("sort_by", &arguments[1], 2)?;
+        // Copied first, the comparator may change the list
+        let elements = list.borrow().clone();
+        let sorted = merge_sort(elements, &mut |a, b| match comparator
+            .call(interpreter, &[a.clone(), b.clone()])?
+        {
+            Value::Number(n) => Ok(n.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+            other => Err(RuntimeError::from(NativeError::Failed {
+                native: "sort_by",
+                message: format!(
+                    "the comparator must return a number, got {}",
+                    other.describe()
+                ),
+            })),
+        })?;
+        Ok(Value::List(Rc::new(RefCell::new(sorted))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "sort_by"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Map {
+    arity: Arity,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Map {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("map", &arguments[0])?;
+        let function = callback_argument("map", &arguments[1], 1)?;
+        // Copied first, the function may change the list
+        let elements = list.borrow().clone();
+        let mapped = elements
+            .into_iter()
+            .map(|element| function.call(interpreter, &[element]))
+            .collect::<Result<_, _>>()?;
+        Ok(Value::List(Rc::new(RefCell::new(mapped))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "map"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Filter {
+    arity: Arity,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Filter {
+    /// Keeps the elements the predicate returns a truthy value for.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("filter", &arguments[0])?;
+        let predicate = callback_argument("filter", &arguments[1], 1)?;
+        let elements = list.borrow().clone();
+        let mut kept = Vec::new();
+        for element in elements {
+            if predicate
+                .call(interpreter, std::slice::from_ref(&element))?
+                .is_truthy()
+            {
+                kept.push(element);
+            }
+        }
+        Ok(Value::List(Rc::new(RefCell::new(kept))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "filter"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Reduce {
+    arity: Arity,
+}
+
+impl Reduce {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(3),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Reduce {
+    /// `reduce(list, function, initial)` calls `function(accumulator, element)` for each element,
+    /// starting with `initial`, and returns the last result. An empty list gives `initial`.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let list = list_argument("reduce", &arguments[0])?;
+        let function = callback_argument("reduce", &arguments[1], 2)?;
+        let elements = list.borrow().clone();
+        elements
+            .into_iter()
+            .try_fold(arguments[2].clone(), |accumulator, element| {
+                function.call(interpreter, &[accumulator, element])
+            })
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "reduce"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// A function the native calls with `arguments` arguments.
+fn callback_argument<'a>(
+    native: &'static str,
+    value: &Value<'a>,
+    arguments: usize,
+) -> Result<Callable<'a>, NativeError> {
+    let callable = callable_argument(native, value)?;
+    if !callable.arity().accepts(arguments) {
+        return Err(NativeError::InvalidArgument {
+            native,
+            expected: match arguments {
+                1 => "a function with one parameter",
+                _ => "a function with two parameters",
+            },
+            got: format!("one taking {}", callable.arity()),
+        });
+    }
+    Ok(callable)
+}
+
+/// A stable merge sort that stops at the first error of `compare`. The standard library's sorts
+/// can't fail and may panic if `compare` isn't a total order, which a comparator written in Lox
+/// doesn't have to be.
+fn merge_sort<T, E>(
+    mut items: Vec<T>,
+    compare: &mut impl FnMut(&T, &T) -> Result<Ordering, E>,
+) -> Result<Vec<T>, E> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        if compare(l, r)? == Ordering::Greater {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_sort_is_stable() {
+        let items = vec![(2, 'a'), (1, 'b'), (2, 'c'), (0, 'd'), (1, 'e')];
+        let sorted = merge_sort(items, &mut |a, b| Ok::<_, ()>(a.0.cmp(&b.0))).unwrap();
+        assert_eq!(
+            sorted,
+            vec![(0, 'd'), (1, 'b'), (1, 'e'), (2, 'a'), (2, 'c')]
+        );
+    }
+
+    #[test]
+    fn merge_sort_stops_at_error() {
+        let mut calls = 0;
+        let result = merge_sort(vec![3, 2, 1, 0], &mut |_, _| {
+            calls += 1;
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{cell::RefCell, rc::Rc};
+
+use super::{key_argument, map_argument};
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::RuntimeError,
+    Interpreter, Value,
+};
+
+// Maps are shared like lists, `remove` changes the map in place. `keys` and `values` return new
+// lists in insertion order.
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Keys {
+    arity: Arity,
+}
+
+impl Keys {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Keys {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let map = map_argument("keys", &arguments[0])?;
+        let keys = map
+            .borrow()
+            .iter()
+            .map(|(key, _)| key.clone().into())
+            .collect();
+        Ok(Value::List(Rc::new(RefCell::new(keys))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "keys"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Values {
+    arity: Arity,
+}
+
+impl Values {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Values {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let map = map_argument("values", &arguments[0])?;
+        let values = map
+            .borrow()
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect();
+        Ok(Value::List(Rc::new(RefCell::new(values))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "values"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Has {
+    arity: Arity,
+}
+
+impl Has {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Has {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let map = map_argument("has", &arguments[0])?;
+        let key = key_argument(interpreter, "has", &map, &arguments[1])?;
+        let has = map.borrow().contains_key(&key);
+        Ok(Value::Boolean(has))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "has"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Remove {
+    arity: Arity,
+}
+
+impl Remove {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Remove {
+    /// Returns the removed value, `nil` if the key wasn't in the map.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let map = map_argument("remove", &arguments[0])?;
+        let key = key_argument(interpreter, "remove", &map, &arguments[1])?;
+        let value = map.borrow_mut().remove(&key);
+        Ok(value.unwrap_or_default())
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "remove"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
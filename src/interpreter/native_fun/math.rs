@@ -0,0 +1,211 @@
+use super::number_argument;
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::{NativeError, RuntimeError},
+    Interpreter, Value,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Min {
+    arity: Arity,
+}
+
+impl Min {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::AtLeast(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Min {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let mut min = f64::INFINITY;
+        for argument in arguments {
+            min = min.min(number_argument("min", argument)?);
+        }
+        Ok(Value::Number(min))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "min"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Max {
+    arity: Arity,
+}
+
+impl Max {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::AtLeast(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Max {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let mut max = f64::NEG_INFINITY;
+        for argument in arguments {
+            max = max.max(number_argument("max", argument)?);
+        }
+        Ok(Value::Number(max))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "max"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// A native applying a function of one number, like `floor` or `sqrt`.
+#[derive(Debug, Clone, Copy)]
+pub struct Unary {
+    name: &'static str,
+    function: fn(f64) -> f64,
+}
+
+impl Unary {
+    pub fn new(name: &'static str, function: fn(f64) -> f64) -> Self {
+        Self { name, function }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Unary {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let x = number_argument(self.name, &arguments[0])?;
+        Ok(Value::Number((self.function)(x)))
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Exact(1)
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// Reads the `x, lo, hi` arguments shared by `clamp` and `between`.
+fn bounded_arguments(
+    native: &'static str,
+    arguments: &[Value],
+) -> Result<(f64, f64, f64), NativeError> {
+    let x = number_argument(native, &arguments[0])?;
+    let lo = number_argument(native, &arguments[1])?;
+    let hi = number_argument(native, &arguments[2])?;
+
+    if lo > hi {
+        return Err(NativeError::Failed {
+            native,
+            message: format!("lower bound {lo} is greater than upper bound {hi}"),
+        });
+    }
+    Ok((x, lo, hi))
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Clamp {
+    arity: Arity,
+}
+
+impl Clamp {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(3),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Clamp {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let (x, lo, hi) = bounded_arguments("clamp", arguments)?;
+        Ok(Value::Number(x.clamp(lo, hi)))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "clamp"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// Whether `lo <= x <= hi`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Between {
+    arity: Arity,
+}
+
+impl Between {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(3),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Between {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let (x, lo, hi) = bounded_arguments("between", arguments)?;
+        Ok(Value::Boolean((lo..=hi).contains(&x)))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "between"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
@@ -1 +1,271 @@
+use super::{
+    callable::Callable,
+    error::{NativeError, RuntimeError},
+    globals::Globals,
+    value::{HashableValue, ListRef, MapRef, Value},
+    Interpreter,
+};
+
+pub mod assert;
 pub mod clock;
+#[cfg(feature = "net")]
+pub mod http;
+pub mod json;
+pub mod list;
+pub mod map;
+pub mod math;
+pub mod reflect;
+pub mod string;
+pub mod version;
+
+/// The numbers defined as globals next to the natives, by the VM too.
+pub const CONSTANTS: [(&str, f64); 4] = [
+    ("NaN", f64::NAN),
+    ("Infinity", f64::INFINITY),
+    ("PI", std::f64::consts::PI),
+    ("E", std::f64::consts::E),
+];
+
+pub fn define_globals(globals: &mut Globals) {
+    let natives: Vec<(&str, Value)> = vec![
+        (
+            "clock",
+            Value::Callable(Callable::native(clock::Clock::new())),
+        ),
+        (
+            "assert_eq",
+            Value::Callable(Callable::native(assert::AssertEq::new())),
+        ),
+        (
+            "assert_raises",
+            Value::Callable(Callable::native(assert::AssertRaises::new())),
+        ),
+        (
+            "to_json",
+            Value::Callable(Callable::native(json::ToJson::new())),
+        ),
+        (
+            "from_json",
+            Value::Callable(Callable::native(json::FromJson::new())),
+        ),
+        ("min", Value::Callable(Callable::native(math::Min::new()))),
+        ("max", Value::Callable(Callable::native(math::Max::new()))),
+        (
+            "floor",
+            Value::Callable(Callable::native(math::Unary::new("floor", f64::floor))),
+        ),
+        (
+            "ceil",
+            Value::Callable(Callable::native(math::Unary::new("ceil", f64::ceil))),
+        ),
+        (
+            "abs",
+            Value::Callable(Callable::native(math::Unary::new("abs", f64::abs))),
+        ),
+        (
+            "sqrt",
+            Value::Callable(Callable::native(math::Unary::new("sqrt", f64::sqrt))),
+        ),
+        (
+            "clamp",
+            Value::Callable(Callable::native(math::Clamp::new())),
+        ),
+        (
+            "between",
+            Value::Callable(Callable::native(math::Between::new())),
+        ),
+        (
+            "is_function",
+            Value::Callable(Callable::native(reflect::IsCallType::function())),
+        ),
+        (
+            "is_class",
+            Value::Callable(Callable::native(reflect::IsCallType::class())),
+        ),
+        (
+            "is_native",
+            Value::Callable(Callable::native(reflect::IsCallType::native())),
+        ),
+        (
+            "arity",
+            Value::Callable(Callable::native(reflect::ArityOf::new())),
+        ),
+        (
+            "name",
+            Value::Callable(Callable::native(reflect::NameOf::new())),
+        ),
+        ("len", Value::Callable(Callable::native(string::Len::new()))),
+        (
+            "len_chars",
+            Value::Callable(Callable::native(string::LenChars::new())),
+        ),
+        (
+            "len_bytes",
+            Value::Callable(Callable::native(string::LenBytes::new())),
+        ),
+        (
+            "substr",
+            Value::Callable(Callable::native(string::Substr::new())),
+        ),
+        (
+            "char_at",
+            Value::Callable(Callable::native(string::CharAt::new())),
+        ),
+        (
+            "to_string",
+            Value::Callable(Callable::native(string::Stringify::new())),
+        ),
+        ("push", Value::Callable(Callable::native(list::Push::new()))),
+        ("pop", Value::Callable(Callable::native(list::Pop::new()))),
+        ("sort", Value::Callable(Callable::native(list::Sort::new()))),
+        (
+            "sort_by",
+            Value::Callable(Callable::native(list::SortBy::new())),
+        ),
+        ("map", Value::Callable(Callable::native(list::Map::new()))),
+        (
+            "filter",
+            Value::Callable(Callable::native(list::Filter::new())),
+        ),
+        (
+            "reduce",
+            Value::Callable(Callable::native(list::Reduce::new())),
+        ),
+        ("keys", Value::Callable(Callable::native(map::Keys::new()))),
+        (
+            "values",
+            Value::Callable(Callable::native(map::Values::new())),
+        ),
+        ("has", Value::Callable(Callable::native(map::Has::new()))),
+        (
+            "remove",
+            Value::Callable(Callable::native(map::Remove::new())),
+        ),
+        (
+            "version",
+            Value::Callable(Callable::native(version::Version::new())),
+        ),
+        (
+            "has_feature",
+            Value::Callable(Callable::native(version::HasFeature::new())),
+        ),
+    ];
+
+    for (name, native) in natives {
+        globals.define(name, Some(native));
+    }
+    for (name, value) in CONSTANTS {
+        globals.define(name, Some(Value::Number(value)));
+    }
+}
+
+/// The natives reaching the network, only defined when `Options::allow_net` is set.
+#[cfg(feature = "net")]
+pub fn define_net_globals(globals: &mut Globals) {
+    globals.define(
+        "http_get",
+        Some(Value::Callable(Callable::native(http::HttpGet::new()))),
+    );
+    globals.define(
+        "http_post",
+        Some(Value::Callable(Callable::native(http::HttpPost::new()))),
+    );
+}
+
+fn string_argument(native: &'static str, value: &Value) -> Result<String, NativeError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a string",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn list_argument<'a>(native: &'static str, value: &Value<'a>) -> Result<ListRef<'a>, NativeError> {
+    match value {
+        Value::List(list) => Ok(list.clone()),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a list",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn map_argument<'a>(native: &'static str, value: &Value<'a>) -> Result<MapRef<'a>, NativeError> {
+    match value {
+        Value::Map(map) => Ok(map.clone()),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a map",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Instances are keys if their class defines `hash()`, which can fail, and are looked up with their
+/// `equals()` method among the keys of `map`.
+fn key_argument<'a>(
+    interpreter: &mut Interpreter<'a>,
+    native: &'static str,
+    map: &MapRef<'a>,
+    value: &Value<'a>,
+) -> Result<HashableValue<'a>, RuntimeError<'a>> {
+    let value = match HashableValue::try_from(value.clone()) {
+        Ok(key) => return Ok(key),
+        Err(value) => value,
+    };
+    if let Value::Instance(instance) = &value {
+        if let Some(key) = interpreter.instance_key(instance)? {
+            return interpreter.find_key(map, key);
+        }
+    }
+    Err(RuntimeError::NativeError(NativeError::InvalidArgument {
+        native,
+        expected: "a number, string, boolean, nil or an instance with hash()",
+        got: value.type_name().to_string(),
+    }))
+}
+
+fn callable_argument<'a>(
+    native: &'static str,
+    value: &Value<'a>,
+) -> Result<Callable<'a>, NativeError> {
+    match value {
+        Value::Callable(callable) => Ok(callable.clone()),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a function",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn number_argument(native: &'static str, value: &Value) -> Result<f64, NativeError> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a number",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn index_argument(native: &'static str, value: &Value) -> Result<usize, NativeError> {
+    match value {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        Value::Number(n) => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a non-negative integer",
+            got: n.to_string(),
+        }),
+        other => Err(NativeError::InvalidArgument {
+            native,
+            expected: "a non-negative integer",
+            got: other.type_name().to_string(),
+        }),
+    }
+}
@@ -0,0 +1,142 @@
+use super::callable_argument;
+use crate::interpreter::{
+    callable::{Arity, CallType, LoxCallable},
+    error::RuntimeError,
+    Interpreter, Value,
+};
+
+// Introspection of callables. Natives count as functions, `is_native` tells them apart from
+// functions written in Lox.
+
+/// `is_function`, `is_class` and `is_native`, which only differ in the call types they accept.
+#[derive(Debug, Clone, Copy)]
+pub struct IsCallType {
+    name: &'static str,
+    call_types: &'static [CallType],
+    arity: Arity,
+}
+
+impl IsCallType {
+    pub fn function() -> Self {
+        Self::new("is_function", &[CallType::Function, CallType::Native])
+    }
+
+    pub fn class() -> Self {
+        Self::new("is_class", &[CallType::Class])
+    }
+
+    pub fn native() -> Self {
+        Self::new("is_native", &[CallType::Native])
+    }
+
+    fn new(name: &'static str, call_types: &'static [CallType]) -> Self {
+        Self {
+            name,
+            call_types,
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for IsCallType {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let is = match &arguments[0] {
+            Value::Callable(callable) => self.call_types.contains(&callable.call_type()),
+            _ => false,
+        };
+        Ok(Value::Boolean(is))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// The number of arguments a callable needs. For natives taking a variable number of arguments
+/// this is the minimum.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ArityOf {
+    arity: Arity,
+}
+
+impl ArityOf {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for ArityOf {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let callable = callable_argument("arity", &arguments[0])?;
+        let min = match callable.arity() {
+            Arity::Exact(n) | Arity::AtLeast(n) | Arity::Between(n, _) => n,
+        };
+        Ok(Value::Number(min as f64))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "arity"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NameOf {
+    arity: Arity,
+}
+
+impl NameOf {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for NameOf {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let callable = callable_argument("name", &arguments[0])?;
+        Ok(Value::String(callable.name().to_string()))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "name"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
@@ -0,0 +1,259 @@
+use super::{index_argument, string_argument};
+use crate::interpreter::{
+    callable::{Arity, LoxCallable},
+    error::{NativeError, RuntimeError},
+    Interpreter, Value,
+};
+
+// Strings are indexed and measured in chars (Unicode scalar values), so slicing never lands in
+// the middle of a multi-byte character. `len_bytes` is there for when the UTF-8 size matters.
+// `len` measures lists and maps as well, `len_chars` only strings.
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Len {
+    arity: Arity,
+}
+
+impl Len {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Len {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        match &arguments[0] {
+            Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+            Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+            Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+            other => Err(NativeError::InvalidArgument {
+                native: "len",
+                expected: "a string, list or map",
+                got: other.type_name().to_string(),
+            }
+            .into()),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "len"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LenChars {
+    arity: Arity,
+}
+
+impl LenChars {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for LenChars {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let s = string_argument("len_chars", &arguments[0])?;
+        Ok(Value::Number(s.chars().count() as f64))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "len_chars"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LenBytes {
+    arity: Arity,
+}
+
+impl LenBytes {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for LenBytes {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let s = string_argument("len_bytes", &arguments[0])?;
+        Ok(Value::Number(s.len() as f64))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "len_bytes"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Substr {
+    arity: Arity,
+}
+
+impl Substr {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(3),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Substr {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let s = string_argument("substr", &arguments[0])?;
+        let start = index_argument("substr", &arguments[1])?;
+        let len = index_argument("substr", &arguments[2])?;
+
+        let char_count = s.chars().count();
+        if start > char_count {
+            return Err(NativeError::IndexOutOfBounds {
+                native: "substr",
+                index: start,
+                len: char_count,
+            }
+            .into());
+        }
+
+        // A length running past the end is cut off at the end of the string
+        Ok(Value::String(s.chars().skip(start).take(len).collect()))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "substr"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CharAt {
+    arity: Arity,
+}
+
+impl CharAt {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(2),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for CharAt {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let s = string_argument("char_at", &arguments[0])?;
+        let index = index_argument("char_at", &arguments[1])?;
+
+        match s.chars().nth(index) {
+            Some(c) => Ok(Value::String(c.to_string())),
+            None => Err(NativeError::IndexOutOfBounds {
+                native: "char_at",
+                index,
+                len: s.chars().count(),
+            }
+            .into()),
+        }
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "char_at"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// Converts any value to the string `print` would show for it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stringify {
+    arity: Arity,
+}
+
+impl Stringify {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Stringify {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        Ok(Value::String(arguments[0].to_string()))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "to_string"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
@@ -0,0 +1,99 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::string_argument;
+use crate::{
+    interpreter::{
+        callable::{Arity, LoxCallable},
+        class::{LoxClass, LoxInstance},
+        error::RuntimeError,
+        Interpreter, Value,
+    },
+    version,
+};
+
+/// Returns an object with the `version`, `git`, `dialect` and `features` of the interpreter.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Version {
+    arity: Arity,
+}
+
+impl Version {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(0),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for Version {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        _arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let instance = LoxInstance::new(Rc::new(LoxClass::new("Version", None, HashMap::new())));
+        {
+            let mut fields = instance.fields.borrow_mut();
+            let info = [
+                ("version", version::VERSION),
+                ("git", version::GIT_HASH),
+                ("dialect", version::DIALECT),
+                ("features", version::FEATURES),
+            ];
+            for (name, value) in info {
+                fields.insert(Cow::Borrowed(name), Value::String(value.to_string()));
+            }
+        }
+        Ok(Value::Instance(Rc::new(RefCell::new(instance))))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "version"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
+
+/// Whether a language addition like `break_continue` or a cargo feature like `net` is available,
+/// so scripts can fall back when run on a build without it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HasFeature {
+    arity: Arity,
+}
+
+impl HasFeature {
+    pub fn new() -> Self {
+        Self {
+            arity: Arity::Exact(1),
+        }
+    }
+}
+
+impl<'a> LoxCallable<'a> for HasFeature {
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter<'a>,
+        arguments: &[Value<'a>],
+    ) -> Result<Value<'a>, RuntimeError<'a>> {
+        let name = string_argument("has_feature", &arguments[0])?;
+        Ok(Value::Boolean(version::has_feature(&name)))
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        "has_feature"
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn>")
+    }
+}
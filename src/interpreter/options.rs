@@ -0,0 +1,52 @@
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Printed values longer than this many characters are cut off with a truncation marker.
+    pub max_print_len: Option<usize>,
+    /// Instances can't have more than this many fields.
+    pub max_fields: Option<usize>,
+    /// New fields can only be created while the instance's `init` runs, so a typo like
+    /// `obj.nmae = 1` is an error instead of silently creating a field.
+    pub strict_fields: bool,
+    /// Reading a variable declared without a value before anything was assigned to it is an
+    /// error instead of yielding `nil`.
+    pub strict_variables: bool,
+    /// Defines the `http_get` and `http_post` natives, without it scripts can't reach the network.
+    #[cfg(feature = "net")]
+    pub allow_net: bool,
+}
+
+impl Options {
+    pub fn truncate(&self, text: String) -> String {
+        let Some(max) = self.max_print_len else {
+            return text;
+        };
+
+        let len = text.chars().count();
+        if len <= max {
+            return text;
+        }
+
+        let kept: String = text.chars().take(max).collect();
+        format!("{kept}... ({} more characters)", len - max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate() {
+        let options = Options {
+            max_print_len: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(options.truncate("abc".to_string()), "abc");
+        assert_eq!(
+            options.truncate("äbcdef".to_string()),
+            "äbc... (3 more characters)"
+        );
+        assert_eq!(Options::default().truncate("abcdef".to_string()), "abcdef");
+    }
+}
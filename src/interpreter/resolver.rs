@@ -1,9 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{ast::*, lex::Token};
+use crate::{
+    ast::*,
+    lex::{Token, TokenType},
+    lint::{Lint, Warning},
+};
 
 use super::error::ResolverError;
 
+fn ungroup<'e, 'a>(mut expr: &'e Expr<'a>) -> &'e Expr<'a> {
+    while let Expr::Grouping(grouping) = expr {
+        expr = grouping.value;
+    }
+    expr
+}
+
 #[derive(Default, Copy, Clone, PartialEq)]
 pub enum FunctionType {
     #[default]
@@ -21,21 +32,52 @@ pub enum ClassType {
     Subclass,
 }
 
+/// What the Resolver found out about a program, handed to the Interpreter that runs it.
+#[derive(Debug, Default, Clone)]
+pub struct Analysis<'a> {
+    /// How many scopes up each local variable expression finds its variable
+    pub locals: HashMap<NodeId, usize>,
+    /// The variable expressions that refer to globals, with the name they refer to
+    pub globals: Vec<(NodeId, &'a str)>,
+    /// Lint warnings in source order
+    pub warnings: Vec<Warning<'a>>,
+}
+
+impl<'a> Analysis<'a> {
+    /// Takes the warnings so they can be reported before the analysis is handed on.
+    pub fn take_warnings(&mut self) -> Vec<Warning<'a>> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
 #[derive(Default)]
 pub struct Resolver<'a> {
     scopes: Vec<HashMap<&'a str, bool>>,
-    locals: HashMap<Expr<'a>, usize>,
+    /// Variables declared with `var` in each scope that haven't been used yet
+    unused: Vec<Vec<Token<'a>>>,
+    locals: HashMap<NodeId, usize>,
+    global_exprs: Vec<(NodeId, &'a str)>,
     current_function: FunctionType,
     current_class: ClassType,
+    /// Number of loops around the current statement inside the current function
+    loop_depth: usize,
+    warnings: Vec<Warning<'a>>,
+    /// Globals declared so far
+    globals: HashSet<&'a str>,
 }
 
 impl<'a, 'b: 'a> Resolver<'a> {
     pub fn new() -> Self {
         Self {
             scopes: vec![],
+            unused: vec![],
+            globals: HashSet::new(),
             locals: HashMap::new(),
+            global_exprs: vec![],
             current_function: FunctionType::None,
             current_class: ClassType::None,
+            loop_depth: 0,
+            warnings: vec![],
         }
     }
 
@@ -43,8 +85,20 @@ impl<'a, 'b: 'a> Resolver<'a> {
         stmts.iter().try_for_each(|stmt| self.resolve_stmt(stmt))
     }
 
-    pub fn get_locals(self) -> HashMap<Expr<'a>, usize> {
-        self.locals
+    /// Takes the warnings found so far, in source order.
+    pub fn take_warnings(&mut self) -> Vec<Warning<'a>> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| warning.token.span.begin);
+        warnings
+    }
+
+    /// Finishes the resolution of everything passed to `resolve` so far.
+    pub fn analysis(mut self) -> Analysis<'a> {
+        Analysis {
+            warnings: self.take_warnings(),
+            locals: self.locals,
+            globals: self.global_exprs,
+        }
     }
 
     fn resolve_stmt(&mut self, stmt: &'b Stmt<'a>) -> Result<(), ResolverError<'a>> {
@@ -61,13 +115,23 @@ impl<'a, 'b: 'a> Resolver<'a> {
 
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.unused.push(vec![]);
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        let unused = self.unused.pop().unwrap_or_default();
+        self.warnings
+            .extend(unused.into_iter().map(|token| Warning {
+                lint: Lint::UnusedVariable,
+                token,
+            }));
     }
 
     fn declare(&mut self, name: &Token<'a>) -> Result<(), ResolverError<'a>> {
+        if name.kind.is_keyword() {
+            return Err(ResolverError::ReservedName { token: *name });
+        }
         if self.scopes.is_empty() {
             return Ok(());
         }
@@ -95,13 +159,72 @@ impl<'a, 'b: 'a> Resolver<'a> {
         }
     }
 
-    fn resolve_local(&mut self, expr: Expr<'a>, name: Token<'a>) {
+    /// Warns about `if (a = b)`, which is almost always a mistyped `==`, and about conditions
+    /// that are constants. A parenthesized assignment is taken as intended, an endless
+    /// `while (true)` too.
+    fn check_condition(&mut self, keyword: Token<'a>, condition: &Expr<'a>) {
+        let equals = match condition {
+            Expr::Assign(assign) => Some(assign.equals),
+            Expr::Set(set) => Some(set.equals),
+            Expr::SetIndex(set) => Some(set.equals),
+            Expr::Compound(compound) => Some(compound.equals),
+            _ => None,
+        };
+        if let Some(equals) = equals {
+            self.warnings.push(Warning {
+                lint: Lint::AssignmentInCondition,
+                token: equals,
+            });
+        }
+
+        let value = match ungroup(condition) {
+            Expr::Assign(assign) => ungroup(assign.value),
+            Expr::Set(set) => ungroup(set.value),
+            Expr::SetIndex(set) => ungroup(set.value),
+            Expr::Compound(compound) => ungroup(compound.value),
+            Expr::Literal(literal)
+                if keyword.kind != TokenType::If && literal.value == LiteralValue::Bool(true) =>
+            {
+                return;
+            }
+            condition => condition,
+        };
+        if let Expr::Literal(_) = value {
+            self.warnings.push(Warning {
+                lint: Lint::ConstantCondition,
+                token: keyword,
+            });
+        }
+    }
+
+    /// A global declared again replaces the earlier one when the declaration runs. For a function
+    /// or class that is usually a mistake, `lint` tells which warning to report.
+    fn check_redeclaration(&mut self, name: &Token<'a>, lint: Lint) {
+        if !self.scopes.is_empty() {
+            return;
+        }
+        if !self.globals.insert(name.lexeme) {
+            self.warnings.push(Warning { lint, token: *name });
+        }
+    }
+
+    /// Records how many scopes up the expression `id` finds `name`, or that it refers to a global.
+    fn resolve_local(&mut self, id: NodeId, name: Token<'a>) {
         for (i, scope) in self.scopes.iter().enumerate().rev() {
             if scope.contains_key(name.lexeme) {
-                self.locals.insert(expr, self.scopes.len() - 1 - i);
+                self.locals.insert(id, self.scopes.len() - 1 - i);
+                self.unused[i].retain(|token| token.lexeme != name.lexeme);
                 return;
             }
         }
+        self.global_exprs.push((id, name.lexeme));
+    }
+
+    fn check_in_loop(&self, keyword: Token<'a>) -> Result<(), ResolverError<'a>> {
+        match self.loop_depth {
+            0 => Err(ResolverError::LoopControlOutsideLoop { token: keyword }),
+            _ => Ok(()),
+        }
     }
 
     fn resolve_function(
@@ -111,6 +234,8 @@ impl<'a, 'b: 'a> Resolver<'a> {
     ) -> Result<(), ResolverError<'a>> {
         let enclosing_function = self.current_function;
         self.current_function = fn_type;
+        // `break` and `continue` can't leave a function
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
 
         self.begin_scope();
         function
@@ -121,11 +246,12 @@ impl<'a, 'b: 'a> Resolver<'a> {
                 self.define(param);
                 Ok(())
             })?;
-        self.resolve_stmts(&function.body)?;
+        let body = self.resolve_stmts(&function.body);
         self.end_scope();
 
         self.current_function = enclosing_function;
-        Ok(())
+        self.loop_depth = enclosing_loop_depth;
+        body
     }
 }
 
@@ -137,19 +263,19 @@ impl<'a, 'b: 'a> ExprVisitor<'a, 'b> for Resolver<'a> {
     }
 
     fn visit_grouping(&mut self, node: &'b ExprGrouping<'a>) -> Self::Output {
-        self.resolve_expr(&node.value)?;
+        self.resolve_expr(node.value)?;
         Ok(())
     }
 
     fn visit_logical(&mut self, node: &'b ExprLogical<'a>) -> Self::Output {
-        self.resolve_expr(&node.left)?;
-        self.resolve_expr(&node.right)?;
+        self.resolve_expr(node.left)?;
+        self.resolve_expr(node.right)?;
         Ok(())
     }
 
     fn visit_set(&mut self, node: &'b ExprSet<'a>) -> Self::Output {
-        self.resolve_expr(&node.value)?;
-        self.resolve_expr(&node.object)?;
+        self.resolve_expr(node.value)?;
+        self.resolve_expr(node.object)?;
         Ok(())
     }
 
@@ -163,7 +289,7 @@ impl<'a, 'b: 'a> ExprVisitor<'a, 'b> for Resolver<'a> {
                 token: node.keyword,
             });
         }
-        self.resolve_local(Expr::Super(node.clone()), node.keyword);
+        self.resolve_local(NodeId::of(node), node.keyword);
         Ok(())
     }
 
@@ -174,36 +300,84 @@ impl<'a, 'b: 'a> ExprVisitor<'a, 'b> for Resolver<'a> {
             });
         }
 
-        self.resolve_local(Expr::This(node.clone()), node.keyword);
+        self.resolve_local(NodeId::of(node), node.keyword);
         Ok(())
     }
 
     fn visit_unary(&mut self, node: &'b ExprUnary<'a>) -> Self::Output {
-        self.resolve_expr(&node.value)?;
+        self.resolve_expr(node.value)?;
+        Ok(())
+    }
+
+    /// Resolving the target as a read also tells the interpreter where to write.
+    fn visit_update(&mut self, node: &'b ExprUpdate<'a>) -> Self::Output {
+        self.resolve_expr(node.target)?;
+        Ok(())
+    }
+
+    fn visit_compound(&mut self, node: &'b ExprCompound<'a>) -> Self::Output {
+        self.resolve_expr(node.target)?;
+        self.resolve_expr(node.value)?;
         Ok(())
     }
 
     fn visit_binary(&mut self, node: &'b ExprBinary<'a>) -> Self::Output {
-        self.resolve_expr(&node.left)?;
-        self.resolve_expr(&node.right)?;
+        self.resolve_expr(node.left)?;
+        self.resolve_expr(node.right)?;
         Ok(())
     }
 
     fn visit_call(&mut self, node: &'b ExprCall<'a>) -> Self::Output {
-        self.resolve_expr(&node.callee)?;
+        self.resolve_expr(node.callee)?;
         node.arguments
             .iter()
             .try_for_each(|arg| self.resolve_expr(arg))
     }
 
     fn visit_get(&mut self, node: &'b ExprGet<'a>) -> Self::Output {
-        self.resolve_expr(&node.object)?;
+        self.resolve_expr(node.object)?;
         Ok(())
     }
 
+    fn visit_list(&mut self, node: &'b ExprList<'a>) -> Self::Output {
+        node.elements
+            .iter()
+            .try_for_each(|element| self.resolve_expr(element))
+    }
+
+    fn visit_map(&mut self, node: &'b ExprMap<'a>) -> Self::Output {
+        node.keys
+            .iter()
+            .zip(&node.values)
+            .try_for_each(|(key, value)| {
+                self.resolve_expr(key)?;
+                self.resolve_expr(value)
+            })
+    }
+
+    fn visit_interpolation(&mut self, node: &'b ExprInterpolation<'a>) -> Self::Output {
+        node.parts
+            .iter()
+            .try_for_each(|part| self.resolve_expr(part))
+    }
+
+    fn visit_index(&mut self, node: &'b ExprIndex<'a>) -> Self::Output {
+        self.resolve_expr(node.object)?;
+        self.resolve_expr(node.index)
+    }
+
+    fn visit_set_index(&mut self, node: &'b ExprSetIndex<'a>) -> Self::Output {
+        self.resolve_expr(node.object)?;
+        self.resolve_expr(node.index)?;
+        self.resolve_expr(node.value)
+    }
+
     fn visit_assign(&mut self, node: &'b ExprAssign<'a>) -> Self::Output {
-        self.resolve_expr(&node.value)?;
-        self.resolve_local(Expr::Assign(node.clone()), node.name);
+        if node.name.kind == TokenType::This {
+            return Err(ResolverError::AssignToThis { token: node.equals });
+        }
+        self.resolve_expr(node.value)?;
+        self.resolve_local(NodeId::of(node), node.name);
         Ok(())
     }
 
@@ -218,7 +392,7 @@ impl<'a, 'b: 'a> ExprVisitor<'a, 'b> for Resolver<'a> {
             }
         }
 
-        self.resolve_local(Expr::Variable(node.clone()), node.name);
+        self.resolve_local(NodeId::of(node), node.name);
 
         Ok(())
     }
@@ -234,10 +408,15 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Resolver<'a> {
         Ok(())
     }
 
+    fn visit_break(&mut self, node: &'b StmtBreak<'a>) -> Self::Output {
+        self.check_in_loop(node.keyword)
+    }
+
     fn visit_class(&mut self, node: &'b StmtClass<'a>) -> Self::Output {
         let enclosing_class = self.current_class;
         self.current_class = ClassType::Class;
         self.declare(&node.name)?;
+        self.check_redeclaration(&node.name, Lint::Redefinition);
         self.define(&node.name);
 
         if let Some(superclass) = &node.superclass {
@@ -270,6 +449,10 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Resolver<'a> {
             }
             self.resolve_function(method, declaration)
         })?;
+        // `this` is the class itself in class methods
+        node.class_methods
+            .iter()
+            .try_for_each(|method| self.resolve_function(method, FunctionType::Method))?;
 
         self.end_scope();
 
@@ -282,12 +465,17 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Resolver<'a> {
         Ok(())
     }
 
+    fn visit_continue(&mut self, node: &'b StmtContinue<'a>) -> Self::Output {
+        self.check_in_loop(node.keyword)
+    }
+
     fn visit_expression(&mut self, node: &'b StmtExpression<'a>) -> Self::Output {
         self.resolve_expr(&node.expr)
     }
 
     fn visit_function(&mut self, node: &'b StmtFunction<'a>) -> Self::Output {
         self.declare(&node.name)?;
+        self.check_redeclaration(&node.name, Lint::Redefinition);
         self.define(&node.name);
 
         self.resolve_function(node, FunctionType::Function)?;
@@ -295,8 +483,9 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Resolver<'a> {
     }
 
     fn visit_if(&mut self, node: &'b StmtIf<'a>) -> Self::Output {
+        self.check_condition(node.keyword, &node.condition);
         self.resolve_expr(&node.condition)?;
-        self.resolve_stmt(&node.then_branch)?;
+        self.resolve_stmt(node.then_branch)?;
         if let Some(else_branch) = &node.else_branch {
             self.resolve_stmt(else_branch)?;
         }
@@ -328,16 +517,27 @@ impl<'a, 'b: 'a> StmtVisitor<'a, 'b> for Resolver<'a> {
 
     fn visit_var(&mut self, node: &'b StmtVar<'a>) -> Self::Output {
         self.declare(&node.name)?;
+        self.check_redeclaration(&node.name, Lint::Redeclaration);
         if let Some(expr) = &node.initializer {
             self.resolve_expr(expr)?;
         }
         self.define(&node.name);
+        if let Some(unused) = self.unused.last_mut() {
+            unused.push(node.name);
+        }
         Ok(())
     }
 
     fn visit_while(&mut self, node: &'b StmtWhile<'a>) -> Self::Output {
+        self.check_condition(node.keyword, &node.condition);
         self.resolve_expr(&node.condition)?;
-        self.resolve_stmt(&node.body)?;
+        self.loop_depth += 1;
+        let body = self.resolve_stmt(node.body);
+        self.loop_depth -= 1;
+        body?;
+        if let Some(increment) = &node.increment {
+            self.resolve_expr(increment)?;
+        }
         Ok(())
     }
 }
@@ -1,25 +1,73 @@
 use std::{
     cell::RefCell,
     fmt::{self},
+    hash::{Hash, Hasher},
     rc::Rc,
 };
 
 use crate::ast::LiteralValue;
 
-use super::{callable::LoxCallable, class::LoxInstance};
-use std::ops::{Add, Div, Mul, Neg, Not, Sub};
+use super::{
+    callable::{CallType, Callable},
+    class::LoxInstance,
+    map::LoxMap,
+};
+use std::ops::{Add, Div, Mul, Neg, Not, Rem, Sub};
 
-#[derive(Debug, Clone)]
+/// Lists are shared, copies of a list value refer to the same elements.
+pub type ListRef<'a> = Rc<RefCell<Vec<Value<'a>>>>;
+
+/// Maps are shared like lists.
+pub type MapRef<'a> = Rc<RefCell<LoxMap<'a>>>;
+
+#[derive(Debug, Clone, Default)]
 pub enum Value<'a> {
     Number(f64),
     String(String),
     Boolean(bool),
-    Callable(Rc<dyn LoxCallable<'a>>),
+    Callable(Callable<'a>),
     Instance(Rc<RefCell<LoxInstance<'a>>>),
+    List(ListRef<'a>),
+    Map(MapRef<'a>),
+    #[default]
     Nil,
 }
 
 impl<'a> Value<'a> {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Callable(callable) => match callable.call_type() {
+                CallType::Class => "class",
+                CallType::Function | CallType::Native => "function",
+            },
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Nil => "nil",
+        }
+    }
+
+    /// The type and value for error messages, like `string "a"` or `number 3`. Long values are
+    /// shortened.
+    pub fn describe(&self) -> String {
+        const MAX_LEN: usize = 32;
+
+        let value = match self {
+            Value::Nil => return "nil".to_string(),
+            Value::Instance(instance) => return instance.borrow().to_string(),
+            Value::String(s) => format!("\"{s}\""),
+            value => value.to_string(),
+        };
+        let value = match value.chars().count() > MAX_LEN {
+            true => format!("{}...", value.chars().take(MAX_LEN).collect::<String>()),
+            false => value,
+        };
+        format!("{} {value}", self.type_name())
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Value::Number(_) => true,
@@ -28,16 +76,12 @@ impl<'a> Value<'a> {
             Value::Callable(_) => true,
             Value::Nil => false,
             Value::Instance(_) => true,
+            Value::List(_) => true,
+            Value::Map(_) => true,
         }
     }
 }
 
-impl<'a> Default for Value<'a> {
-    fn default() -> Self {
-        Self::Nil
-    }
-}
-
 impl<'a> Neg for Value<'a> {
     type Output = Self;
 
@@ -60,6 +104,8 @@ impl<'a> Not for Value<'a> {
             Value::Callable(_) => Value::Boolean(false),
             Value::Nil => Value::Boolean(true),
             Value::Instance(_) => Value::Boolean(false),
+            Value::List(_) => Value::Boolean(false),
+            Value::Map(_) => Value::Boolean(false),
         }
     }
 }
@@ -109,6 +155,18 @@ impl<'a> Mul for Value<'a> {
     }
 }
 
+/// Like `fmod` in C, the result has the sign of the dividend.
+impl<'a> Rem for Value<'a> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        match (self, rhs) {
+            (Value::Number(l), Value::Number(r)) => Value::Number(l % r),
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl<'a> PartialEq for Value<'a> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -116,7 +174,13 @@ impl<'a> PartialEq for Value<'a> {
             (Value::String(l), Value::String(r)) => l == r,
             (Value::Boolean(l), Value::Boolean(r)) => l == r,
             (Value::Nil, Value::Nil) => true,
-            (Value::Callable(l), Value::Callable(r)) => Rc::ptr_eq(l, r),
+            (Value::Callable(l), Value::Callable(r)) => l.ptr_eq(r),
+            // Bound methods hold their own copy of the instance, but all copies share the fields
+            (Value::Instance(l), Value::Instance(r)) => {
+                Rc::ptr_eq(&l.borrow().fields, &r.borrow().fields)
+            }
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
             _ => false,
         }
     }
@@ -148,12 +212,195 @@ impl<'a> From<LiteralValue> for Value<'a> {
 impl<'a> fmt::Display for Value<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            // Spelled like the globals, so printed values read back as the same number
+            Value::Number(n) if n.is_nan() => write!(f, "NaN"),
+            Value::Number(n) if n.is_infinite() => match n.is_sign_positive() {
+                true => write!(f, "Infinity"),
+                false => write!(f, "-Infinity"),
+            },
             Value::Number(n) => write!(f, "{}", n),
             Value::String(s) => write!(f, "{}", s),
             Value::Boolean(b) => write!(f, "{}", b),
-            Value::Callable(lox_callable) => write!(f, "{:?}", lox_callable),
+            Value::Callable(callable) => write!(f, "{callable}"),
             Value::Nil => write!(f, "nil"),
             Value::Instance(lox_instance) => write!(f, "{:?}", lox_instance.borrow()),
+            Value::List(_) | Value::Map(_) => write_nested(f, self, &mut vec![]),
+        }
+    }
+}
+
+// `seen` holds the lists and maps currently being written, one containing itself is written as
+// `[...]` or `{...}` instead of recursing forever. Strings inside them are quoted, so `["1"]` and
+// `[1]` differ.
+fn write_nested(
+    f: &mut fmt::Formatter<'_>,
+    value: &Value,
+    seen: &mut Vec<*const ()>,
+) -> fmt::Result {
+    let id = match value {
+        Value::String(s) => return write!(f, "{s:?}"),
+        Value::List(list) => Rc::as_ptr(list) as *const (),
+        Value::Map(map) => Rc::as_ptr(map) as *const (),
+        value => return write!(f, "{value}"),
+    };
+    if seen.contains(&id) {
+        return match value {
+            Value::List(_) => write!(f, "[...]"),
+            _ => write!(f, "{{...}}"),
+        };
+    }
+
+    seen.push(id);
+    match value {
+        Value::List(list) => {
+            write!(f, "[")?;
+            for (i, element) in list.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_nested(f, element, seen)?;
+            }
+            write!(f, "]")?;
         }
+        Value::Map(map) => {
+            write!(f, "{{")?;
+            for (i, (key, element)) in map.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_nested(f, &key.clone().into(), seen)?;
+                write!(f, ": ")?;
+                write_nested(f, element, seen)?;
+            }
+            write!(f, "}}")?;
+        }
+        _ => {}
+    }
+    seen.pop();
+    Ok(())
+}
+
+/// A value that can be used as a map key: a number, string, boolean, nil or an instance whose
+/// class defines `hash()`.
+#[derive(Debug, Clone)]
+pub enum HashableValue<'a> {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Nil,
+    /// Compared by identity. `hash` is what its `hash()` returned when it was made a key, changing
+    /// the instance later doesn't move it in the maps it is a key of.
+    Instance {
+        instance: Rc<RefCell<LoxInstance<'a>>>,
+        hash: u64,
+    },
+}
+
+impl<'a> HashableValue<'a> {
+    /// `hash` is what the instance's `hash()` returned.
+    pub fn instance(instance: Rc<RefCell<LoxInstance<'a>>>, hash: f64) -> Self {
+        HashableValue::Instance {
+            instance,
+            hash: number_bits(hash),
+        }
+    }
+}
+
+/// Gives the value back if it can't be a key. Instances need the interpreter to call their
+/// `hash()`, see `Interpreter::map_key`.
+impl<'a> TryFrom<Value<'a>> for HashableValue<'a> {
+    type Error = Value<'a>;
+
+    fn try_from(value: Value<'a>) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) => Ok(HashableValue::Number(n)),
+            Value::String(s) => Ok(HashableValue::String(s)),
+            Value::Boolean(b) => Ok(HashableValue::Boolean(b)),
+            Value::Nil => Ok(HashableValue::Nil),
+            Value::Callable(_) | Value::Instance(_) | Value::List(_) | Value::Map(_) => Err(value),
+        }
+    }
+}
+
+impl<'a> From<HashableValue<'a>> for Value<'a> {
+    fn from(value: HashableValue<'a>) -> Self {
+        match value {
+            HashableValue::Number(n) => Value::Number(n),
+            HashableValue::String(s) => Value::String(s),
+            HashableValue::Boolean(b) => Value::Boolean(b),
+            HashableValue::Nil => Value::Nil,
+            HashableValue::Instance { instance, .. } => Value::Instance(instance),
+        }
+    }
+}
+
+/// Numbers are compared by their bits so NaN can be a key, `-0` is the same key as `0`.
+fn number_bits(n: f64) -> u64 {
+    match n == 0.0 {
+        true => 0,
+        false => n.to_bits(),
+    }
+}
+
+impl PartialEq for HashableValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (HashableValue::Number(l), HashableValue::Number(r)) => {
+                number_bits(*l) == number_bits(*r)
+            }
+            (HashableValue::String(l), HashableValue::String(r)) => l == r,
+            (HashableValue::Boolean(l), HashableValue::Boolean(r)) => l == r,
+            (HashableValue::Nil, HashableValue::Nil) => true,
+            (
+                HashableValue::Instance { instance: l, .. },
+                HashableValue::Instance { instance: r, .. },
+            ) => Rc::ptr_eq(&l.borrow().fields, &r.borrow().fields),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HashableValue<'_> {}
+
+impl Hash for HashableValue<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            HashableValue::Number(n) => number_bits(*n).hash(state),
+            HashableValue::String(s) => s.hash(state),
+            HashableValue::Boolean(b) => b.hash(state),
+            HashableValue::Nil => {}
+            HashableValue::Instance { hash, .. } => hash.hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::interpreter::native_fun::clock::Clock;
+
+    use super::*;
+
+    #[test]
+    fn hashable_values() {
+        let key = |value| HashableValue::try_from(value).unwrap();
+
+        let keys: HashSet<_> = [
+            key(Value::Number(0.0)),
+            key(Value::Number(-0.0)),
+            key(Value::Number(f64::NAN)),
+            key(Value::Number(f64::NAN)),
+            key(Value::String("0".to_string())),
+            key(Value::Boolean(false)),
+            key(Value::Nil),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(keys.len(), 5);
+
+        let function = Value::Callable(Callable::native(Clock::new()));
+        assert!(HashableValue::try_from(function).is_err());
     }
 }
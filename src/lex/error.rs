@@ -1,13 +1,49 @@
 use thiserror::Error;
 
+use super::Span;
+
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone)]
 pub enum TokenError {
-    #[error("String `{0}` is not terminated")]
-    NonTerminatedString(String),
+    /// `line` and `span` locate the opening quote, the string may run until the end of the file.
+    /// The message leaves out the whitespace the file ends with.
+    #[error("[line {line}] String `{}` is not terminated", string.trim_end())]
+    NonTerminatedString {
+        string: String,
+        line: u32,
+        span: Span,
+    },
+
+    #[error("[line {line}] Unexpected token `{token}`")]
+    UnexpectedToken {
+        token: String,
+        line: u32,
+        span: Span,
+    },
 
-    #[error("Unexpected token `{0}`")]
-    UnexpectedToken(String),
+    #[error("Malformed number literal `{0}`: {1}")]
+    MalformedNumber(String, &'static str),
 
     #[error("Unexpected end of file")]
     UnexpectedEOF,
+
+    #[error("Invalid directive `{0}`: {1}")]
+    InvalidDirective(String, &'static str),
+
+    /// `line` and `span` locate the `/*` that wasn't closed, the outermost one if they are nested.
+    #[error("[line {line}] Block comment is not terminated")]
+    UnterminatedComment { line: u32, span: Span },
+}
+
+impl TokenError {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenError::NonTerminatedString { .. } => "E0001",
+            TokenError::UnexpectedToken { .. } => "E0002",
+            TokenError::MalformedNumber(..) => "E0003",
+            TokenError::UnexpectedEOF => "E0004",
+            TokenError::InvalidDirective(..) => "E0005",
+            TokenError::UnterminatedComment { .. } => "E0008",
+        }
+    }
 }
@@ -1,5 +1,8 @@
 use core::fmt;
-use std::hash::{Hash, Hasher};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
 
 use error::TokenError;
 
@@ -12,9 +15,13 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
+    Percent,
     Plus,
     Semicolon,
     Slash,
@@ -29,15 +36,31 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    MinusEqual,
+    MinusMinus,
+    PercentEqual,
+    Pipe,
+    PlusEqual,
+    PlusPlus,
+    SlashEqual,
+    StarEqual,
 
     // Literals.
     Ident,
     String,
+    /// The part of an interpolated string before a `${`, from the opening quote or the `}` of the
+    /// previous expression
+    Interpolation,
+    /// The part of an interpolated string after its last expression, from the `}` to the closing
+    /// quote
+    InterpolationEnd,
     Number(f64),
 
     // Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -67,6 +90,93 @@ impl Hash for TokenType {
 
 impl Eq for TokenType {}
 
+impl TokenType {
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenType::And
+                | TokenType::Break
+                | TokenType::Class
+                | TokenType::Continue
+                | TokenType::Else
+                | TokenType::False
+                | TokenType::Fun
+                | TokenType::For
+                | TokenType::If
+                | TokenType::Nil
+                | TokenType::Or
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Super
+                | TokenType::This
+                | TokenType::True
+                | TokenType::Var
+                | TokenType::While
+        )
+    }
+
+    /// How the kind is spelled in error messages, like `';'` or `a name`.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenType::LeftParen => "'('",
+            TokenType::RightParen => "')'",
+            TokenType::LeftBrace => "'{'",
+            TokenType::RightBrace => "'}'",
+            TokenType::LeftBracket => "'['",
+            TokenType::RightBracket => "']'",
+            TokenType::Colon => "':'",
+            TokenType::Comma => "','",
+            TokenType::Dot => "'.'",
+            TokenType::Minus => "'-'",
+            TokenType::Percent => "'%'",
+            TokenType::Plus => "'+'",
+            TokenType::Semicolon => "';'",
+            TokenType::Slash => "'/'",
+            TokenType::Star => "'*'",
+            TokenType::Bang => "'!'",
+            TokenType::BangEqual => "'!='",
+            TokenType::Equal => "'='",
+            TokenType::EqualEqual => "'=='",
+            TokenType::Greater => "'>'",
+            TokenType::GreaterEqual => "'>='",
+            TokenType::Less => "'<'",
+            TokenType::LessEqual => "'<='",
+            TokenType::MinusEqual => "'-='",
+            TokenType::MinusMinus => "'--'",
+            TokenType::PercentEqual => "'%='",
+            TokenType::Pipe => "'|>'",
+            TokenType::PlusEqual => "'+='",
+            TokenType::PlusPlus => "'++'",
+            TokenType::SlashEqual => "'/='",
+            TokenType::StarEqual => "'*='",
+            TokenType::Ident => "a name",
+            TokenType::String => "a string",
+            TokenType::Interpolation => "a string",
+            TokenType::InterpolationEnd => "the end of a string",
+            TokenType::Number(_) => "a number",
+            TokenType::And => "'and'",
+            TokenType::Break => "'break'",
+            TokenType::Class => "'class'",
+            TokenType::Continue => "'continue'",
+            TokenType::Else => "'else'",
+            TokenType::False => "'false'",
+            TokenType::Fun => "'fun'",
+            TokenType::For => "'for'",
+            TokenType::If => "'if'",
+            TokenType::Nil => "'nil'",
+            TokenType::Or => "'or'",
+            TokenType::Print => "'print'",
+            TokenType::Return => "'return'",
+            TokenType::Super => "'super'",
+            TokenType::This => "'this'",
+            TokenType::True => "'true'",
+            TokenType::Var => "'var'",
+            TokenType::While => "'while'",
+            TokenType::EOF => "the end of the file",
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Eq, Hash)]
 pub struct Span {
     pub begin: u32,
@@ -100,9 +210,13 @@ impl<'a> fmt::Display for Token<'a> {
             TokenType::RightParen => write!(f, "RightParen {lexeme} null"),
             TokenType::LeftBrace => write!(f, "LeftBrace {lexeme} null"),
             TokenType::RightBrace => write!(f, "RightBrace {lexeme} null"),
+            TokenType::LeftBracket => write!(f, "LeftBracket {lexeme} null"),
+            TokenType::RightBracket => write!(f, "RightBracket {lexeme} null"),
+            TokenType::Colon => write!(f, "Colon {lexeme} null"),
             TokenType::Comma => write!(f, "Comma {lexeme} null"),
             TokenType::Dot => write!(f, "Dot {lexeme} null"),
             TokenType::Minus => write!(f, "Minus {lexeme} null"),
+            TokenType::Percent => write!(f, "Percent {lexeme} null"),
             TokenType::Plus => write!(f, "Plus {lexeme} null"),
             TokenType::Semicolon => write!(f, "Semicolon {lexeme} null"),
             TokenType::Slash => write!(f, "Slash {lexeme} null"),
@@ -115,11 +229,23 @@ impl<'a> fmt::Display for Token<'a> {
             TokenType::GreaterEqual => write!(f, "GreaterEqual {lexeme} null"),
             TokenType::Less => write!(f, "Less {lexeme} null"),
             TokenType::LessEqual => write!(f, "LessEqual {lexeme} null"),
+            TokenType::MinusEqual => write!(f, "MinusEqual {lexeme} null"),
+            TokenType::MinusMinus => write!(f, "MinusMinus {lexeme} null"),
+            TokenType::PercentEqual => write!(f, "PercentEqual {lexeme} null"),
+            TokenType::Pipe => write!(f, "Pipe {lexeme} null"),
+            TokenType::PlusEqual => write!(f, "PlusEqual {lexeme} null"),
+            TokenType::PlusPlus => write!(f, "PlusPlus {lexeme} null"),
+            TokenType::SlashEqual => write!(f, "SlashEqual {lexeme} null"),
+            TokenType::StarEqual => write!(f, "StarEqual {lexeme} null"),
             TokenType::Ident => write!(f, "Ident {lexeme} null"),
             TokenType::String => write!(f, "String {lexeme} {lexeme}"),
+            TokenType::Interpolation => write!(f, "Interpolation {lexeme} {lexeme}"),
+            TokenType::InterpolationEnd => write!(f, "InterpolationEnd {lexeme} {lexeme}"),
             TokenType::Number(n) => write!(f, "Number {lexeme} {n}"),
             TokenType::And => write!(f, "And {lexeme} null"),
+            TokenType::Break => write!(f, "Break {lexeme} null"),
             TokenType::Class => write!(f, "Class {lexeme} null"),
+            TokenType::Continue => write!(f, "Continue {lexeme} null"),
             TokenType::Else => write!(f, "Else {lexeme} null"),
             TokenType::False => write!(f, "False {lexeme} null"),
             TokenType::Fun => write!(f, "Fun {lexeme} null"),
@@ -139,11 +265,35 @@ impl<'a> fmt::Display for Token<'a> {
     }
 }
 
+/// A `//` comment, kept next to the tokens so later stages can look at annotations in comments.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Comment<'a> {
+    /// The comment text, including the leading `//`
+    pub text: &'a str,
+    pub line: u32,
+    pub span: Span,
+}
+
+/// A string whose `${` expression is being scanned.
+#[derive(Debug, Clone, Copy)]
+struct OpenString {
+    /// Where the opening quote is
+    begin: usize,
+    line: u32,
+    /// How many `{` of the expression aren't closed yet, the next `}` ends it if there are none
+    braces: usize,
+}
+
 pub struct Scanner<'a> {
     source: &'a str,
     position: usize,
     start: usize,
     line: u32,
+    comments: Vec<Comment<'a>>,
+    /// Constants from `#define NAME value` lines, keyed by name
+    defines: HashMap<&'a str, Token<'a>>,
+    /// The strings the current token is interpolated into, innermost last
+    interpolations: Vec<OpenString>,
 }
 
 impl<'a> Scanner<'a> {
@@ -153,18 +303,62 @@ impl<'a> Scanner<'a> {
             position: 0,
             start: 0,
             line: 1,
+            comments: vec![],
+            defines: HashMap::new(),
+            interpolations: vec![],
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token<'a>>, TokenError> {
+    /// The comments skipped by `scan_tokens` so far, in source order.
+    pub fn comments(&self) -> &[Comment<'a>] {
+        &self.comments
+    }
+
+    /// Scans the whole source. Scanning goes on after an error, so all lexical errors are reported
+    /// at once, in source order.
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token<'a>>, Vec<TokenError>> {
         let mut tokens = vec![];
+        let mut errors = vec![];
         while self.position < self.source.len() {
             self.start = self.position;
-            let token = self.scan_token()?;
-            tokens.push(token);
+            match self.scan_token() {
+                // Only trailing whitespace and comments were left
+                Ok(token) if token.kind == TokenType::EOF => break,
+                Ok(token) => tokens.push(token),
+                Err(error) => {
+                    self.synchronize(&error);
+                    errors.push(error);
+                }
+            }
+        }
+        // The source ended inside an expression, the outermost string is the one left open
+        if let Some(open) = self.interpolations.first() {
+            errors.push(TokenError::NonTerminatedString {
+                string: self.source[open.begin..].to_string(),
+                line: open.line,
+                span: Span {
+                    begin: open.begin as u32,
+                    end: self.source.len() as u32,
+                },
+            });
+            self.interpolations.clear();
+        }
+        tokens.push(self.eof());
+
+        match errors.is_empty() {
+            true => Ok(tokens),
+            false => Err(errors),
+        }
+    }
+
+    // Skips what is left of the erroneous input. Most errors already consumed all of it, only a
+    // directive may stop in the middle of its line.
+    fn synchronize(&mut self, error: &TokenError) {
+        if let TokenError::InvalidDirective(..) = error {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.read_char();
+            }
         }
-        tokens.push(self.make_token(TokenType::EOF));
-        Ok(tokens)
     }
 
     fn peek(&self) -> Option<char> {
@@ -184,7 +378,7 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    fn skip_whitespace(&mut self) {
+    fn skip_whitespace(&mut self) -> Result<(), TokenError> {
         while let Some(c) = self.peek() {
             match c {
                 ' ' | '\r' | '\t' => {
@@ -195,16 +389,55 @@ impl<'a> Scanner<'a> {
                     self.read_char();
                 }
                 '/' if self.peek_nth(1) == Some('/') => {
-                    while let Some(c) = self.read_char() {
-                        if c == '\n' {
-                            self.line += 1;
-                            break;
-                        }
-                    }
+                    let begin = self.position;
+                    let end = self.source[begin..]
+                        .find('\n')
+                        .map_or(self.source.len(), |i| begin + i);
+                    self.comments.push(Comment {
+                        text: &self.source[begin..end],
+                        line: self.line,
+                        span: Span {
+                            begin: begin as u32,
+                            end: end as u32,
+                        },
+                    });
+                    self.position = end;
                 }
+                '/' if self.peek_nth(1) == Some('*') => self.block_comment()?,
                 _ => break,
             }
         }
+        Ok(())
+    }
+
+    /// Skips a `/* */` comment, comments nested in it have to be closed as well.
+    fn block_comment(&mut self) -> Result<(), TokenError> {
+        let (begin, line) = (self.position, self.line);
+        let mut depth = 0;
+        while let Some(c) = self.read_char() {
+            match c {
+                '/' if self.peek() == Some('*') => {
+                    self.read_char();
+                    depth += 1;
+                }
+                '*' if self.peek() == Some('/') => {
+                    self.read_char();
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                '\n' => self.line += 1,
+                _ => (),
+            }
+        }
+        Err(TokenError::UnterminatedComment {
+            line,
+            span: Span {
+                begin: begin as u32,
+                end: self.position as u32,
+            },
+        })
     }
 
     fn make_token(&self, kind: TokenType) -> Token<'a> {
@@ -220,6 +453,13 @@ impl<'a> Scanner<'a> {
         )
     }
 
+    /// A zero-width token at the end of the source.
+    fn eof(&mut self) -> Token<'a> {
+        self.position = self.source.len();
+        self.start = self.position;
+        self.make_token(TokenType::EOF)
+    }
+
     fn make_token_with_lexeme(&self, kind: TokenType, lexeme: &'a str) -> Token<'a> {
         Token::new(
             kind,
@@ -242,38 +482,92 @@ impl<'a> Scanner<'a> {
         false
     }
 
-    fn number(&mut self) -> Token<'a> {
-        while let Some(c) = self.peek() {
-            if !c.is_ascii_digit() {
-                break;
-            }
+    fn number(&mut self) -> Result<Token<'a>, TokenError> {
+        let radix = match (&self.source[self.start..self.position], self.peek()) {
+            ("0", Some('x' | 'X')) => Some(16),
+            ("0", Some('b' | 'B')) => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
             self.read_char();
+            return self.radix_number(radix);
         }
 
+        self.digits(10)?;
+
         if let Some('.') = self.peek() {
             if let Some(next) = self.peek_nth(1) {
                 if next.is_ascii_digit() {
                     self.read_char();
-
-                    while let Some(c) = self.peek() {
-                        if !c.is_ascii_digit() {
-                            break;
-                        }
-                        self.read_char();
-                    }
+                    self.digits(10)?;
                 }
             }
         }
 
+        if let Some('e' | 'E') = self.peek() {
+            self.read_char();
+            if let Some('+' | '-') = self.peek() {
+                self.read_char();
+            }
+            if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                return Err(self.malformed_number("missing exponent digits"));
+            }
+            self.digits(10)?;
+        }
+
         let value: f64 = self.source[self.start..self.position]
+            .replace('_', "")
             .parse()
             .unwrap_or(0.0);
-        self.make_token(TokenType::Number(value))
+        Ok(self.make_token(TokenType::Number(value)))
     }
 
-    fn string(&mut self) -> Result<Token<'a>, TokenError> {
+    fn radix_number(&mut self, radix: u32) -> Result<Token<'a>, TokenError> {
+        if !self.peek().is_some_and(|c| c.is_digit(radix)) {
+            return Err(self.malformed_number("expected at least one digit after the prefix"));
+        }
+        self.digits(radix)?;
+
+        // Folding into a f64 instead of parsing an integer type means huge literals lose
+        // precision like decimal ones do, instead of failing to parse
+        let value = self.source[self.start + 2..self.position]
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |acc, digit| acc * radix as f64 + digit as f64);
+        Ok(self.make_token(TokenType::Number(value)))
+    }
+
+    // Consumes a run of digits where single underscores may separate two digits
+    fn digits(&mut self, radix: u32) -> Result<(), TokenError> {
         while let Some(c) = self.peek() {
-            if c == '"' {
+            if c == '_' {
+                if !self.peek_nth(1).is_some_and(|next| next.is_digit(radix)) {
+                    self.read_char();
+                    return Err(
+                        self.malformed_number("underscores must be placed between two digits")
+                    );
+                }
+            } else if !c.is_digit(radix) {
+                break;
+            }
+            self.read_char();
+        }
+        Ok(())
+    }
+
+    fn malformed_number(&mut self, reason: &'static str) -> TokenError {
+        // Swallow the rest of the literal so the error shows all of it
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.read_char();
+        }
+        TokenError::MalformedNumber(self.source[self.start..self.position].to_string(), reason)
+    }
+
+    /// Reads a string, or its part up to the next `${`, after the opening quote at `begin` or the `}`
+    /// ending an expression in it. A `${` leaves the string open until that expression ends.
+    fn string(&mut self, begin: usize, line: u32) -> Result<Token<'a>, TokenError> {
+        while let Some(c) = self.peek() {
+            if c == '"' || (c == '$' && self.peek_nth(1) == Some('{')) {
                 break;
             }
             if c == '\n' {
@@ -282,15 +576,128 @@ impl<'a> Scanner<'a> {
             self.read_char();
         }
 
-        self.peek().ok_or(TokenError::NonTerminatedString(
-            self.source[self.start..self.position].to_string(),
-        ))?;
+        let kind = match self.read_char() {
+            Some('"') if self.start == begin => TokenType::String,
+            Some('"') => TokenType::InterpolationEnd,
+            Some(_) => {
+                self.read_char();
+                self.interpolations.push(OpenString {
+                    begin,
+                    line,
+                    braces: 0,
+                });
+                TokenType::Interpolation
+            }
+            None => {
+                // The strings this one is interpolated into end with the source too, one error
+                // covers them
+                self.interpolations.clear();
+                return Err(TokenError::NonTerminatedString {
+                    string: self.source[begin..self.position].to_string(),
+                    line,
+                    span: Span {
+                        begin: begin as u32,
+                        end: self.position as u32,
+                    },
+                });
+            }
+        };
+
+        // Leave out the quotes, the `${` and the `}`, they are all one byte
+        let end = match kind {
+            TokenType::Interpolation => self.position - 2,
+            _ => self.position - 1,
+        };
+        let lexeme = &self.source[self.start + 1..end];
+        Ok(self.make_token_with_lexeme(kind, lexeme))
+    }
+
+    /// Reads a `#define NAME value` line after its `#`. The value has to be a single literal,
+    /// optionally a negative number.
+    fn define(&mut self) -> Result<(), TokenError> {
+        let invalid = |scanner: &mut Self, reason| {
+            let end = scanner.source[scanner.start..]
+                .find('\n')
+                .map_or(scanner.source.len(), |i| scanner.start + i);
+            TokenError::InvalidDirective(scanner.source[scanner.start..end].to_string(), reason)
+        };
+
+        let directive = self.position;
+        while self.peek().is_some_and(|c| c.is_alphanumeric()) {
+            self.read_char();
+        }
+        if &self.source[directive..self.position] != "define" {
+            return Err(invalid(self, "only #define is supported"));
+        }
+
+        self.skip_blanks();
+        let name_start = self.position;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.read_char();
+        }
+        let name = &self.source[name_start..self.position];
+        if !name.starts_with(|c: char| c.is_alphabetic()) {
+            return Err(invalid(self, "expected a name"));
+        }
+        if self.defines.contains_key(name) {
+            return Err(invalid(self, "the name is already defined"));
+        }
+
+        self.skip_blanks();
+        let directive_start = self.start;
+        let value_start = self.position;
+        let negative = self.match_next('-');
+        self.start = self.position;
+        let open = self.interpolations.len();
+        let value = match self.read_char() {
+            Some(c) if c.is_ascii_digit() => self.number()?,
+            Some('"') if !negative => self.string(self.start, self.line)?,
+            Some(c) if c.is_alphabetic() && !negative => self.identifier(),
+            _ => self.make_token(TokenType::EOF),
+        };
+        let value = match value.kind {
+            TokenType::Number(n) if negative => Token {
+                kind: TokenType::Number(-n),
+                lexeme: &self.source[value_start..self.position],
+                ..value
+            },
+            TokenType::Number(_)
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil => value,
+            _ => {
+                // An interpolated string isn't a literal, forget that it was opened
+                self.interpolations.truncate(open);
+                self.start = directive_start;
+                return Err(invalid(
+                    self,
+                    "the value must be a number, string, boolean or nil",
+                ));
+            }
+        };
+        self.start = directive_start;
 
-        self.read_char();
+        self.skip_blanks();
+        let rest = &self.source[self.position..];
+        if !(rest.is_empty() || rest.starts_with('\n') || rest.starts_with("//")) {
+            return Err(invalid(
+                self,
+                "expected the end of the line after the value",
+            ));
+        }
 
-        let lexeme = &self.source[self.start + 1..self.position - 1];
-        Ok(self.make_token_with_lexeme(TokenType::String, lexeme))
+        self.defines.insert(name, value);
+        Ok(())
     }
+
+    // Skips spaces and tabs, but not newlines
+    fn skip_blanks(&mut self) {
+        while let Some(' ' | '\t' | '\r') = self.peek() {
+            self.read_char();
+        }
+    }
+
     fn identifier(&mut self) -> Token<'a> {
         while let Some(c) = self.peek() {
             if !c.is_alphanumeric() && c != '_' {
@@ -302,7 +709,9 @@ impl<'a> Scanner<'a> {
         let lexeme = &self.source[self.start..self.position];
         let kind = match lexeme {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
             "else" => TokenType::Else,
             "false" => TokenType::False,
             "for" => TokenType::For,
@@ -320,27 +729,75 @@ impl<'a> Scanner<'a> {
             _ => TokenType::Ident,
         };
 
+        // A defined constant becomes its value, located where it is used
+        if let Some(value) = self.defines.get(lexeme) {
+            return self.make_token_with_lexeme(value.kind, value.lexeme);
+        }
+
         self.make_token(kind)
     }
 
     pub fn scan_token(&mut self) -> Result<Token<'a>, TokenError> {
-        self.skip_whitespace();
+        self.skip_whitespace()?;
         self.start = self.position;
 
         let c = self.read_char();
         let Some(c) = c else {
-            return Ok(self.make_token(TokenType::EOF));
+            return Ok(self.eof());
         };
         let token = match c {
             '(' => self.make_token(TokenType::LeftParen),
             ')' => self.make_token(TokenType::RightParen),
-            '{' => self.make_token(TokenType::LeftBrace),
-            '}' => self.make_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    open.braces += 1;
+                }
+                self.make_token(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(open) if open.braces == 0 => {
+                    let open = self.interpolations.pop().unwrap();
+                    self.string(open.begin, open.line)?
+                }
+                Some(open) => {
+                    open.braces -= 1;
+                    self.make_token(TokenType::RightBrace)
+                }
+                None => self.make_token(TokenType::RightBrace),
+            },
+            '[' => self.make_token(TokenType::LeftBracket),
+            ']' => self.make_token(TokenType::RightBracket),
+            ':' => self.make_token(TokenType::Colon),
             ',' => self.make_token(TokenType::Comma),
             '.' => self.make_token(TokenType::Dot),
-            '-' => self.make_token(TokenType::Minus),
-            '+' => self.make_token(TokenType::Plus),
-            '*' => self.make_token(TokenType::Star),
+            '-' => {
+                let token = if self.match_next('=') {
+                    TokenType::MinusEqual
+                } else if self.match_next('-') {
+                    TokenType::MinusMinus
+                } else {
+                    TokenType::Minus
+                };
+                self.make_token(token)
+            }
+            '+' => {
+                let token = if self.match_next('=') {
+                    TokenType::PlusEqual
+                } else if self.match_next('+') {
+                    TokenType::PlusPlus
+                } else {
+                    TokenType::Plus
+                };
+                self.make_token(token)
+            }
+            '*' => {
+                let token = if self.match_next('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.make_token(token)
+            }
             ';' => self.make_token(TokenType::Semicolon),
             '!' => {
                 let token = if self.match_next('=') {
@@ -374,11 +831,40 @@ impl<'a> Scanner<'a> {
                 };
                 self.make_token(token)
             }
-            '/' => self.make_token(TokenType::Slash),
-            '"' => self.string()?,
-            c if c.is_ascii_digit() => self.number(),
+            '/' => {
+                let token = if self.match_next('=') {
+                    TokenType::SlashEqual
+                } else {
+                    TokenType::Slash
+                };
+                self.make_token(token)
+            }
+            '%' => {
+                let token = if self.match_next('=') {
+                    TokenType::PercentEqual
+                } else {
+                    TokenType::Percent
+                };
+                self.make_token(token)
+            }
+            '#' => {
+                self.define()?;
+                return self.scan_token();
+            }
+            '|' if self.match_next('>') => self.make_token(TokenType::Pipe),
+            '"' => self.string(self.start, self.line)?,
+            c if c.is_ascii_digit() => self.number()?,
             c if c.is_alphabetic() => self.identifier(),
-            _ => return Err(TokenError::UnexpectedToken(c.to_string())),
+            _ => {
+                return Err(TokenError::UnexpectedToken {
+                    token: c.to_string(),
+                    line: self.line,
+                    span: Span {
+                        begin: self.start as u32,
+                        end: self.position as u32,
+                    },
+                })
+            }
         };
 
         Ok(token)
@@ -400,7 +886,7 @@ mod test {
     fn whitespace() {
         let input = "  (";
         let mut scanner = Scanner::new(input);
-        scanner.skip_whitespace();
+        scanner.skip_whitespace().unwrap();
         assert_eq!(scanner.peek().unwrap(), '(');
     }
 
@@ -434,6 +920,32 @@ mod test {
         assert_eq!(token, scanner.scan_token().unwrap());
     }
 
+    #[test]
+    fn compound_assignment_and_update() {
+        let kinds: Vec<_> = Scanner::new("+= -= *= /= %= + = ++ --- ")
+            .scan_tokens()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+                TokenType::Plus,
+                TokenType::Equal,
+                TokenType::PlusPlus,
+                TokenType::MinusMinus,
+                TokenType::Minus,
+                TokenType::EOF,
+            ]
+        );
+    }
+
     #[test]
     fn number() {
         let input = "1234.123 123";
@@ -446,19 +958,120 @@ mod test {
         assert_eq!(token, scanner.scan_token().unwrap());
     }
 
+    #[test]
+    fn number_extensions() {
+        let input = "0xFF 0b1010 1_000_000 1e-3 2.5E2 0x_1";
+        let mut scanner = Scanner::new(input);
+        let expected = [255.0, 10.0, 1_000_000.0, 1e-3, 250.0];
+        for value in expected {
+            assert_eq!(TokenType::Number(value), scanner.scan_token().unwrap().kind);
+        }
+        assert_eq!(
+            Err(TokenError::MalformedNumber(
+                "0x_1".to_string(),
+                "expected at least one digit after the prefix"
+            )),
+            scanner.scan_token()
+        );
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let cases = [
+            ("1_", "underscores must be placed between two digits"),
+            ("1__0", "underscores must be placed between two digits"),
+            ("1.5_", "underscores must be placed between two digits"),
+            ("1e", "missing exponent digits"),
+            ("1e+", "missing exponent digits"),
+            ("0b", "expected at least one digit after the prefix"),
+            ("0b2", "expected at least one digit after the prefix"),
+        ];
+        for (input, reason) in cases {
+            let mut scanner = Scanner::new(input);
+            assert_eq!(
+                Err(TokenError::MalformedNumber(input.to_string(), reason)),
+                scanner.scan_token(),
+                "{input}"
+            );
+        }
+    }
+
     #[test]
     fn string() {
-        let input = "\"test\" \"test";
+        let input = "\"test\" \"test\n\n";
         let mut scanner = Scanner::new(input);
         let span = Span { begin: 0, end: 6 };
         let token = Token::new(TokenType::String, "test", 1, span);
         assert_eq!(token, scanner.scan_token().unwrap());
         assert_eq!(
-            Err(TokenError::NonTerminatedString("\"test".to_string())),
+            Err(TokenError::NonTerminatedString {
+                string: "\"test\n\n".to_string(),
+                line: 1,
+                span: Span { begin: 7, end: 14 },
+            }),
             scanner.scan_token()
         );
     }
 
+    #[test]
+    fn interpolation() {
+        let tokens = Scanner::new("\"a ${b} c ${ {\"d\": \"${e}\"} } f\"")
+            .scan_tokens()
+            .unwrap();
+        let tokens: Vec<_> = tokens.iter().map(|t| (t.kind, t.lexeme)).collect();
+        assert_eq!(
+            tokens,
+            [
+                (TokenType::Interpolation, "a "),
+                (TokenType::Ident, "b"),
+                (TokenType::Interpolation, " c "),
+                (TokenType::LeftBrace, "{"),
+                (TokenType::String, "d"),
+                (TokenType::Colon, ":"),
+                (TokenType::Interpolation, ""),
+                (TokenType::Ident, "e"),
+                (TokenType::InterpolationEnd, ""),
+                (TokenType::RightBrace, "}"),
+                (TokenType::InterpolationEnd, " f"),
+                (TokenType::EOF, ""),
+            ]
+        );
+
+        // The segments span their delimiters, the expressions keep their own spans
+        let tokens = Scanner::new("\"${x}!\"").scan_tokens().unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| (t.span.begin, t.span.end)).collect();
+        assert_eq!(spans, [(0, 3), (3, 4), (4, 7), (7, 7)]);
+
+        assert_eq!(
+            Scanner::new("\"$ {}$\"").scan_tokens().unwrap()[0],
+            Token::new(TokenType::String, "$ {}$", 1, Span { begin: 0, end: 7 })
+        );
+    }
+
+    #[test]
+    fn unterminated_interpolation() {
+        let errors = Scanner::new("print \"a ${b\n;").scan_tokens().unwrap_err();
+        assert_eq!(
+            errors,
+            [TokenError::NonTerminatedString {
+                string: "\"a ${b\n;".to_string(),
+                line: 1,
+                span: Span { begin: 6, end: 14 },
+            }]
+        );
+
+        // Only the innermost string is reported
+        let errors = Scanner::new("\"a ${\"b ${c} d").scan_tokens().unwrap_err();
+        assert_eq!(
+            errors,
+            [TokenError::NonTerminatedString {
+                string: "\"b ${c} d".to_string(),
+                line: 1,
+                span: Span { begin: 5, end: 14 },
+            }]
+        );
+    }
+
     #[test]
     fn ident() {
         let input = "test t123 class";
@@ -479,11 +1092,84 @@ mod test {
         let input = "💣";
         let mut scanner = Scanner::new(input);
         assert_eq!(
-            Err(TokenError::UnexpectedToken("💣".to_string())),
+            Err(TokenError::UnexpectedToken {
+                token: "💣".to_string(),
+                line: 1,
+                span: Span { begin: 0, end: 4 },
+            }),
             scanner.scan_token()
         );
     }
 
+    #[test]
+    fn block_comments() {
+        let input = "/* a\n/* nested\n*/ still */ print /**/ 1;\n/* open /* */";
+        let mut scanner = Scanner::new(input);
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![TokenError::UnterminatedComment {
+                line: 4,
+                span: Span { begin: 41, end: 54 },
+            }]
+        );
+
+        let tokens = Scanner::new("/* a\n/* b */\n*/ print")
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(tokens[0].kind, TokenType::Print);
+        assert_eq!(tokens[0].line, 3);
+    }
+
+    #[test]
+    fn collects_all_errors() {
+        let input = "var a = @;\n#include x\nprint 1_;\nprint a; $\nprint \"open";
+        assert_eq!(
+            Err(vec![
+                TokenError::UnexpectedToken {
+                    token: "@".to_string(),
+                    line: 1,
+                    span: Span { begin: 8, end: 9 },
+                },
+                TokenError::InvalidDirective("#include x".to_string(), "only #define is supported"),
+                TokenError::MalformedNumber(
+                    "1_".to_string(),
+                    "underscores must be placed between two digits"
+                ),
+                TokenError::UnexpectedToken {
+                    token: "$".to_string(),
+                    line: 4,
+                    span: Span { begin: 41, end: 42 },
+                },
+                TokenError::NonTerminatedString {
+                    string: "\"open".to_string(),
+                    line: 5,
+                    span: Span { begin: 49, end: 54 },
+                },
+            ]),
+            Scanner::new(input).scan_tokens()
+        );
+    }
+
+    #[test]
+    fn eof() {
+        let input = "print 1; // done\n\n";
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+        let span = Span { begin: 18, end: 18 };
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[3], Token::new(TokenType::EOF, "", 3, span));
+    }
+
+    #[test]
+    fn leading_whitespace() {
+        let input = "\n\n  print 1;\t\n";
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+        let span = Span { begin: 4, end: 9 };
+        assert_eq!(tokens[0], Token::new(TokenType::Print, "print", 3, span));
+        assert_eq!(&input[4..9], tokens[0].lexeme);
+        assert_eq!(tokens[3].span, Span { begin: 14, end: 14 });
+    }
+
     #[test]
     fn single_line_comment() {
         let mut scanner = Scanner::new("// This is a comment\nvar x");
@@ -508,4 +1194,48 @@ mod test {
             assert_eq!(token, tokens[1]);
         }
     }
+
+    #[test]
+    fn define() {
+        let input = "#define PI 3.5\n#define MIN -1 // lowest\nprint PI + MIN;";
+        let tokens = Scanner::new(input).scan_tokens().unwrap();
+
+        // The value keeps its lexeme but takes the place of the name
+        let span = Span { begin: 46, end: 48 };
+        assert_eq!(
+            tokens[1],
+            Token::new(TokenType::Number(3.5), "3.5", 3, span)
+        );
+        assert_eq!(tokens[3].kind, TokenType::Number(-1.0));
+        assert_eq!(tokens[3].lexeme, "-1");
+    }
+
+    #[test]
+    fn invalid_define() {
+        let cases = [
+            ("#include x", "only #define is supported"),
+            ("#define 1 2", "expected a name"),
+            (
+                "#define A",
+                "the value must be a number, string, boolean or nil",
+            ),
+            (
+                "#define A B",
+                "the value must be a number, string, boolean or nil",
+            ),
+            (
+                "#define A 1 2",
+                "expected the end of the line after the value",
+            ),
+            ("#define A 1\n#define A 2", "the name is already defined"),
+        ];
+
+        for (input, reason) in cases {
+            let errors = Scanner::new(input).scan_tokens().unwrap_err();
+            match errors.as_slice() {
+                [TokenError::InvalidDirective(_, r)] => assert_eq!(*r, reason, "{input}"),
+                other => panic!("{input}: expected an invalid directive, got {other:?}"),
+            }
+        }
+    }
 }
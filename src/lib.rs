@@ -1,4 +1,24 @@
 pub mod ast;
+pub mod diagnostics;
+pub mod doctest;
+pub mod error;
+pub mod explain;
 pub mod interpreter;
 pub mod lex;
+pub mod lint;
 pub mod parser;
+pub mod repl;
+pub mod serve;
+pub mod session;
+pub mod source;
+pub mod stats;
+pub mod stdlib;
+pub mod testing;
+pub mod version;
+pub mod vm;
+pub mod watch;
+
+pub use error::LoxError;
+#[cfg(feature = "threads")]
+pub use session::{run_many, RunResult, SessionThread};
+pub use session::{run_source, Session};
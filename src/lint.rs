@@ -0,0 +1,341 @@
+use std::{collections::HashMap, fmt};
+
+use crate::lex::{Comment, Token};
+
+const IGNORE: &str = "lox-ignore:";
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Lint {
+    UnusedVariable,
+    /// An `if` or loop condition that is an assignment, `token` is its `=`
+    AssignmentInCondition,
+    /// An `if` or loop condition that is a literal, `token` is the `if`, `while` or `for`
+    ConstantCondition,
+    /// A global function or class declared again, `token` is the name of the later one
+    Redefinition,
+    /// A global `var` declared again, `token` is the name of the later one
+    Redeclaration,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[
+        Lint::UnusedVariable,
+        Lint::AssignmentInCondition,
+        Lint::ConstantCondition,
+        Lint::Redefinition,
+        Lint::Redeclaration,
+    ];
+
+    /// The name used to refer to the lint in `// lox-ignore:` comments.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::AssignmentInCondition => "assignment-in-condition",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::Redefinition => "redefinition",
+            Lint::Redeclaration => "redeclaration",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+
+    /// The level of a lint that wasn't configured.
+    fn default_level(&self, strict: bool) -> Level {
+        match (self, strict) {
+            (Lint::Redefinition, true) => Level::Deny,
+            (Lint::Redeclaration, false) => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+}
+
+/// How the warnings of a lint are reported.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Level {
+    Allow,
+    Warn,
+    /// Reported as an error, the script isn't run
+    Deny,
+}
+
+/// The level of each lint. Lints that weren't configured warn, except `redeclaration` which is
+/// only shown in strict mode. In strict mode the lints that point at likely bugs are errors.
+#[derive(Debug, Default, Clone)]
+pub struct Levels {
+    strict: bool,
+    configured: HashMap<Lint, Level>,
+}
+
+impl Levels {
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.configured.insert(lint, level);
+    }
+
+    pub fn get(&self, lint: Lint) -> Level {
+        match self.configured.get(&lint) {
+            Some(&level) => level,
+            None => lint.default_level(self.strict),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Warning<'a> {
+    pub lint: Lint,
+    pub token: Token<'a>,
+}
+
+impl Warning<'_> {
+    /// The warning as reported at `level`, `None` if the lint is allowed.
+    pub fn report(&self, level: Level) -> Option<String> {
+        let kind = match level {
+            Level::Allow => return None,
+            Level::Warn => "Warning",
+            Level::Deny => "Error",
+        };
+        Some(format!(
+            "[line {}] {kind}: {} [{}]",
+            self.token.line,
+            self.message(),
+            self.lint.name()
+        ))
+    }
+
+    fn message(&self) -> String {
+        match self.lint {
+            Lint::UnusedVariable => {
+                format!("Local variable '{}' is never used.", self.token.lexeme)
+            }
+            Lint::AssignmentInCondition => "Assignment used as a condition, did you mean '=='? \
+                 Wrap it in parentheses if the assignment is intended."
+                .to_string(),
+            Lint::ConstantCondition => {
+                format!(
+                    "The condition of this '{}' is a constant.",
+                    self.token.lexeme
+                )
+            }
+            Lint::Redefinition => format!(
+                "'{}' is already defined, this definition replaces it.",
+                self.token.lexeme
+            ),
+            Lint::Redeclaration => format!(
+                "Global '{}' is already declared, this declaration replaces it.",
+                self.token.lexeme
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Warning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.report(Level::Warn).unwrap_or_default())
+    }
+}
+
+/// Drops the warnings silenced by a `// lox-ignore: <lint>, <lint>` comment on the line before
+/// them.
+pub fn suppress<'a>(warnings: Vec<Warning<'a>>, comments: &[Comment]) -> Vec<Warning<'a>> {
+    let mut ignored: HashMap<u32, Vec<&str>> = HashMap::new();
+    for comment in comments {
+        let Some(lints) = comment.text[2..].trim_start().strip_prefix(IGNORE) else {
+            continue;
+        };
+        ignored
+            .entry(comment.line + 1)
+            .or_default()
+            .extend(lints.split(',').map(str::trim));
+    }
+
+    warnings
+        .into_iter()
+        .filter(|warning| {
+            !ignored
+                .get(&warning.token.line)
+                .is_some_and(|lints| lints.contains(&warning.lint.name()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::Arena,
+        interpreter::resolver::Resolver,
+        lex::{Scanner, Span, TokenType},
+        parser::{Parser, TokenStream},
+    };
+
+    use super::*;
+
+    fn warnings(input: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(input);
+        let arena = Arena::new();
+        let tokens = scanner.scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts).unwrap();
+
+        suppress(resolver.take_warnings(), scanner.comments())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn unused_variable() {
+        let input = "var global = 1;
+{
+  var used = 1;
+  var unused = 2;
+  print used;
+}";
+
+        assert_eq!(
+            warnings(input),
+            vec!["[line 4] Warning: Local variable 'unused' is never used. [unused-variable]"]
+        );
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        let input = "var a = 1;
+if (a = clock) print a;
+while (a = a) {}
+for (; a = a;) {}
+if ((a = clock)) print a;
+if (a == 3) print a;";
+
+        assert_eq!(
+            warnings(input),
+            vec![
+                "[line 2] Warning: Assignment used as a condition, did you mean '=='? Wrap it in parentheses if the assignment is intended. [assignment-in-condition]",
+                "[line 3] Warning: Assignment used as a condition, did you mean '=='? Wrap it in parentheses if the assignment is intended. [assignment-in-condition]",
+                "[line 4] Warning: Assignment used as a condition, did you mean '=='? Wrap it in parentheses if the assignment is intended. [assignment-in-condition]",
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_condition() {
+        let input = "var a;
+if (true) print 1;
+while ((nil)) {}
+if (\"yes\") print 2;
+if (a = 1) print a;
+while (true) {}
+for (;;) {}
+if (a) print a;";
+
+        assert_eq!(
+            warnings(input),
+            vec![
+                "[line 2] Warning: The condition of this 'if' is a constant. [constant-condition]",
+                "[line 3] Warning: The condition of this 'while' is a constant. [constant-condition]",
+                "[line 4] Warning: The condition of this 'if' is a constant. [constant-condition]",
+                "[line 5] Warning: The condition of this 'if' is a constant. [constant-condition]",
+                "[line 5] Warning: Assignment used as a condition, did you mean '=='? Wrap it in parentheses if the assignment is intended. [assignment-in-condition]",
+            ]
+        );
+    }
+
+    #[test]
+    fn redefinition() {
+        let input = "fun a() {}
+class B {}
+fun a() {}
+class B {}
+var a = 1;
+{
+  fun c() {}
+}
+fun c() {}";
+
+        assert_eq!(
+            warnings(input),
+            vec![
+                "[line 3] Warning: 'a' is already defined, this definition replaces it. [redefinition]",
+                "[line 4] Warning: 'B' is already defined, this definition replaces it. [redefinition]",
+                "[line 5] Warning: Global 'a' is already declared, this declaration replaces it. [redeclaration]",
+            ]
+        );
+        assert_eq!(Levels::default().get(Lint::Redefinition), Level::Warn);
+        assert_eq!(Levels::strict().get(Lint::Redefinition), Level::Deny);
+    }
+
+    #[test]
+    fn redeclaration() {
+        let input = "var a = 1;
+var a = 2;
+{
+  var b = a;
+  print b;
+}
+var b;
+fun f() {}";
+
+        assert_eq!(
+            warnings(input),
+            vec!["[line 2] Warning: Global 'a' is already declared, this declaration replaces it. [redeclaration]"]
+        );
+        assert_eq!(Levels::default().get(Lint::Redeclaration), Level::Allow);
+        assert_eq!(Levels::strict().get(Lint::Redeclaration), Level::Warn);
+    }
+
+    #[test]
+    fn levels() {
+        let warning = Warning {
+            lint: Lint::UnusedVariable,
+            token: Token::new(TokenType::Ident, "a", 3, Span { begin: 0, end: 1 }),
+        };
+        let mut levels = Levels::default();
+        assert_eq!(levels.get(Lint::UnusedVariable), Level::Warn);
+        assert_eq!(
+            warning.report(levels.get(Lint::UnusedVariable)),
+            Some(warning.to_string())
+        );
+
+        levels.set(Lint::UnusedVariable, Level::Deny);
+        assert_eq!(
+            warning.report(levels.get(Lint::UnusedVariable)).unwrap(),
+            "[line 3] Error: Local variable 'a' is never used. [unused-variable]"
+        );
+        assert_eq!(warning.report(Level::Allow), None);
+
+        assert_eq!(
+            Lint::from_name("constant-condition"),
+            Some(Lint::ConstantCondition)
+        );
+        assert_eq!(Lint::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn ignore_comments() {
+        let input = "{
+  // lox-ignore: unused-variable
+  var a = 1;
+  var b = 2; // lox-ignore: unused-variable
+  // lox-ignore: something-else
+  var c = 3;
+}";
+
+        assert_eq!(
+            warnings(input),
+            vec![
+                "[line 4] Warning: Local variable 'b' is never used. [unused-variable]",
+                "[line 6] Warning: Local variable 'c' is never used. [unused-variable]",
+            ]
+        );
+    }
+}
@@ -1,58 +1,437 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 use rust_lox::{
-    interpreter::{resolver::Resolver, Interpreter},
-    lex::Scanner,
-    parser::{self, TokenStream},
+    ast::{printer::AstPrinter, Arena, Stmt},
+    diagnostics::{ColorChoice, Renderer},
+    doctest,
+    error::LoxError,
+    explain,
+    interpreter::{hooks::Trace, options::Options, resolver::Resolver, Interpreter},
+    lex::{Scanner, Token},
+    lint::{self, Level, Levels, Lint},
+    parser::{self, Limits, TokenStream},
+    repl::Repl,
+    serve::Server,
+    source,
+    stats::{self, Stats},
+    stdlib, testing, version,
+    vm::{compiler::Compiler, ir, Vm},
+    watch::Watcher,
 };
-use std::fs;
+use std::{io, num::NonZeroUsize, path::PathBuf};
+
+#[global_allocator]
+static ALLOC: stats::CountingAlloc = stats::CountingAlloc;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, long_version = version::LONG_VERSION, about, long_about = None)]
 struct Args {
-    #[arg(required = true)]
-    path: String,
+    /// The script to run, without one an interactive session starts
+    path: Option<String>,
+
+    /// Load the Lox standard library into globals before running the script
+    #[arg(long)]
+    stdlib: bool,
+
+    /// Run as a long-lived JSON-RPC server over stdin/stdout instead of running a script. Every
+    /// loaded source is kept until `reset`, so memory grows with each `load`
+    #[arg(long, conflicts_with = "path")]
+    serve: bool,
+
+    /// Run the examples in the script's `///` doc comments instead of the script itself
+    #[arg(long)]
+    doctest: bool,
+
+    /// Run the script's `fun test_*()` functions and report which of them failed
+    #[arg(long, conflicts_with = "doctest")]
+    test: bool,
+
+    /// Run again whenever the script changes, until interrupted
+    #[arg(long)]
+    watch: bool,
+
+    /// Replace bytes that aren't valid UTF-8 in the script with U+FFFD instead of failing
+    #[arg(long)]
+    lossy: bool,
+
+    /// Truncate printed values longer than this many characters
+    #[arg(long, value_name = "CHARS")]
+    max_print_len: Option<usize>,
+
+    /// Limit the number of fields an instance can have
+    #[arg(long, value_name = "N")]
+    max_fields: Option<usize>,
+
+    /// Only allow creating new fields inside `init`
+    #[arg(long)]
+    strict_fields: bool,
+
+    /// Print how much heap memory reading, scanning, parsing, resolving and running took to stderr
+    #[arg(long)]
+    stats: bool,
+
+    /// Print the tokens of the script, one per line, instead of running it
+    #[arg(long, conflicts_with_all = ["doctest", "test"])]
+    tokens: bool,
+
+    /// Print the syntax tree of the script, one top-level statement per line, instead of running
+    /// it
+    #[arg(long, conflicts_with_all = ["doctest", "test", "tokens"])]
+    ast: bool,
+
+    /// Print each statement to stderr before it is executed
+    #[arg(long)]
+    trace: bool,
+
+    /// Print each grammar rule to stderr when the parser enters and leaves it
+    #[arg(long)]
+    trace_parser: bool,
+
+    /// Stop parsing after this many syntax errors instead of reporting all of them
+    #[arg(long, value_name = "N")]
+    max_errors: Option<NonZeroUsize>,
+
+    /// Report lints that point at likely bugs, like redefining a global function, as errors, warn
+    /// about redeclared global variables and make reading an unassigned variable an error
+    #[arg(long)]
+    strict: bool,
+
+    /// Define the `http_get` and `http_post` natives, scripts can't reach the network without it
+    #[cfg(feature = "net")]
+    #[arg(long)]
+    allow_net: bool,
+
+    /// Don't report warnings of this lint, like unused-variable. Can be repeated
+    #[arg(long, value_name = "LINT", value_parser = parse_lint)]
+    allow: Vec<Lint>,
+
+    /// Report warnings of this lint as errors and don't run the script. Can be repeated
+    #[arg(long, value_name = "LINT", value_parser = parse_lint)]
+    deny: Vec<Lint>,
+
+    /// Color errors and warnings, `auto` colors if stderr is a terminal and NO_COLOR isn't set
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Run the script by walking the AST or by compiling it to bytecode for the VM. The VM can't
+    /// be combined with `--stdlib`, the limits, `--strict` or `--trace`
+    #[arg(long, value_enum, default_value_t = Backend::Tree)]
+    backend: Backend,
+
+    /// How much the VM optimizes the bytecode: 0 not at all, 1 fuses common instruction pairs and
+    /// 2 also runs it as register code with copy propagation and dead-store elimination. Implies
+    /// `--backend vm`, the default is 1
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u8).range(0..=2))]
+    opt: Option<u8>,
+
+    /// Print the compiled bytecode to stderr before running it. Implies `--backend vm`
+    #[arg(long)]
+    disassemble: bool,
+
+    /// Print the VM's stack and each instruction to stderr before it is executed. Implies
+    /// `--backend vm`
+    #[arg(long)]
+    trace_exec: bool,
+
+    /// Print a longer explanation of an error code, like E0109
+    #[arg(long, value_name = "CODE", conflicts_with = "path")]
+    explain: Option<String>,
+}
+
+impl Args {
+    /// `--opt`, `--disassemble` and `--trace-exec` imply the VM.
+    fn vm(&self) -> bool {
+        self.backend == Backend::Vm || self.opt.is_some() || self.disassemble || self.trace_exec
+    }
+
+    /// The flags given that only the tree-walker supports. clap can't tie a conflict to the value
+    /// of `--backend`, so they are checked after parsing.
+    fn tree_only(&self) -> Vec<&'static str> {
+        let flags = [
+            ("--stdlib", self.stdlib),
+            ("--max-print-len", self.max_print_len.is_some()),
+            ("--max-fields", self.max_fields.is_some()),
+            ("--strict-fields", self.strict_fields),
+            ("--strict", self.strict),
+            ("--trace", self.trace),
+            #[cfg(feature = "net")]
+            ("--allow-net", self.allow_net),
+        ];
+        flags
+            .into_iter()
+            .filter(|(_, given)| *given)
+            .map(|(flag, _)| flag)
+            .collect()
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Backend {
+    Tree,
+    Vm,
+}
+
+fn parse_lint(name: &str) -> Result<Lint, String> {
+    Lint::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = Lint::ALL.iter().map(Lint::name).collect();
+        format!("unknown lint, expected one of {}", names.join(", "))
+    })
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    if let (true, [flag, ..]) = (args.vm(), args.tree_only().as_slice()) {
+        let message = format!("{flag} isn't supported by the VM backend");
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, message)
+            .exit();
+    }
+
+    if let Some(code) = args.explain {
+        match explain::explain(&code) {
+            Some(explanation) => println!("{explanation}"),
+            None => {
+                eprintln!("{code} is not a known error code.");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
 
-    let binding = fs::read_to_string(&args.path)?;
-    let contents = binding.trim();
+    if args.serve {
+        return Ok(Server::new().run(io::stdin().lock(), io::stdout().lock())?);
+    }
 
-    let mut lexer = Scanner::new(contents);
-    let tokens = lexer.scan_tokens();
+    let renderer = Renderer::new(args.color);
+    let prelude = if args.stdlib { stdlib::load()? } else { &[] };
+
+    let Some(path) = args.path.clone() else {
+        let mut repl = Repl::new(renderer);
+        repl.set_options(options(&args));
+        repl.load_prelude(prelude)?;
+        return Ok(repl.run(io::stdin().lock(), io::stdout().lock())?);
+    };
+
+    if !args.watch {
+        std::process::exit(run(&args, &path, prelude, &renderer)?);
+    }
+
+    let mut watcher = Watcher::new(vec![PathBuf::from(&path)]);
+    loop {
+        match run(&args, &path, prelude, &renderer) {
+            Ok(code) => eprintln!("[watch] Exited with {code}, waiting for changes."),
+            Err(e) => eprintln!("[watch] {path}: {e}, waiting for changes."),
+        }
+        watcher.wait();
+        eprintln!("\n[watch] ---------- {path} changed, running again ----------\n");
+    }
+}
+
+/// Runs the script, its tests or its doc tests once and returns the exit code.
+fn run(
+    args: &Args,
+    path: &str,
+    prelude: &[Stmt<'static>],
+    renderer: &Renderer,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut stats = Stats::new();
+    let code = run_phases(args, path, prelude, renderer, &mut stats);
+    if args.stats {
+        eprintln!("{stats}");
+    }
+    code
+}
+
+fn run_phases(
+    args: &Args,
+    path: &str,
+    prelude: &[Stmt<'static>],
+    renderer: &Renderer,
+    stats: &mut Stats,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let binding = match stats.measure("read", || source::read(path, args.lossy)) {
+        Ok(source) => {
+            stats.note(format!("{} bytes of source", source.len()));
+            source
+        }
+        Err(e) => return Ok(report(renderer, None, e)),
+    };
+
+    if args.doctest {
+        let summary = doctest::run_all(path, &binding, &mut io::stdout())?;
+        return Ok(if summary.failed > 0 { 1 } else { 0 });
+    }
+
+    if args.test {
+        let summary = testing::run_all(path, &binding, &mut io::stdout())?;
+        return Ok(if summary.failed > 0 { 1 } else { 0 });
+    }
+
+    // Not trimmed, the spans of the tokens are byte offsets into the file as it is on disk
+    let mut lexer = Scanner::new(&binding);
+    let tokens = stats.measure("scan", || lexer.scan_tokens());
     let tokens = match tokens {
-        Ok(t) => t,
-        Err(e) => {
-            eprintln!("{e:#}");
-            std::process::exit(65);
+        Ok(t) => {
+            stats.note(format!("{} tokens", t.len()));
+            t
         }
+        Err(errors) => return Ok(report(renderer, Some(&binding), errors)),
     };
+    if args.tokens {
+        for token in &tokens {
+            println!("{:>4} {token}", token.line);
+        }
+        return Ok(0);
+    }
 
-    let mut parser = parser::Parser::new(TokenStream::new(tokens));
-    let stmts = parser.parse();
+    let arena = Arena::new();
+    let mut parser = parser::Parser::new(TokenStream::new(tokens), &arena);
+    parser.set_limits(Limits {
+        max_errors: args.max_errors.map(NonZeroUsize::get),
+        ..Default::default()
+    });
+    if args.trace_parser {
+        parser.set_trace(Box::new(io::stderr()));
+    }
+    let stmts = stats.measure("parse", || parser.parse());
     let stmts = match stmts {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("{e:#}");
-            std::process::exit(65);
+        Ok(s) => {
+            stats.note(format!("{} top-level statements", s.len()));
+            s
         }
+        Err(errors) => return Ok(report(renderer, Some(&binding), errors)),
     };
+    if args.ast {
+        let tree = AstPrinter.print(&stmts);
+        if !tree.is_empty() {
+            println!("{tree}");
+        }
+        return Ok(0);
+    }
 
     let mut resolver = Resolver::new();
-    if let Err(e) = resolver.resolve(&stmts) {
-        eprintln!("{e:#}");
-        std::process::exit(65);
+    let resolved = stats.measure("resolve", || {
+        resolver.resolve(prelude).and_then(|_| {
+            // The prelude is trusted, only warn about the script itself
+            resolver.take_warnings();
+            resolver.resolve(&stmts)
+        })
+    });
+    if let Err(e) = resolved {
+        return Ok(report(renderer, in_script(&binding, e.token()), e));
+    }
+    let mut levels = match args.strict {
+        true => Levels::strict(),
+        false => Levels::default(),
+    };
+    args.allow
+        .iter()
+        .for_each(|&lint| levels.set(lint, Level::Allow));
+    args.deny
+        .iter()
+        .for_each(|&lint| levels.set(lint, Level::Deny));
+
+    let mut analysis = resolver.analysis();
+    let mut denied = false;
+    for warning in lint::suppress(analysis.take_warnings(), lexer.comments()) {
+        let level = levels.get(warning.lint);
+        denied |= level == Level::Deny;
+        if let Some(report) = renderer.warning(&warning, level) {
+            eprintln!("{report}");
+        }
+    }
+    if denied {
+        return Ok(65);
+    }
+
+    if args.vm() {
+        let opt = args.opt.unwrap_or(1);
+        let chunk = stats.measure("compile", || {
+            let mut compiler = Compiler::new();
+            compiler.set_optimize(opt >= 1);
+            compiler.compile_all(&[prelude, &stmts])
+        });
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return Ok(report(renderer, in_script(&binding, Some(e.token())), e)),
+        };
+        stats.note(format!("{} instructions", chunk.code.len()));
+        if args.disassemble {
+            eprint!("{}", chunk.disassemble("script"));
+        }
+        let program = (opt >= 2).then(|| {
+            let mut program = ir::Program::lower(&chunk);
+            program.optimize();
+            program
+        });
+        if let (true, Some(program)) = (args.disassemble, &program) {
+            eprint!("{}", program.listing(&chunk, "ir"));
+        }
+        let mut vm = Vm::new();
+        if args.trace_exec {
+            vm.set_trace(Box::new(io::stderr()));
+        }
+        let result = stats.measure("run", || match &program {
+            Some(program) => vm.run_ir(program, &chunk),
+            None => vm.run(&chunk),
+        });
+        return match result {
+            Ok(_) => Ok(0),
+            Err(e) => Ok(report(renderer, in_script(&binding, e.token()), e)),
+        };
     }
 
-    let mut interpreter = Interpreter::new(resolver.get_locals());
-    let res = interpreter.interpret(&stmts);
+    let mut interpreter = Interpreter::new(analysis);
+    interpreter.set_options(options(args));
+    if args.trace {
+        interpreter.set_hooks(Box::new(Trace::new(options(args))));
+    }
+    let res = stats.measure("run", || {
+        interpreter
+            .interpret(prelude)
+            .and_then(|_| interpreter.interpret(&stmts))
+    });
 
     match res {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            eprintln!("{e:#}");
-            std::process::exit(65);
+        Ok(_) => Ok(0),
+        Err(e) => Ok(report(renderer, in_script(&binding, e.token()), e)),
+    }
+}
+
+/// The interpreter options set by the flags, for scripts and the REPL alike.
+fn options(args: &Args) -> Options {
+    Options {
+        max_print_len: args.max_print_len,
+        max_fields: args.max_fields,
+        strict_fields: args.strict_fields,
+        strict_variables: args.strict,
+        #[cfg(feature = "net")]
+        allow_net: args.allow_net,
+    }
+}
+
+/// Prints the errors of a failed stage, each followed by the code it points at if `source` is
+/// given, and returns the exit code for them.
+fn report(renderer: &Renderer, source: Option<&str>, error: impl Into<LoxError>) -> i32 {
+    let error = error.into();
+    for report in error.reports() {
+        eprintln!("{}", renderer.error(&report.message, report.code));
+        let snippet = source
+            .zip(report.span)
+            .and_then(|(source, span)| renderer.snippet(source, span));
+        if let Some(snippet) = snippet {
+            eprintln!("{snippet}");
         }
     }
+    error.exit_code()
+}
+
+/// The script if the token is part of it. Errors found in the prelude carry spans into the
+/// prelude, which would underline the wrong code.
+fn in_script<'s>(script: &'s str, token: Option<Token>) -> Option<&'s str> {
+    let lexeme = token?.lexeme.as_ptr();
+    script
+        .as_bytes()
+        .as_ptr_range()
+        .contains(&lexeme)
+        .then_some(script)
 }
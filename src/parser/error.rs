@@ -1,106 +1,158 @@
 use strum::EnumDiscriminants;
 use thiserror::Error;
 
-use crate::lex::Token;
+use crate::lex::{Token, TokenType};
 
 // TODO: Shard this into smaller domain rule-specific errors
 #[derive(Error, Debug, PartialEq, PartialOrd, Clone, EnumDiscriminants)]
 #[strum_discriminants(name(ParserErrorContext))]
 pub enum ParserError<'a> {
-    #[error("[line {}] Error: Expected ')' after expression.", token.line)]
+    #[error("[line {}] Error{}: Expected ')' after expression.", token.line, at(token))]
     UnmatchedParanthesis { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected '(' after if.", token.line)]
+    #[error("[line {}] Error{}: Expected '(' after if.", token.line, at(token))]
     ExpectedLeftParenAfterIf { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected '(' after for.", token.line)]
+    #[error("[line {}] Error{}: Expected '(' after for.", token.line, at(token))]
     ExpectedLeftParenAfterFor { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected '(' after while.", token.line)]
+    #[error("[line {}] Error{}: Expected '(' after while.", token.line, at(token))]
     ExpectedLeftParenAfterWhile { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected ')' after condition.", token.line)]
+    #[error("[line {}] Error{}: Expected ')' after condition.", token.line, at(token))]
     ExpectedRightParenAfterCondition { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected ')' after for clauses.", token.line)]
+    #[error("[line {}] Error{}: Expected ')' after for clauses.", token.line, at(token))]
     ExpectedRightParenAfterForClause { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected ')' after for arguments.", token.line)]
+    #[error("[line {}] Error{}: Expected ')' after for arguments.", token.line, at(token))]
     ExpectedRightParenAfterArguments { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected expression.", token.line)]
+    #[error("[line {}] Error{}: Expected expression.", token.line, at(token))]
     ExpectedExpression { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected semicolon.", token.line)]
+    #[error("[line {}] Error{}: Expected semicolon.", token.line, at(token))]
     ExpectedSemicolon { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected function name.", token.line)]
+    #[error("[line {}] Error{}: Expected function name.", token.line, at(token))]
     ExpectedFunctionName { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected ( after function name.", token.line)]
+    #[error("[line {}] Error{}: Expected ( after function name.", token.line, at(token))]
     ExpectedLeftParenAfterFunctionName { token: Token<'a> },
 
-    #[error("[line {}] Error: Can't have more than 255 parameters.", token.line)]
+    #[error("[line {}] Error{}: Can't have more than 255 parameters.", token.line, at(token))]
     TooManyFunctionParameters { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected Parameter Name.", token.line)]
+    #[error("[line {}] Error{}: Expected Parameter Name.", token.line, at(token))]
     ExpectedParameterName { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected ) after parameters.", token.line)]
+    #[error("[line {}] Error{}: Expected ) after parameters.", token.line, at(token))]
     ExpectedRightParenAfterParameters { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected {{ before function body.", token.line)]
+    #[error("[line {}] Error{}: Expected {{ before function body.", token.line, at(token))]
     ExpectedLeftBraceBeforeFunctionBody { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected semicolon after loop condition.", token.line)]
+    #[error("[line {}] Error{}: Expected semicolon after loop condition.", token.line, at(token))]
     ExpectedSemicolonAfterLoopCondition { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected semicolon after return value.", token.line)]
+    #[error("[line {}] Error{}: Expected semicolon after return value.", token.line, at(token))]
     ExpectedSemicolonAfterReturnValue { token: Token<'a> },
 
-    #[error("[line {}] Error: Unexpected token: {}.", token.line, token.lexeme)]
+    #[error("[line {}] Error{}: Unexpected token: {}.", token.line, at(token), token.lexeme)]
     UnexpectedToken { token: Token<'a> },
 
-    #[error("[line {}] Error: Unexpected end of file.", token.line)]
+    #[error("[line {}] Error{}: Unexpected end of file.", token.line, at(token))]
     UnexpectedEOF { token: Token<'a> },
 
-    #[error("[line {}] Error: Invalid assignment target.", token.line)]
+    #[error("[line {}] Error{}: Invalid assignment target.", token.line, at(token))]
     InvalidAssignmentTarget { token: Token<'a> },
 
-    #[error("[line {}] Error: Can't have more than 255 arguments.", token.line)]
+    #[error("[line {}] Error{}: Can't have more than 255 arguments.", token.line, at(token))]
     TooManyFunctionArguments { token: Token<'a> },
 
-    #[error("[line {}] Error: Expected {{ after block.", token.line)]
+    #[error("[line {}] Error{}: Expected {{ after block.", token.line, at(token))]
     ExpectedRightBrace { token: Token<'a> },
 
-    #[error("[line {}] Expected class name.", token.line)]
+    #[error("[line {}] Error{}: Expected class name.", token.line, at(token))]
     ExpectedClassName { token: Token<'a> },
 
-    #[error("[line {}] Expected {{ before class body.", token.line)]
+    #[error("[line {}] Error{}: Expected {{ before class body.", token.line, at(token))]
     ExpectedLeftBraceBeforeClassBody { token: Token<'a> },
 
-    #[error("[line {}] Expected method.", token.line)]
+    #[error("[line {}] Error{}: Expected method.", token.line, at(token))]
     ExpectedMethod { token: Token<'a> },
 
-    #[error("[line {}] Expected }} after class body.", token.line)]
+    #[error("[line {}] Error{}: Expected }} after class body.", token.line, at(token))]
     ExpectedRightBraceAfterClassBody { token: Token<'a> },
 
-    #[error("[line {}] Expected property name after '.'.", token.line)]
+    #[error("[line {}] Error{}: Expected property name after '.'.", token.line, at(token))]
     ExpectedPropertyNameAfterDot { token: Token<'a> },
 
-    #[error("[line {}] Expected superclass name.", token.line)]
+    #[error("[line {}] Error{}: Expected superclass name.", token.line, at(token))]
     ExpectedSuperclassName { token: Token<'a> },
 
-    #[error("[line {}] Expected '.' after 'super'.", token.line)]
+    #[error("[line {}] Error{}: Expected '.' after 'super'.", token.line, at(token))]
     ExpectedDotAfterSuper { token: Token<'a> },
 
-    #[error("[line {}] Expect superclass method name.", token.line)]
+    #[error("[line {}] Error{}: Expect superclass method name.", token.line, at(token))]
     ExpectedSuperclassMethodName { token: Token<'a> },
 
+    #[error("[line {}] Error{}: Nested more than {} levels deep.", token.line, at(token), max)]
+    TooDeep { token: Token<'a>, max: usize },
+
+    #[error("[line {}] Error{}: Can't have more than {} statements.", token.line, at(token), max)]
+    TooManyStatements { token: Token<'a>, max: usize },
+
+    #[error("[line {}] Error{}: Expected ']' after list elements.", token.line, at(token))]
+    ExpectedRightBracketAfterElements { token: Token<'a> },
+
+    #[error("[line {}] Error{}: Expected ']' after index.", token.line, at(token))]
+    ExpectedRightBracketAfterIndex { token: Token<'a> },
+
+    #[error("[line {}] Error{}: Expected ':' after map key.", token.line, at(token))]
+    ExpectedColonAfterKey { token: Token<'a> },
+
+    #[error("[line {}] Error{}: Expected '}}' after map entries.", token.line, at(token))]
+    ExpectedRightBraceAfterEntries { token: Token<'a> },
+
+    #[error("[line {}] Error{}: Expected '}}' after interpolated expression.", token.line, at(token))]
+    ExpectedRightBraceAfterInterpolation { token: Token<'a> },
+
+    #[error("[line {}] Error: Found '{}' after '{}', expected an operator or ';'.", token.line, token.lexeme, after.lexeme)]
+    UnexpectedName { token: Token<'a>, after: Token<'a> },
+
+    /// `token` is the token found instead
+    #[error("[line {}] Error{}: {}", token.line, at(token), expected_one_of(expected, token))]
+    ExpectedOneOf {
+        token: Token<'a>,
+        expected: Vec<TokenType>,
+    },
+
     #[error("{0}")]
     TokenStream(#[from] TokenStreamError),
 }
 
+/// Errors at the EOF token say so, its line alone doesn't tell that the file ended too early.
+fn at(token: &Token) -> &'static str {
+    match token.kind {
+        TokenType::EOF => " at end of file",
+        _ => "",
+    }
+}
+
+fn expected_one_of(expected: &[TokenType], found: &Token) -> String {
+    let mut kinds: Vec<_> = expected.iter().map(TokenType::describe).collect();
+    let last = kinds.pop().unwrap_or_default();
+    let kinds = match kinds.is_empty() {
+        true => last.to_string(),
+        false => format!("{} or {last}", kinds.join(", ")),
+    };
+    match found.kind {
+        TokenType::EOF => format!("Expected {kinds}."),
+        _ => format!("Expected {kinds}, found '{}'.", found.lexeme),
+    }
+}
+
 impl<'a> ParserErrorContext {
     pub fn to_error(self, token: Token<'a>) -> ParserError<'a> {
         match self {
@@ -179,6 +231,128 @@ impl<'a> ParserErrorContext {
             ParserErrorContext::ExpectedSuperclassMethodName => {
                 ParserError::ExpectedSuperclassMethodName { token }
             }
+            ParserErrorContext::ExpectedRightBracketAfterElements => {
+                ParserError::ExpectedRightBracketAfterElements { token }
+            }
+            ParserErrorContext::ExpectedRightBracketAfterIndex => {
+                ParserError::ExpectedRightBracketAfterIndex { token }
+            }
+            ParserErrorContext::ExpectedColonAfterKey => {
+                ParserError::ExpectedColonAfterKey { token }
+            }
+            ParserErrorContext::ExpectedRightBraceAfterEntries => {
+                ParserError::ExpectedRightBraceAfterEntries { token }
+            }
+            ParserErrorContext::ExpectedRightBraceAfterInterpolation => {
+                ParserError::ExpectedRightBraceAfterInterpolation { token }
+            }
+            ParserErrorContext::ExpectedOneOf => ParserError::ExpectedOneOf {
+                token,
+                expected: vec![],
+            },
+            ParserErrorContext::UnexpectedName => ParserError::UnexpectedName {
+                token,
+                after: token,
+            },
+            // Limit errors are created directly, they need the limit
+            ParserErrorContext::TooDeep => ParserError::TooDeep { token, max: 0 },
+            ParserErrorContext::TooManyStatements => {
+                ParserError::TooManyStatements { token, max: 0 }
+            }
+        }
+    }
+}
+
+impl<'a> ParserError<'a> {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::UnmatchedParanthesis { .. } => "E0101",
+            ParserError::ExpectedLeftParenAfterIf { .. } => "E0102",
+            ParserError::ExpectedLeftParenAfterFor { .. } => "E0103",
+            ParserError::ExpectedLeftParenAfterWhile { .. } => "E0104",
+            ParserError::ExpectedRightParenAfterCondition { .. } => "E0105",
+            ParserError::ExpectedRightParenAfterForClause { .. } => "E0106",
+            ParserError::ExpectedRightParenAfterArguments { .. } => "E0107",
+            ParserError::ExpectedExpression { .. } => "E0108",
+            ParserError::ExpectedSemicolon { .. } => "E0109",
+            ParserError::ExpectedFunctionName { .. } => "E0110",
+            ParserError::ExpectedLeftParenAfterFunctionName { .. } => "E0111",
+            ParserError::TooManyFunctionParameters { .. } => "E0112",
+            ParserError::ExpectedParameterName { .. } => "E0113",
+            ParserError::ExpectedRightParenAfterParameters { .. } => "E0114",
+            ParserError::ExpectedLeftBraceBeforeFunctionBody { .. } => "E0115",
+            ParserError::ExpectedSemicolonAfterLoopCondition { .. } => "E0116",
+            ParserError::ExpectedSemicolonAfterReturnValue { .. } => "E0117",
+            ParserError::UnexpectedToken { .. } => "E0118",
+            ParserError::UnexpectedEOF { .. } => "E0119",
+            ParserError::InvalidAssignmentTarget { .. } => "E0120",
+            ParserError::TooManyFunctionArguments { .. } => "E0121",
+            ParserError::ExpectedRightBrace { .. } => "E0122",
+            ParserError::ExpectedClassName { .. } => "E0123",
+            ParserError::ExpectedLeftBraceBeforeClassBody { .. } => "E0124",
+            ParserError::ExpectedMethod { .. } => "E0125",
+            ParserError::ExpectedRightBraceAfterClassBody { .. } => "E0126",
+            ParserError::ExpectedPropertyNameAfterDot { .. } => "E0127",
+            ParserError::ExpectedSuperclassName { .. } => "E0128",
+            ParserError::ExpectedDotAfterSuper { .. } => "E0129",
+            ParserError::ExpectedSuperclassMethodName { .. } => "E0130",
+            ParserError::TooDeep { .. } => "E0131",
+            ParserError::TooManyStatements { .. } => "E0132",
+            ParserError::UnexpectedName { .. } => "E0133",
+            ParserError::ExpectedOneOf { .. } => "E0134",
+            ParserError::ExpectedRightBracketAfterElements { .. } => "E0135",
+            ParserError::ExpectedRightBracketAfterIndex { .. } => "E0136",
+            ParserError::ExpectedColonAfterKey { .. } => "E0137",
+            ParserError::ExpectedRightBraceAfterEntries { .. } => "E0138",
+            ParserError::ExpectedRightBraceAfterInterpolation { .. } => "E0139",
+            ParserError::TokenStream(_) => "E0100",
+        }
+    }
+
+    /// The token the error was found at.
+    pub fn token(&self) -> Option<Token<'a>> {
+        match self {
+            ParserError::UnmatchedParanthesis { token, .. }
+            | ParserError::ExpectedLeftParenAfterIf { token, .. }
+            | ParserError::ExpectedLeftParenAfterFor { token, .. }
+            | ParserError::ExpectedLeftParenAfterWhile { token, .. }
+            | ParserError::ExpectedRightParenAfterCondition { token, .. }
+            | ParserError::ExpectedRightParenAfterForClause { token, .. }
+            | ParserError::ExpectedRightParenAfterArguments { token, .. }
+            | ParserError::ExpectedExpression { token, .. }
+            | ParserError::ExpectedSemicolon { token, .. }
+            | ParserError::ExpectedFunctionName { token, .. }
+            | ParserError::ExpectedLeftParenAfterFunctionName { token, .. }
+            | ParserError::TooManyFunctionParameters { token, .. }
+            | ParserError::ExpectedParameterName { token, .. }
+            | ParserError::ExpectedRightParenAfterParameters { token, .. }
+            | ParserError::ExpectedLeftBraceBeforeFunctionBody { token, .. }
+            | ParserError::ExpectedSemicolonAfterLoopCondition { token, .. }
+            | ParserError::ExpectedSemicolonAfterReturnValue { token, .. }
+            | ParserError::UnexpectedToken { token, .. }
+            | ParserError::UnexpectedEOF { token, .. }
+            | ParserError::InvalidAssignmentTarget { token, .. }
+            | ParserError::TooManyFunctionArguments { token, .. }
+            | ParserError::ExpectedRightBrace { token, .. }
+            | ParserError::ExpectedClassName { token, .. }
+            | ParserError::ExpectedLeftBraceBeforeClassBody { token, .. }
+            | ParserError::ExpectedMethod { token, .. }
+            | ParserError::ExpectedRightBraceAfterClassBody { token, .. }
+            | ParserError::ExpectedPropertyNameAfterDot { token, .. }
+            | ParserError::ExpectedSuperclassName { token, .. }
+            | ParserError::ExpectedDotAfterSuper { token, .. }
+            | ParserError::ExpectedSuperclassMethodName { token, .. }
+            | ParserError::TooDeep { token, .. }
+            | ParserError::TooManyStatements { token, .. }
+            | ParserError::UnexpectedName { token, .. }
+            | ParserError::ExpectedOneOf { token, .. }
+            | ParserError::ExpectedRightBracketAfterElements { token, .. }
+            | ParserError::ExpectedRightBracketAfterIndex { token, .. }
+            | ParserError::ExpectedColonAfterKey { token, .. }
+            | ParserError::ExpectedRightBraceAfterEntries { token, .. }
+            | ParserError::ExpectedRightBraceAfterInterpolation { token, .. } => Some(*token),
+            ParserError::TokenStream(_) => None,
         }
     }
 }
@@ -1,19 +1,26 @@
 pub mod error;
 
+use std::io::Write;
+
 use error::{ParserError, ParserErrorContext, TokenStreamError};
 
 use crate::{
     ast::{
-        Expr, ExprAssign, ExprBinary, ExprCall, ExprGet, ExprGrouping, ExprLiteral, ExprLogical,
-        ExprSet, ExprSuper, ExprThis, ExprUnary, ExprVariable, LiteralValue, Stmt, StmtBlock,
-        StmtClass, StmtExpression, StmtFunction, StmtIf, StmtPrint, StmtReturn, StmtVar, StmtWhile,
+        Arena, Expr, ExprAssign, ExprBinary, ExprCall, ExprCompound, ExprGet, ExprGrouping,
+        ExprIndex, ExprInterpolation, ExprList, ExprLiteral, ExprLogical, ExprMap, ExprSet,
+        ExprSetIndex, ExprSuper, ExprThis, ExprUnary, ExprUpdate, ExprVariable, LiteralValue, Stmt,
+        StmtBlock, StmtBreak, StmtClass, StmtContinue, StmtExpression, StmtFunction, StmtIf,
+        StmtPrint, StmtReturn, StmtVar, StmtWhile,
     },
-    lex::{Token, TokenType},
+    lex::{Span, Token, TokenType},
 };
 
 pub struct TokenStream<'a> {
     tokens: Vec<Token<'a>>,
     position: usize,
+    /// Kinds `match_expected` looked for in vain at `expected_at`
+    expected: Vec<TokenType>,
+    expected_at: usize,
 }
 
 impl<'a> TokenStream<'a> {
@@ -21,6 +28,8 @@ impl<'a> TokenStream<'a> {
         Self {
             tokens,
             position: 0,
+            expected: vec![],
+            expected_at: 0,
         }
     }
 
@@ -31,10 +40,21 @@ impl<'a> TokenStream<'a> {
         Ok(&self.tokens[self.position])
     }
 
+    /// The token `k` places after the current one, `peek_nth(0)` is the current token. Looking past
+    /// the end gives the EOF token.
+    fn peek_nth(&self, k: usize) -> &Token<'a> {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.position + k).min(last)]
+    }
+
     fn is_at_end(&self) -> bool {
         self.tokens[self.position].kind == TokenType::EOF
     }
 
+    fn skip_to_end(&mut self) {
+        self.position = self.tokens.len() - 1;
+    }
+
     fn previous(&self) -> Result<Token<'a>, TokenStreamError> {
         if self.position == 0 {
             return Err(TokenStreamError::OutOfBounds);
@@ -67,6 +87,20 @@ impl<'a> TokenStream<'a> {
         Ok(false)
     }
 
+    /// Like `match_l`, but remembers the kinds as acceptable at this point, so a `consume` failing
+    /// right after can name all of them.
+    fn match_expected(&mut self, kinds: &[TokenType]) -> Result<bool, TokenStreamError> {
+        if self.match_l(kinds)? {
+            return Ok(true);
+        }
+        if self.expected_at != self.position {
+            self.expected.clear();
+            self.expected_at = self.position;
+        }
+        self.expected.extend_from_slice(kinds);
+        Ok(false)
+    }
+
     fn consume(
         &mut self,
         kind: &TokenType,
@@ -76,71 +110,251 @@ impl<'a> TokenStream<'a> {
             let token = self.advance()?;
             return Ok(token);
         }
-        let err = error_context.to_error(self.previous()?);
-        Err(err)
+
+        if self.expected_at == self.position && !self.expected.is_empty() {
+            let mut expected = std::mem::take(&mut self.expected);
+            expected.push(*kind);
+            return Err(ParserError::ExpectedOneOf {
+                token: *self.peek()?,
+                expected,
+            });
+        }
+        // Past the last token there is nothing left to blame but the end of the file
+        let token = match self.is_at_end() {
+            true => *self.peek()?,
+            false => self.previous()?,
+        };
+        Err(error_context.to_error(token))
+    }
+}
+
+/// Bounds that keep hostile or generated input from exhausting the host's stack or memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// How deep expressions and statements may be nested in each other
+    pub max_depth: usize,
+    pub max_statements: Option<usize>,
+    /// Parsing stops after this many errors, the rest of the input isn't checked. At least one
+    /// error is always reported
+    pub max_errors: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_statements: None,
+            max_errors: None,
+        }
     }
 }
 
 pub struct Parser<'a> {
     tokenstream: TokenStream<'a>,
+    /// Where the nodes of the parsed trees are allocated
+    arena: &'a Arena<'a>,
+    errors: Vec<ParserError<'a>>,
+    limits: Limits,
+    depth: usize,
+    statements: usize,
+    /// Set once a limit was exceeded
+    aborted: bool,
+    /// Where `rule` logs the grammar rules, see `--trace-parser`
+    trace: Option<Box<dyn Write + 'a>>,
+    rules: usize,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(tokenstream: TokenStream<'a>) -> Self {
-        Self { tokenstream }
+    pub fn new(tokenstream: TokenStream<'a>, arena: &'a Arena<'a>) -> Self {
+        Self {
+            tokenstream,
+            arena,
+            errors: vec![],
+            limits: Limits::default(),
+            depth: 0,
+            statements: 0,
+            aborted: false,
+            trace: None,
+            rules: 0,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Logs each grammar rule to `out` when it is entered and left, indented by how many rules
+    /// are still running.
+    pub fn set_trace(&mut self, out: Box<dyn Write + 'a>) {
+        self.trace = Some(out);
+    }
+
+    /// Runs the grammar rule `name`, which `parse` implements.
+    fn rule<T>(
+        &mut self,
+        name: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError<'a>>,
+    ) -> Result<T, ParserError<'a>> {
+        if self.trace.is_none() {
+            return parse(self);
+        }
+
+        self.log(&format!("{name} at {}", self.current()));
+        self.rules += 1;
+        let result = parse(self);
+        self.rules -= 1;
+        match &result {
+            Ok(_) => self.log(&format!("{name} done, next {}", self.current())),
+            Err(_) => self.log(&format!("{name} failed at {}", self.current())),
+        }
+        result
+    }
+
+    fn log(&mut self, message: &str) {
+        let indent = "  ".repeat(self.rules);
+        if let Some(out) = self.trace.as_mut() {
+            // Tracing is a debugging aid, it must not change the outcome of parsing
+            let _ = writeln!(out, "[parse] {indent}{message}");
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt<'a>>, ParserError<'a>> {
+    /// The current token as shown in the trace, like `'print' (line 1)`.
+    fn current(&self) -> String {
+        let token = self.tokenstream.peek_nth(0);
+        match token.kind {
+            TokenType::EOF => token.kind.describe().to_string(),
+            _ => format!("'{}' (line {})", token.lexeme, token.line),
+        }
+    }
+
+    /// Runs `parse` one nesting level deeper, failing once `max_depth` is exceeded instead of
+    /// overflowing the stack.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError<'a>>,
+    ) -> Result<T, ParserError<'a>> {
+        if self.depth >= self.limits.max_depth {
+            return Err(ParserError::TooDeep {
+                token: *self.tokenstream.peek()?,
+                max: self.limits.max_depth,
+            });
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Chains like `a = b = c` or `!!x` are parsed in a loop, but the resolver and interpreter
+    /// still walk the resulting tree recursively, so each link counts as a nesting level.
+    fn check_chain(&mut self, links: usize) -> Result<(), ParserError<'a>> {
+        match self.depth + links > self.limits.max_depth {
+            true => Err(ParserError::TooDeep {
+                token: self.tokenstream.previous()?,
+                max: self.limits.max_depth,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// Parses the whole token stream, recovering after each error so that every error in the
+    /// source is reported, not just the first one.
+    pub fn parse(&mut self) -> Result<Vec<Stmt<'a>>, Vec<ParserError<'a>>> {
         let mut statements = vec![];
         while !self.tokenstream.is_at_end() {
-            statements.push(self.declaration()?);
+            let start = self.tokenstream.position;
+            match self.rule("declaration", Self::declaration) {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => {
+                    if let Err(e) = self.recover(e, start) {
+                        self.errors.push(e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(max) = self.limits.max_errors {
+            self.errors.truncate(max.max(1));
+        }
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
-        Ok(statements)
     }
 
-    fn try_with_sync<T, F>(&mut self, f: F) -> Result<T, ParserError<'a>>
-    where
-        F: FnOnce(&mut Self) -> Result<T, ParserError<'a>>,
-    {
-        match f(self) {
-            Ok(res) => Ok(res),
-            Err(e) => {
-                self.synchronize()?;
-                Err(e)
-            }
+    /// Records the error and skips ahead to the next statement. `start` is the position the failed
+    /// statement started at, so we never get stuck on a token that can't start a statement.
+    fn recover(&mut self, error: ParserError<'a>, start: usize) -> Result<(), ParserError<'a>> {
+        if !self.record(error) {
+            return Ok(());
+        }
+        if self.tokenstream.position == start {
+            self.tokenstream.advance()?;
+        }
+        self.synchronize()
+    }
+
+    /// Records an error, returns false once a limit was hit. Input past a limit can't be parsed
+    /// reliably, so the rest of it is skipped instead of reporting an error per nesting level.
+    fn record(&mut self, error: ParserError<'a>) -> bool {
+        if self.aborted {
+            return false;
+        }
+
+        let limit = matches!(
+            error,
+            ParserError::TooDeep { .. } | ParserError::TooManyStatements { .. }
+        );
+        self.errors.push(error);
+        let limit = limit
+            || self
+                .limits
+                .max_errors
+                .is_some_and(|max| self.errors.len() >= max);
+        if limit {
+            self.aborted = true;
+            self.tokenstream.skip_to_end();
         }
+        !limit
     }
 
     fn declaration(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        self.statements += 1;
+        if let Some(max) = self.limits.max_statements {
+            if self.statements > max {
+                return Err(ParserError::TooManyStatements {
+                    token: *self.tokenstream.peek()?,
+                    max,
+                });
+            }
+        }
+
         if self.tokenstream.match_l(&[TokenType::Var])? {
-            return self.try_with_sync(|s| s.var_declaration());
+            return self.rule("var_declaration", Self::var_declaration);
         }
 
         if self.tokenstream.match_l(&[TokenType::Class])? {
-            return self.try_with_sync(|s| s.class_declaration());
+            return self.rule("class_declaration", Self::class_declaration);
         }
 
         if self.tokenstream.match_l(&[TokenType::Fun])? {
-            return self.try_with_sync(|s| s.function(ParserErrorContext::ExpectedFunctionName));
+            return self.rule("function", |parser| {
+                parser.function(ParserErrorContext::ExpectedFunctionName)
+            });
         }
 
-        self.statement()
+        self.rule("statement", Self::statement)
     }
 
     fn class_declaration(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
-        let name = self
-            .tokenstream
-            .consume(&TokenType::Ident, ParserErrorContext::ExpectedClassName)?;
+        let name = self.name(ParserErrorContext::ExpectedClassName)?;
 
         let mut superclass = None;
-        if self.tokenstream.match_l(&[TokenType::Less])? {
-            self.tokenstream.consume(
-                &TokenType::Ident,
-                ParserErrorContext::ExpectedSuperclassName,
-            )?;
-            superclass = Some(Expr::Variable(ExprVariable::new(
-                self.tokenstream.previous()?,
-            )));
+        if self.tokenstream.match_expected(&[TokenType::Less])? {
+            superclass = self.rule("superclass", Self::superclass)?;
         }
 
         self.tokenstream.consume(
@@ -149,85 +363,153 @@ impl<'a> Parser<'a> {
         )?;
 
         let mut methods = vec![];
+        let mut class_methods = vec![];
 
         while !self.tokenstream.check(&TokenType::RightBrace)? && !self.tokenstream.is_at_end() {
-            let Stmt::Function(fun) = self.function(ParserErrorContext::ExpectedMethod)? else {
-                // TODO: Handle errror
-                panic!("not a function");
-            };
-
-            methods.push(fun);
+            let start = self.tokenstream.position;
+            let is_class_method = self.tokenstream.match_l(&[TokenType::Class])?;
+            match self.rule("function", |parser| {
+                parser.function(ParserErrorContext::ExpectedMethod)
+            }) {
+                Ok(Stmt::Function(fun)) if is_class_method => class_methods.push(fun),
+                Ok(Stmt::Function(fun)) => methods.push(fun),
+                Ok(_) => unreachable!("function() only returns function statements"),
+                Err(e) => {
+                    if !self.record(e) {
+                        continue;
+                    }
+                    if self.tokenstream.position == start {
+                        self.tokenstream.advance()?;
+                    }
+                    self.synchronize_method()?;
+                }
+            }
         }
         self.tokenstream.consume(
             &TokenType::RightBrace,
             ParserErrorContext::ExpectedRightBraceAfterClassBody,
         )?;
 
-        Ok(Stmt::Class(StmtClass::new(name, superclass, methods)))
+        Ok(Stmt::Class(StmtClass::new(
+            name,
+            superclass,
+            methods,
+            class_methods,
+        )))
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
-        let name = self.tokenstream.consume(
+    /// The name after `<`. A missing or misspelled name directly before the class body is recorded
+    /// and the class is parsed without a superclass, so its body doesn't cause more errors.
+    fn superclass(&mut self) -> Result<Option<Expr<'a>>, ParserError<'a>> {
+        let error = match self.tokenstream.consume(
             &TokenType::Ident,
-            ParserErrorContext::InvalidAssignmentTarget,
-        )?;
+            ParserErrorContext::ExpectedSuperclassName,
+        ) {
+            Ok(name) => return Ok(Some(Expr::Variable(ExprVariable::new(name)))),
+            Err(error) => error,
+        };
+
+        if self.tokenstream.peek_nth(1).kind == TokenType::LeftBrace
+            && !self.tokenstream.check(&TokenType::LeftBrace)?
+        {
+            self.tokenstream.advance()?;
+        }
+        if !self.tokenstream.check(&TokenType::LeftBrace)? {
+            return Err(error);
+        }
+        self.record(error);
+        Ok(None)
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        let name = self.name(ParserErrorContext::InvalidAssignmentTarget)?;
         let mut initializer = None;
-        if self.tokenstream.match_l(&[TokenType::Equal])? {
-            initializer = Some(self.expression()?);
+        if self.tokenstream.match_expected(&[TokenType::Equal])? {
+            initializer = Some(self.rule("expression", Self::expression)?);
         }
-        self.tokenstream
-            .consume(&TokenType::Semicolon, ParserErrorContext::ExpectedSemicolon)?;
+        self.semicolon(ParserErrorContext::ExpectedSemicolon)?;
         Ok(Stmt::Var(StmtVar::new(name, initializer)))
     }
 
     fn statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        self.nested(Self::statement_inner)
+    }
+
+    fn statement_inner(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
         if self.tokenstream.match_l(&[TokenType::If])? {
-            return self.if_statement();
+            return self.rule("if_statement", Self::if_statement);
         }
         if self.tokenstream.match_l(&[TokenType::Print])? {
-            return self.print_statement();
+            return self.rule("print_statement", Self::print_statement);
         }
         if self.tokenstream.match_l(&[TokenType::Return])? {
-            return self.return_statement();
+            return self.rule("return_statement", Self::return_statement);
+        }
+        if self
+            .tokenstream
+            .match_l(&[TokenType::Break, TokenType::Continue])?
+        {
+            return self.rule("loop_control_statement", Self::loop_control_statement);
         }
         if self.tokenstream.match_l(&[TokenType::LeftBrace])? {
-            return Ok(Stmt::Block(StmtBlock::new(self.block()?)));
+            return Ok(Stmt::Block(StmtBlock::new(
+                self.rule("block", Self::block)?,
+            )));
         }
         if self.tokenstream.match_l(&[TokenType::While])? {
-            return self.while_statement();
+            return self.rule("while_statement", Self::while_statement);
         }
         if self.tokenstream.match_l(&[TokenType::For])? {
-            return self.for_statement();
+            return self.rule("for_statement", Self::for_statement);
+        }
+
+        // Two names in a row can't start an expression, like a misspelled `var` in `let x = 1;`
+        let (first, second) = (self.tokenstream.peek_nth(0), self.tokenstream.peek_nth(1));
+        if first.kind == TokenType::Ident && second.kind == TokenType::Ident {
+            return Err(ParserError::UnexpectedName {
+                token: *second,
+                after: *first,
+            });
         }
 
-        self.expression_statement()
+        self.rule("expression_statement", Self::expression_statement)
     }
 
     fn if_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        let keyword = self.tokenstream.previous()?;
         self.tokenstream.consume(
             &TokenType::LeftParen,
             ParserErrorContext::ExpectedLeftParenAfterIf,
         )?;
-        let condition = self.expression()?;
+        let condition = self.rule("expression", Self::expression)?;
         self.tokenstream.consume(
             &TokenType::RightParen,
             ParserErrorContext::ExpectedRightParenAfterCondition,
         )?;
 
-        let then_branch = Box::new(self.statement()?);
+        let then_branch = self.arena.stmt(self.rule("statement", Self::statement)?);
         let mut else_branch = None;
         if self.tokenstream.match_l(&[TokenType::Else])? {
-            else_branch = Some(Box::new(self.statement()?));
+            else_branch = Some(self.arena.stmt(self.rule("statement", Self::statement)?));
         }
 
-        Ok(Stmt::If(StmtIf::new(condition, then_branch, else_branch)))
+        Ok(Stmt::If(StmtIf::new(
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        )))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt<'a>>, ParserError<'a>> {
         let mut statements = vec![];
 
         while !self.tokenstream.check(&TokenType::RightBrace)? && !self.tokenstream.is_at_end() {
-            statements.push(self.declaration()?);
+            let start = self.tokenstream.position;
+            match self.rule("declaration", Self::declaration) {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => self.recover(e, start)?,
+            }
         }
 
         self.tokenstream.consume(
@@ -239,9 +521,8 @@ impl<'a> Parser<'a> {
     }
 
     fn print_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
-        let value = self.expression()?;
-        self.tokenstream
-            .consume(&TokenType::Semicolon, ParserErrorContext::ExpectedSemicolon)?;
+        let value = self.rule("expression", Self::expression)?;
+        self.semicolon(ParserErrorContext::ExpectedSemicolon)?;
         Ok(Stmt::Print(StmtPrint::new(value)))
     }
 
@@ -250,34 +531,48 @@ impl<'a> Parser<'a> {
         let mut value = None;
 
         if !self.tokenstream.check(&TokenType::Semicolon)? {
-            value = Some(self.expression()?);
+            value = Some(self.rule("expression", Self::expression)?);
         }
 
-        self.tokenstream.consume(
-            &TokenType::Semicolon,
-            ParserErrorContext::ExpectedSemicolonAfterReturnValue,
-        )?;
+        self.semicolon(ParserErrorContext::ExpectedSemicolonAfterReturnValue)?;
 
         Ok(Stmt::Return(StmtReturn::new(keyword, value)))
     }
 
+    fn loop_control_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        let keyword = self.tokenstream.previous()?;
+        self.semicolon(ParserErrorContext::ExpectedSemicolon)?;
+
+        match keyword.kind {
+            TokenType::Break => Ok(Stmt::Break(StmtBreak::new(keyword))),
+            _ => Ok(Stmt::Continue(StmtContinue::new(keyword))),
+        }
+    }
+
     fn while_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        let keyword = self.tokenstream.previous()?;
         self.tokenstream.consume(
             &TokenType::LeftParen,
             ParserErrorContext::ExpectedLeftParenAfterWhile,
         )?;
-        let condition = self.expression()?;
+        let condition = self.rule("expression", Self::expression)?;
         self.tokenstream.consume(
             &TokenType::RightParen,
             ParserErrorContext::ExpectedRightParenAfterCondition,
         )?;
 
-        let body = self.statement()?;
+        let body = self.rule("statement", Self::statement)?;
 
-        Ok(Stmt::While(StmtWhile::new(condition, Box::new(body))))
+        Ok(Stmt::While(StmtWhile::new(
+            keyword,
+            condition,
+            self.arena.stmt(body),
+            None,
+        )))
     }
 
     fn for_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
+        let keyword = self.tokenstream.previous()?;
         self.tokenstream.consume(
             &TokenType::LeftParen,
             ParserErrorContext::ExpectedLeftParenAfterFor,
@@ -288,14 +583,14 @@ impl<'a> Parser<'a> {
         if self.tokenstream.match_l(&[TokenType::Semicolon])? {
             initializer = None;
         } else if self.tokenstream.match_l(&[TokenType::Var])? {
-            initializer = Some(self.var_declaration()?);
+            initializer = Some(self.rule("var_declaration", Self::var_declaration)?);
         } else {
-            initializer = Some(self.expression_statement()?);
+            initializer = Some(self.rule("expression_statement", Self::expression_statement)?);
         }
 
         let mut condition = None;
         if !self.tokenstream.check(&TokenType::Semicolon)? {
-            condition = Some(self.expression()?);
+            condition = Some(self.rule("expression", Self::expression)?);
         }
         self.tokenstream.consume(
             &TokenType::Semicolon,
@@ -304,30 +599,17 @@ impl<'a> Parser<'a> {
 
         let mut increment = None;
         if !self.tokenstream.check(&TokenType::RightParen)? {
-            increment = Some(self.expression()?);
+            increment = Some(self.rule("expression", Self::expression)?);
         }
         self.tokenstream.consume(
             &TokenType::RightParen,
             ParserErrorContext::ExpectedRightParenAfterForClause,
         )?;
 
-        let mut body = self.statement()?;
-
-        if let Some(increment) = increment {
-            body = Stmt::Block(StmtBlock::new(vec![
-                body,
-                Stmt::Expression(StmtExpression::new(increment)),
-            ]));
-        }
-
-        if let Some(condition) = condition {
-            body = Stmt::While(StmtWhile::new(condition, Box::new(body)));
-        } else {
-            body = Stmt::While(StmtWhile::new(
-                Expr::Literal(ExprLiteral::new(LiteralValue::Bool(true))),
-                Box::new(body),
-            ));
-        }
+        let body = self.arena.stmt(self.rule("statement", Self::statement)?);
+        let condition =
+            condition.unwrap_or_else(|| Expr::Literal(ExprLiteral::new(LiteralValue::Bool(true))));
+        let mut body = Stmt::While(StmtWhile::new(keyword, condition, body, increment));
 
         if let Some(initializer) = initializer {
             body = Stmt::Block(StmtBlock::new(vec![initializer, body]));
@@ -337,14 +619,29 @@ impl<'a> Parser<'a> {
     }
 
     fn expression_statement(&mut self) -> Result<Stmt<'a>, ParserError<'a>> {
-        let value = self.expression()?;
-        self.tokenstream
-            .consume(&TokenType::Semicolon, ParserErrorContext::ExpectedSemicolon)?;
+        let value = self.rule("expression", Self::expression)?;
+        self.semicolon(ParserErrorContext::ExpectedSemicolon)?;
         Ok(Stmt::Expression(StmtExpression::new(value)))
     }
 
     fn function(&mut self, kind: ParserErrorContext) -> Result<Stmt<'a>, ParserError<'a>> {
-        let name = self.tokenstream.consume(&TokenType::Ident, kind)?;
+        self.nested(|parser| parser.function_inner(kind))
+    }
+
+    fn function_inner(&mut self, kind: ParserErrorContext) -> Result<Stmt<'a>, ParserError<'a>> {
+        // A class body is only methods, a keyword there is more likely a misplaced statement
+        let name = match kind {
+            ParserErrorContext::ExpectedMethod => {
+                self.tokenstream.consume(&TokenType::Ident, kind)?
+            }
+            _ => self.name(kind)?,
+        };
+        if kind == ParserErrorContext::ExpectedMethod
+            && self.tokenstream.match_expected(&[TokenType::LeftBrace])?
+        {
+            let body = self.rule("block", Self::block)?;
+            return Ok(Stmt::Function(StmtFunction::new(name, vec![], body, true)));
+        }
         self.tokenstream.consume(
             &TokenType::LeftParen,
             ParserErrorContext::ExpectedLeftParenAfterFunctionName,
@@ -353,25 +650,23 @@ impl<'a> Parser<'a> {
         let mut parameters = vec![];
         if !self.tokenstream.check(&TokenType::RightParen)? {
             if parameters.len() >= 255 {
-                let token = self.tokenstream.peek()?;
-                let err = ParserError::TooManyFunctionParameters { token: *token };
-                eprintln!("{}", err);
+                let token = *self.tokenstream.peek()?;
+                self.errors
+                    .push(ParserError::TooManyFunctionParameters { token });
             }
-            parameters.push(
-                self.tokenstream
-                    .consume(&TokenType::Ident, ParserErrorContext::ExpectedParameterName)?,
-            );
+            parameters.push(self.name(ParserErrorContext::ExpectedParameterName)?);
 
-            while self.tokenstream.match_l(&[TokenType::Comma])? {
+            while self.tokenstream.match_expected(&[TokenType::Comma])? {
+                // Allow a trailing comma
+                if self.tokenstream.check(&TokenType::RightParen)? {
+                    break;
+                }
                 if parameters.len() >= 255 {
-                    let token = self.tokenstream.peek()?;
-                    let err = ParserError::TooManyFunctionParameters { token: *token };
-                    eprintln!("{}", err);
+                    let token = *self.tokenstream.peek()?;
+                    self.errors
+                        .push(ParserError::TooManyFunctionParameters { token });
                 }
-                parameters.push(
-                    self.tokenstream
-                        .consume(&TokenType::Ident, ParserErrorContext::ExpectedParameterName)?,
-                );
+                parameters.push(self.name(ParserErrorContext::ExpectedParameterName)?);
             }
         }
         self.tokenstream.consume(
@@ -383,17 +678,19 @@ impl<'a> Parser<'a> {
             &TokenType::LeftBrace,
             ParserErrorContext::ExpectedLeftBraceBeforeFunctionBody,
         )?;
-        let body = self.block()?;
+        let body = self.rule("block", Self::block)?;
 
-        Ok(Stmt::Function(StmtFunction::new(name, parameters, body)))
+        Ok(Stmt::Function(StmtFunction::new(
+            name, parameters, body, false,
+        )))
     }
 
     fn expression(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        self.assignment()
+        self.nested(|parser| parser.rule("assignment", Self::assignment))
     }
 
     fn call(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.primary()?;
+        let mut expr = self.rule("primary", Self::primary)?;
 
         loop {
             if self.tokenstream.match_l(&[TokenType::LeftParen])? {
@@ -403,33 +700,72 @@ impl<'a> Parser<'a> {
                     &TokenType::Ident,
                     ParserErrorContext::ExpectedPropertyNameAfterDot,
                 )?;
-                expr = Expr::Get(ExprGet::new(Box::new(expr), name));
+                expr = Expr::Get(ExprGet::new(self.arena.expr(expr), name));
+            } else if self.tokenstream.match_l(&[TokenType::LeftBracket])? {
+                let index = self.rule("expression", Self::expression)?;
+                let bracket = self.tokenstream.consume(
+                    &TokenType::RightBracket,
+                    ParserErrorContext::ExpectedRightBracketAfterIndex,
+                )?;
+                expr = Expr::Index(ExprIndex::new(
+                    self.arena.expr(expr),
+                    bracket,
+                    self.arena.expr(index),
+                ));
             } else {
                 break;
             }
         }
 
+        if self
+            .tokenstream
+            .match_l(&[TokenType::PlusPlus, TokenType::MinusMinus])?
+        {
+            expr = self.update(self.tokenstream.previous()?, expr, false)?;
+        }
+
         Ok(expr)
     }
 
+    /// `++` or `--` applied to `target`, which must be a variable or a property.
+    fn update(
+        &self,
+        operator: Token<'a>,
+        target: Expr<'a>,
+        prefix: bool,
+    ) -> Result<Expr<'a>, ParserError<'a>> {
+        match target {
+            Expr::Variable(_) | Expr::Get(_) => Ok(Expr::Update(ExprUpdate::new(
+                operator,
+                self.arena.expr(target),
+                prefix,
+            ))),
+            _ => Err(ParserError::InvalidAssignmentTarget { token: operator }),
+        }
+    }
+
     fn finish_call(&mut self, callee: Expr<'a>) -> Result<Expr<'a>, ParserError<'a>> {
         let mut arguments = vec![];
 
         if !self.tokenstream.check(&TokenType::RightParen)? {
             if arguments.len() >= 255 {
-                let token = self.tokenstream.peek()?;
-                let err = ParserError::TooManyFunctionArguments { token: *token };
-                eprintln!("{}", err);
+                let token = *self.tokenstream.peek()?;
+                self.errors
+                    .push(ParserError::TooManyFunctionArguments { token });
             }
-            arguments.push(self.expression()?);
+            arguments.push(self.rule("expression", Self::expression)?);
 
-            while self.tokenstream.match_l(&[TokenType::Comma])? {
+            while self.tokenstream.match_expected(&[TokenType::Comma])? {
+                // Allow a trailing comma
+                if self.tokenstream.check(&TokenType::RightParen)? {
+                    break;
+                }
                 if arguments.len() >= 255 {
-                    let token = self.tokenstream.peek()?;
-                    let err = ParserError::TooManyFunctionArguments { token: *token };
-                    eprintln!("{}", err);
+                    let token = *self.tokenstream.peek()?;
+                    self.errors
+                        .push(ParserError::TooManyFunctionArguments { token });
                 }
-                arguments.push(self.expression()?);
+                arguments.push(self.rule("expression", Self::expression)?);
             }
         }
 
@@ -439,78 +775,146 @@ impl<'a> Parser<'a> {
         )?;
 
         Ok(Expr::Call(ExprCall::new(
-            Box::new(callee),
+            self.arena.expr(callee),
             paren,
             arguments,
         )))
     }
 
+    /// Assignment is right-associative. The targets of a chain like `a = b = c` are collected
+    /// first and the assignments built from the right, so long chains don't recurse.
+    /// `x += 1` is desugared to `x = x + 1`, compound assignments of fields and indexes are
+    /// `Expr::Compound` so their object and index are evaluated once.
     fn assignment(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let expr = self.or()?;
-
-        let operators = [TokenType::Equal];
-
-        if self.tokenstream.match_l(&operators)? {
-            let equals = self.tokenstream.previous()?;
-            let value = self.assignment()?;
-
-            if let Expr::Variable(var) = &expr {
-                let name = var.name;
-                return Ok(Expr::Assign(ExprAssign::new(name, Box::new(value))));
-            } else if let Expr::Get(get) = expr {
-                return Ok(Expr::Set(ExprSet::new(
-                    get.object,
-                    get.name,
-                    Box::new(value),
-                )));
-            }
+        let mut targets = vec![];
+        let mut expr = self.rule("pipe", Self::pipe)?;
+
+        let operators = [
+            TokenType::Equal,
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+            TokenType::PercentEqual,
+        ];
+        while self.tokenstream.match_l(&operators)? {
+            targets.push((expr, self.tokenstream.previous()?));
+            expr = self.rule("pipe", Self::pipe)?;
+        }
+        self.check_chain(targets.len())?;
+
+        for (target, equals) in targets.into_iter().rev() {
+            let target = self.arena.expr(target);
+            let value = self.arena.expr(expr);
+            expr = match (target, compound_operator(equals)) {
+                (Expr::Get(_) | Expr::Index(_), Some(operator)) => {
+                    Expr::Compound(ExprCompound::new(target, equals, operator, value))
+                }
+                (Expr::Variable(_) | Expr::This(_), Some(operator)) => {
+                    let value = Expr::Binary(ExprBinary::new(target, operator, value));
+                    self.assign(target, equals, self.arena.expr(value))?
+                }
+                (target, None) => self.assign(target, equals, value)?,
+                _ => return Err(ParserError::InvalidAssignmentTarget { token: equals }),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// `target = value`
+    fn assign(
+        &self,
+        target: &'a Expr<'a>,
+        equals: Token<'a>,
+        value: &'a Expr<'a>,
+    ) -> Result<Expr<'a>, ParserError<'a>> {
+        Ok(match target {
+            Expr::Variable(var) => Expr::Assign(ExprAssign::new(var.name, equals, value)),
+            // Rejected by the resolver
+            Expr::This(this) => Expr::Assign(ExprAssign::new(this.keyword, equals, value)),
+            Expr::Get(get) => Expr::Set(ExprSet::new(get.object, get.name, equals, value)),
+            Expr::Index(index) => Expr::SetIndex(ExprSetIndex::new(
+                index.object,
+                index.bracket,
+                index.index,
+                equals,
+                value,
+            )),
+            _ => return Err(ParserError::InvalidAssignmentTarget { token: equals }),
+        })
+    }
+
+    /// `x |> f` is desugared to `f(x)` and `x |> g(1)` to `g(x, 1)`.
+    fn pipe(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
+        let mut expr = self.rule("or", Self::or)?;
 
-            return Err(ParserError::InvalidAssignmentTarget { token: equals });
+        while self.tokenstream.match_l(&[TokenType::Pipe])? {
+            let operator = self.tokenstream.previous()?;
+            expr = match self.rule("or", Self::or)? {
+                Expr::Call(mut call) => {
+                    call.arguments.insert(0, expr);
+                    Expr::Call(call)
+                }
+                callee => Expr::Call(ExprCall::new(self.arena.expr(callee), operator, vec![expr])),
+            };
         }
 
         Ok(expr)
     }
 
     fn or(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.and()?;
+        let mut expr = self.rule("and", Self::and)?;
 
         while self.tokenstream.match_l(&[TokenType::Or])? {
             let operator = self.tokenstream.previous()?;
-            let right = self.and()?;
-            expr = Expr::Logical(ExprLogical::new(Box::new(expr), operator, Box::new(right)));
+            let right = self.rule("and", Self::and)?;
+            expr = Expr::Logical(ExprLogical::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ));
         }
 
         Ok(expr)
     }
 
     fn and(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.equality()?;
+        let mut expr = self.rule("equality", Self::equality)?;
 
         while self.tokenstream.match_l(&[TokenType::And])? {
             let operator = self.tokenstream.previous()?;
-            let right = self.equality()?;
-            expr = Expr::Logical(ExprLogical::new(Box::new(expr), operator, Box::new(right)));
+            let right = self.rule("equality", Self::equality)?;
+            expr = Expr::Logical(ExprLogical::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ));
         }
 
         Ok(expr)
     }
 
     fn equality(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.comparison()?;
+        let mut expr = self.rule("comparison", Self::comparison)?;
 
         let operators = [TokenType::BangEqual, TokenType::EqualEqual];
 
         while self.tokenstream.match_l(&operators)? {
             let operator = self.tokenstream.previous()?;
-            let right = self.comparison()?;
-            expr = Expr::Binary(ExprBinary::new(Box::new(expr), operator, Box::new(right)));
+            let right = self.rule("comparison", Self::comparison)?;
+            expr = Expr::Binary(ExprBinary::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ));
         }
 
         Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.term()?;
+        let mut expr = self.rule("term", Self::term)?;
 
         let operators = [
             TokenType::Greater,
@@ -521,51 +925,85 @@ impl<'a> Parser<'a> {
 
         while self.tokenstream.match_l(&operators)? {
             let operator = self.tokenstream.previous()?;
-            let right = self.term()?;
-            expr = Expr::Binary(ExprBinary::new(Box::new(expr), operator, Box::new(right)));
+            let right = self.rule("term", Self::term)?;
+            expr = Expr::Binary(ExprBinary::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ));
         }
 
         Ok(expr)
     }
 
     fn term(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.factor()?;
+        let mut expr = self.rule("factor", Self::factor)?;
 
         let operators = [TokenType::Minus, TokenType::Plus];
 
         while self.tokenstream.match_l(&operators)? {
             let operator = self.tokenstream.previous()?;
-            let right = self.factor()?;
-            expr = Expr::Binary(ExprBinary::new(Box::new(expr), operator, Box::new(right)))
+            let right = self.rule("factor", Self::factor)?;
+            expr = Expr::Binary(ExprBinary::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ))
         }
 
         Ok(expr)
     }
 
     fn factor(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let mut expr = self.unary()?;
+        let mut expr = self.rule("unary", Self::unary)?;
 
-        let operators = [TokenType::Slash, TokenType::Star];
+        let operators = [TokenType::Slash, TokenType::Star, TokenType::Percent];
 
         while self.tokenstream.match_l(&operators)? {
             let operator = self.tokenstream.previous()?;
-            let right = self.unary()?;
-            expr = Expr::Binary(ExprBinary::new(Box::new(expr), operator, Box::new(right)))
+            let right = self.nested(|parser| parser.rule("unary", Self::unary))?;
+            expr = Expr::Binary(ExprBinary::new(
+                self.arena.expr(expr),
+                operator,
+                self.arena.expr(right),
+            ))
         }
 
         Ok(expr)
     }
 
+    /// Like assignment, a chain of prefix operators is collected first and applied from the
+    /// inside out instead of recursing once per operator.
     fn unary(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
-        let operators = [TokenType::Bang, TokenType::Minus];
-
-        if self.tokenstream.match_l(&operators)? {
-            let operator = self.tokenstream.previous()?;
-            let right = self.unary()?;
-            return Ok(Expr::Unary(ExprUnary::new(operator, Box::new(right))));
+        let mut operators = vec![];
+        while self.tokenstream.match_l(&[
+            TokenType::Bang,
+            TokenType::Minus,
+            TokenType::PlusPlus,
+            TokenType::MinusMinus,
+        ])? {
+            operators.push(self.tokenstream.previous()?);
+        }
+        self.check_chain(operators.len())?;
+
+        let mut expr = self.rule("call", Self::call)?;
+        for operator in operators.into_iter().rev() {
+            expr = match (operator.kind, expr) {
+                // Fold `-5` into a literal right away, so negative numbers aren't an operation
+                (
+                    TokenType::Minus,
+                    Expr::Literal(ExprLiteral {
+                        value: LiteralValue::F64(n),
+                    }),
+                ) => Expr::Literal(ExprLiteral::new(LiteralValue::F64(-n))),
+                (TokenType::PlusPlus | TokenType::MinusMinus, target) => {
+                    self.update(operator, target, true)?
+                }
+                (_, right) => Expr::Unary(ExprUnary::new(operator, self.arena.expr(right))),
+            };
         }
 
-        self.call()
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Expr<'a>, ParserError<'a>> {
@@ -578,13 +1016,16 @@ impl<'a> Parser<'a> {
             TokenType::String => Ok(Expr::Literal(ExprLiteral::new(LiteralValue::String(
                 self.tokenstream.previous()?.lexeme.to_string(),
             )))),
+            TokenType::Interpolation => {
+                self.rule("interpolation", |parser| parser.interpolation(token))
+            }
             TokenType::LeftParen => {
-                let expr = self.expression()?;
+                let expr = self.rule("expression", Self::expression)?;
                 self.tokenstream.consume(
                     &TokenType::RightParen,
                     ParserErrorContext::UnmatchedParanthesis,
                 )?;
-                return Ok(Expr::Grouping(ExprGrouping::new(Box::new(expr))));
+                Ok(Expr::Grouping(ExprGrouping::new(self.arena.expr(expr))))
             }
             TokenType::This => Ok(Expr::This(ExprThis::new(token))),
             TokenType::Super => {
@@ -598,45 +1039,209 @@ impl<'a> Parser<'a> {
                 Ok(Expr::Super(ExprSuper::new(keyword, method)))
             }
             TokenType::Ident => Ok(Expr::Variable(ExprVariable::new(token))),
+            TokenType::LeftBracket => self.rule("list", |parser| parser.list(token)),
+            TokenType::LeftBrace => self.rule("map", |parser| parser.map(token)),
             _ => Err(ParserError::UnexpectedToken { token }),
         }
     }
 
-    fn synchronize(&mut self) -> Result<(), ParserError<'a>> {
-        self.tokenstream.advance()?;
+    /// The rest of an interpolated string, `start` is its first segment that was just consumed.
+    /// The scanner already matched the `${` and `}`, a segment follows every expression.
+    fn interpolation(&mut self, start: Token<'a>) -> Result<Expr<'a>, ParserError<'a>> {
+        let mut parts = vec![];
+        let mut segment = start;
+        loop {
+            if !segment.lexeme.is_empty() {
+                parts.push(Expr::Literal(ExprLiteral::new(LiteralValue::String(
+                    segment.lexeme.to_string(),
+                ))));
+            }
+            if segment.kind == TokenType::InterpolationEnd {
+                break;
+            }
+            parts.push(self.rule("expression", Self::expression)?);
+            segment = match self.tokenstream.match_l(&[TokenType::Interpolation])? {
+                true => self.tokenstream.previous()?,
+                false => self.tokenstream.consume(
+                    &TokenType::InterpolationEnd,
+                    ParserErrorContext::ExpectedRightBraceAfterInterpolation,
+                )?,
+            };
+        }
+        Ok(Expr::Interpolation(ExprInterpolation::new(start, parts)))
+    }
+
+    /// The elements of a `[1, 2, 3]` literal, `bracket` is the `[` that was just consumed.
+    fn list(&mut self, bracket: Token<'a>) -> Result<Expr<'a>, ParserError<'a>> {
+        let mut elements = vec![];
+        while !self.tokenstream.check(&TokenType::RightBracket)? {
+            elements.push(self.rule("expression", Self::expression)?);
+            if !self.tokenstream.match_l(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        self.tokenstream.consume(
+            &TokenType::RightBracket,
+            ParserErrorContext::ExpectedRightBracketAfterElements,
+        )?;
+        Ok(Expr::List(ExprList::new(bracket, elements)))
+    }
+
+    /// The entries of a `{"key": value}` literal, `brace` is the `{` that was just consumed. A `{`
+    /// starting a statement is a block, so maps only appear inside expressions.
+    fn map(&mut self, brace: Token<'a>) -> Result<Expr<'a>, ParserError<'a>> {
+        let (mut keys, mut values) = (vec![], vec![]);
+        while !self.tokenstream.check(&TokenType::RightBrace)? {
+            keys.push(self.rule("expression", Self::expression)?);
+            self.tokenstream
+                .consume(&TokenType::Colon, ParserErrorContext::ExpectedColonAfterKey)?;
+            values.push(self.rule("expression", Self::expression)?);
+            if !self.tokenstream.match_l(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        self.tokenstream.consume(
+            &TokenType::RightBrace,
+            ParserErrorContext::ExpectedRightBraceAfterEntries,
+        )?;
+        Ok(Expr::Map(ExprMap::new(brace, keys, values)))
+    }
+
+    /// Skips tokens until the start of the next statement. Stops in front of a `}` so the
+    /// enclosing block or class body can still be closed.
+    /// Consumes the name of a declaration. A keyword is taken as well, the resolver rejects it with
+    /// a better message than a parse error could give.
+    fn name(&mut self, context: ParserErrorContext) -> Result<Token<'a>, ParserError<'a>> {
+        if self.tokenstream.peek()?.kind.is_keyword() {
+            return Ok(self.tokenstream.advance()?);
+        }
+        self.tokenstream.consume(&TokenType::Ident, context)
+    }
+
+    /// Consumes the `;` that ends a statement. When it is missing but the next line starts a new
+    /// statement, the error is recorded and parsing goes on as if the `;` was there, so one
+    /// forgotten `;` doesn't also break the statement after it.
+    fn semicolon(&mut self, context: ParserErrorContext) -> Result<(), ParserError<'a>> {
+        let error = match self.tokenstream.consume(&TokenType::Semicolon, context) {
+            Ok(_) => return Ok(()),
+            Err(error) => error,
+        };
+
+        let previous = self.tokenstream.previous()?;
+        let next = self.tokenstream.peek()?;
+        if next.line > previous.line && starts_statement(&next.kind) {
+            self.record(error);
+            return Ok(());
+        }
+        Err(error)
+    }
 
+    fn synchronize(&mut self) -> Result<(), ParserError<'a>> {
         while !self.tokenstream.is_at_end() {
-            if self.tokenstream.previous()?.kind == TokenType::Semicolon {
+            if matches!(self.tokenstream.previous(), Ok(token) if token.kind == TokenType::Semicolon)
+            {
                 return Ok(());
             }
 
             match self.tokenstream.peek()?.kind {
-                TokenType::Class => (),
-                TokenType::Fun => (),
-                TokenType::Var => (),
-                TokenType::For => (),
-                TokenType::If => (),
-                TokenType::While => (),
-                TokenType::Print => (),
-                TokenType::Return => (),
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::RightBrace => return Ok(()),
                 _ => {
                     self.tokenstream.advance()?;
-                    return Ok(());
                 }
             };
         }
         Ok(())
     }
+
+    /// Skips the rest of a broken method, including its body, so parsing can continue with the
+    /// next method of the class.
+    fn synchronize_method(&mut self) -> Result<(), ParserError<'a>> {
+        let mut depth = 0;
+        while !self.tokenstream.is_at_end() {
+            match self.tokenstream.peek()?.kind {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return Ok(()),
+                TokenType::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.tokenstream.advance()?;
+                        return Ok(());
+                    }
+                }
+                _ => (),
+            }
+            self.tokenstream.advance()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a token of this kind can be the first one of a statement, leaving out the ones that
+/// could also continue the previous expression, like `(` or `-`.
+fn starts_statement(kind: &TokenType) -> bool {
+    matches!(
+        kind,
+        TokenType::Class
+            | TokenType::Fun
+            | TokenType::Var
+            | TokenType::For
+            | TokenType::If
+            | TokenType::While
+            | TokenType::Print
+            | TokenType::Return
+            | TokenType::Break
+            | TokenType::Continue
+            | TokenType::LeftBrace
+            | TokenType::Ident
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::Number(_)
+            | TokenType::String
+            | TokenType::Interpolation
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+    )
+}
+
+/// The binary operator of a compound assignment like `+=`, `None` for `=`. It points at the
+/// first character of `equals`, so errors of the operation show that.
+fn compound_operator(equals: Token<'_>) -> Option<Token<'_>> {
+    let kind = match equals.kind {
+        TokenType::PlusEqual => TokenType::Plus,
+        TokenType::MinusEqual => TokenType::Minus,
+        TokenType::StarEqual => TokenType::Star,
+        TokenType::SlashEqual => TokenType::Slash,
+        TokenType::PercentEqual => TokenType::Percent,
+        _ => return None,
+    };
+    let span = Span {
+        begin: equals.span.begin,
+        end: equals.span.begin + 1,
+    };
+    Some(Token::new(kind, &equals.lexeme[..1], equals.line, span))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::lex::{Scanner, Span};
+    use crate::{ast::printer::AstPrinter, lex::Scanner};
 
     use super::*;
-    fn setup(input: &str) -> Parser {
+    fn setup(input: &str) -> Parser<'_> {
         let mut lexer = Scanner::new(input);
-        Parser::new(TokenStream::new(lexer.scan_tokens().unwrap()))
+        // Leaked so the parsed trees can be returned from helpers
+        let arena = Box::leak(Box::default());
+        Parser::new(TokenStream::new(lexer.scan_tokens().unwrap()), arena)
     }
 
     #[test]
@@ -662,23 +1267,492 @@ mod test {
 
             let right = Expr::Literal(ExprLiteral::new(LiteralValue::F64(3.0)));
             let stmt = vec![Stmt::Expression(StmtExpression::new(Expr::Binary(
-                ExprBinary::new(Box::new(left), operator, Box::new(right)),
+                ExprBinary::new(&left, operator, &right),
             )))];
 
             assert_eq!(stmt, stmts);
         }
     }
 
+    fn parse_expr(input: &str) -> Expr<'_> {
+        let mut stmts = setup(input).parse().unwrap();
+        let Stmt::Expression(stmt) = stmts.remove(0) else {
+            panic!("not an expression statement");
+        };
+        stmt.expr
+    }
+
+    fn number(n: f64) -> Expr<'static> {
+        Expr::Literal(ExprLiteral::new(LiteralValue::F64(n)))
+    }
+
+    #[test]
+    fn negative_literal_folding() {
+        assert_eq!(parse_expr("-5;"), number(-5.0));
+        assert_eq!(parse_expr("- -5;"), number(5.0));
+        assert_eq!(parse_expr("-0;"), number(-0.0));
+
+        let Expr::Binary(binary) = parse_expr("-2 * 3;") else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(*binary.left, number(-2.0));
+        assert_eq!(*binary.right, number(3.0));
+    }
+
+    #[test]
+    fn limits() {
+        let parse = |input, limits| {
+            let mut parser = setup(input);
+            parser.set_limits(limits);
+            parser.parse()
+        };
+        let depth = |max_depth| Limits {
+            max_depth,
+            ..Default::default()
+        };
+
+        assert!(parse("print ((1));", depth(4)).is_ok());
+        for input in [
+            "print (((((1)))));",
+            "print - - - - -1;",
+            "a = b = c = d = e = 1;",
+            "{{{{{ print 1; }}}}}",
+            "if (a) if (a) if (a) if (a) if (a) print 1;",
+            "fun a() { fun b() { fun c() { fun d() { fun e() {} } } } }",
+        ] {
+            // Only the first error is reported, not one per level
+            let errors = parse(input, depth(4)).unwrap_err();
+            assert_eq!(errors.len(), 1, "{input}");
+            assert!(
+                matches!(errors[0], ParserError::TooDeep { max: 4, .. }),
+                "{input}"
+            );
+        }
+
+        let statements = Limits {
+            max_statements: Some(2),
+            ..Default::default()
+        };
+        assert!(parse("print 1; print 2;", statements).is_ok());
+        let errors = parse("print 1; { print 2; }", statements).unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ParserError::TooManyStatements { max: 2, .. }]
+        ));
+
+        let errors = |max_errors| Limits {
+            max_errors,
+            ..Default::default()
+        };
+        let input = "print ; print ; print (1; var 2;";
+        assert_eq!(parse(input, errors(None)).unwrap_err().len(), 4);
+        let first = parse(input, errors(Some(2))).unwrap_err();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[1].code(), "E0118");
+        // An error inside a parameter list doesn't stop the statement, but is still counted
+        let parameters = (0..256).map(|i| format!("p{i}")).collect::<Vec<_>>();
+        let input = format!("fun f({}) {{}} print ;", parameters.join(", "));
+        assert_eq!(parse(&input, errors(Some(1))).unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn long_right_associative_chains() {
+        // Chains don't recurse in the parser, so they only need a high enough limit and no extra
+        // stack
+        fn parse_chain(input: &str) -> Expr<'_> {
+            let mut parser = setup(input);
+            parser.set_limits(Limits {
+                max_depth: 2000,
+                ..Default::default()
+            });
+            let mut stmts = parser.parse().unwrap();
+            let Stmt::Expression(stmt) = stmts.remove(0) else {
+                panic!("not an expression statement");
+            };
+            stmt.expr
+        }
+
+        let chain = format!("{}x;", "a = ".repeat(1000));
+        let Expr::Assign(assign) = parse_chain(&chain) else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(assign.name.lexeme, "a");
+        assert!(matches!(*assign.value, Expr::Assign(_)));
+
+        let chain = format!("{}x;", "!".repeat(1000));
+        let Expr::Unary(unary) = parse_chain(&chain) else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(unary.operator.kind, TokenType::Bang);
+
+        assert!(matches!(
+            setup("a = 1 = b;").parse().unwrap_err()[..],
+            [ParserError::InvalidAssignmentTarget { .. }]
+        ));
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let Expr::Call(call) = parse_expr("f(1, 2,);") else {
+            panic!("expected a call");
+        };
+        assert_eq!(call.arguments, vec![number(1.0), number(2.0)]);
+
+        let stmts = setup("fun f(a, b,) {}").parse().unwrap();
+        let Stmt::Function(function) = &stmts[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(function.params.len(), 2);
+
+        // A comma alone is not an empty list
+        assert!(setup("f(,);").parse().is_err());
+        assert!(setup("fun f(,) {}").parse().is_err());
+    }
+
+    #[test]
+    fn pipe_desugaring() {
+        // `x |> f |> g(1)` is `g(f(x), 1)`
+        let Expr::Call(outer) = parse_expr("x |> f |> g(1);") else {
+            panic!("expected a call");
+        };
+        assert!(matches!(outer.callee, Expr::Variable(g) if g.name.lexeme == "g"));
+        assert_eq!(outer.arguments.len(), 2);
+        assert_eq!(outer.arguments[1], number(1.0));
+
+        let Expr::Call(inner) = &outer.arguments[0] else {
+            panic!("expected a nested call");
+        };
+        assert!(matches!(inner.callee, Expr::Variable(f) if f.name.lexeme == "f"));
+        assert!(matches!(&inner.arguments[..], [Expr::Variable(x)] if x.name.lexeme == "x"));
+
+        // Binds looser than `or`
+        let Expr::Call(call) = parse_expr("a or b |> f;") else {
+            panic!("expected a call");
+        };
+        assert!(matches!(call.arguments[0], Expr::Logical(_)));
+    }
+
+    #[test]
+    fn updates() {
+        let print = |input| AstPrinter.print(&setup(input).parse().unwrap());
+        assert_eq!(print("++i;"), "(; (++ i))");
+        assert_eq!(print("o.f--;"), "(; (post-- (. o f)))");
+        // Postfix binds tighter than prefix operators, both tighter than binary ones
+        assert_eq!(print("-i++;"), "(; (- (post++ i)))");
+        assert_eq!(print("a - --b;"), "(; (- a (-- b)))");
+        assert_eq!(print("a.b.c++ + 1;"), "(; (+ (post++ (. (. a b) c)) 1))");
+
+        for input in ["++1;", "(i)++;", "++i++;", "--f();"] {
+            let errors = setup(input).parse().unwrap_err();
+            assert!(
+                matches!(errors[..], [ParserError::InvalidAssignmentTarget { .. }]),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        let print = |input| AstPrinter.print(&setup(input).parse().unwrap());
+        assert_eq!(print("\"a ${b} c\";"), "(; (interpolate a  b  c))");
+        // Empty segments are left out
+        assert_eq!(print("\"${a}${b + 1}\";"), "(; (interpolate a (+ b 1)))");
+        assert_eq!(
+            print("\"${\"${a}!\"}\" + c;"),
+            "(; (+ (interpolate (interpolate a !)) c))"
+        );
+
+        let errors = setup("\"${a b}\";").parse().unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ParserError::ExpectedRightBraceAfterInterpolation { token }] if token.lexeme == "a"
+        ));
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let print = |input| AstPrinter.print(&setup(input).parse().unwrap());
+        assert_eq!(print("a += 1;"), "(; (= a (+ a 1)))");
+        assert_eq!(print("o.f *= 2;"), "(; (*= (. o f) 2))");
+        assert_eq!(print("l[0] %= 3;"), "(; (%= ([] l 0) 3))");
+        // The whole right side is the operand, `a = a - (1 + 2)`
+        assert_eq!(print("a -= 1 + 2;"), "(; (= a (- a (+ 1 2))))");
+        // Right-associative like `=`
+        assert_eq!(print("a /= b += 2;"), "(; (= a (/ a (= b (+ b 2)))))");
+
+        let errors = setup("a + b += 1;").parse().unwrap_err();
+        assert!(matches!(
+            errors[..],
+            [ParserError::InvalidAssignmentTarget { token }] if token.kind == TokenType::PlusEqual
+        ));
+    }
+
+    #[test]
+    fn unary_precedence() {
+        // Only bare literals are folded, grouping keeps the operation
+        let Expr::Unary(unary) = parse_expr("-(5);") else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(unary.operator.kind, TokenType::Minus);
+        assert!(matches!(*unary.value, Expr::Grouping(_)));
+
+        let Expr::Unary(outer) = parse_expr("- -x;") else {
+            panic!("expected a unary expression");
+        };
+        let Expr::Unary(inner) = outer.value else {
+            panic!("expected a nested unary expression");
+        };
+        assert!(matches!(*inner.value, Expr::Variable(_)));
+
+        // Unary binds looser than calls and property access
+        let Expr::Unary(unary) = parse_expr("-a.b();") else {
+            panic!("expected a unary expression");
+        };
+        assert!(matches!(*unary.value, Expr::Call(_)));
+
+        let Expr::Unary(unary) = parse_expr("!-x;") else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(unary.operator.kind, TokenType::Bang);
+        assert!(matches!(*unary.value, Expr::Unary(_)));
+    }
+
+    #[test]
+    fn peek_nth() {
+        let tokens = Scanner::new("a b").scan_tokens().unwrap();
+        let ts = TokenStream::new(tokens);
+        assert_eq!(ts.peek_nth(0).lexeme, "a");
+        assert_eq!(ts.peek_nth(1).lexeme, "b");
+        assert_eq!(ts.peek_nth(2).kind, TokenType::EOF);
+        assert_eq!(ts.peek_nth(10).kind, TokenType::EOF);
+    }
+
     #[test]
     fn rd_error() {
         let input = "(1 + 1";
         let mut parser = setup(input);
 
-        if let Err(expr) = parser.parse() {
-            let span = Span { begin: 5, end: 6 };
-            let right = Token::new(TokenType::Number(1.0), "1", 1, span);
+        if let Err(errors) = parser.parse() {
+            let span = Span { begin: 6, end: 6 };
+            let eof = Token::new(TokenType::EOF, "", 1, span);
 
-            assert_eq!(expr, ParserError::UnmatchedParanthesis { token: right });
+            assert_eq!(
+                errors,
+                vec![ParserError::UnmatchedParanthesis { token: eof }]
+            );
+            assert_eq!(
+                errors[0].to_string(),
+                "[line 1] Error at end of file: Expected ')' after expression."
+            );
         }
     }
+
+    #[test]
+    fn expected_one_of() {
+        let first_error = |input| setup(input).parse().unwrap_err()[0].to_string();
+
+        assert_eq!(
+            first_error("f(a b);"),
+            "[line 1] Error: Expected ',' or ')', found 'b'."
+        );
+        assert_eq!(
+            first_error("var a 1;"),
+            "[line 1] Error: Expected '=' or ';', found '1'."
+        );
+        assert_eq!(
+            first_error("fun f(a"),
+            "[line 1] Error at end of file: Expected ',' or ')'."
+        );
+        // A single expected kind keeps its dedicated error
+        assert_eq!(
+            first_error("print 1"),
+            "[line 1] Error at end of file: Expected semicolon."
+        );
+    }
+
+    #[test]
+    fn missing_semicolon_recovery() {
+        let input = "print 1
+var a = 2
+a = 3
+print a;";
+        let mut parser = setup(input);
+        let errors = parser.parse().unwrap_err();
+        assert_eq!(error_lines(input), vec![1, 2, 3]);
+        assert!(matches!(errors[0], ParserError::ExpectedSemicolon { .. }));
+
+        // On the same line the rest of the statement is skipped as before
+        assert_eq!(error_lines("print 1 print 2;\nprint 3"), vec![1, 2]);
+    }
+
+    fn error_lines(input: &str) -> Vec<u32> {
+        let errors = setup(input).parse().unwrap_err();
+        errors
+            .iter()
+            .map(|e| {
+                let message = e.to_string();
+                let line = message.trim_start_matches("[line ").split(']').next();
+                line.unwrap().parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn superclass() {
+        let stmts = setup("class B < A {}").parse().unwrap();
+        let Stmt::Class(class) = &stmts[0] else {
+            panic!("expected a class, got {:?}", stmts[0]);
+        };
+        let Some(Expr::Variable(superclass)) = &class.superclass else {
+            panic!("expected a superclass, got {:?}", class.superclass);
+        };
+        assert_eq!(superclass.name.lexeme, "A");
+
+        let first_error = |input| setup(input).parse().unwrap_err()[0].to_string();
+        assert_eq!(
+            first_error("class B < {}"),
+            "[line 1] Error: Expected superclass name."
+        );
+        assert_eq!(
+            first_error("class B <"),
+            "[line 1] Error at end of file: Expected superclass name."
+        );
+
+        // The body of a class with a broken superclass is still parsed
+        assert_eq!(error_lines("class B < {\n  m( {}\n}"), vec![1, 2]);
+        assert_eq!(error_lines("class B < \"A\" {\n  m() {}\n}"), vec![1]);
+        assert_eq!(error_lines("class B < (A) {}"), vec![1, 1]);
+    }
+
+    #[test]
+    fn class_methods() {
+        let stmts = setup("class A { m() {} class make(x) {} }")
+            .parse()
+            .unwrap();
+        let Stmt::Class(class) = &stmts[0] else {
+            panic!("expected a class, got {:?}", stmts[0]);
+        };
+        assert_eq!(class.methods.len(), 1);
+        assert_eq!(class.class_methods[0].name.lexeme, "make");
+        assert_eq!(class.class_methods[0].params.len(), 1);
+
+        let first_error = |input| setup(input).parse().unwrap_err()[0].to_string();
+        assert_eq!(
+            first_error("class A { class () {} }"),
+            "[line 1] Error: Expected method."
+        );
+    }
+
+    #[test]
+    fn getters() {
+        let stmts = setup("class A { area { return 1; } size() {} }")
+            .parse()
+            .unwrap();
+        let Stmt::Class(class) = &stmts[0] else {
+            panic!("expected a class, got {:?}", stmts[0]);
+        };
+        assert!(class.methods[0].is_getter);
+        assert!(class.methods[0].params.is_empty());
+        assert!(!class.methods[1].is_getter);
+
+        // Only methods can be getters
+        assert!(setup("fun area { return 1; }").parse().is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let Expr::List(list) = parse_expr("[1, [], 3,];") else {
+            panic!("expected a list");
+        };
+        assert_eq!(list.elements.len(), 3);
+        assert_eq!(list.elements[2], number(3.0));
+
+        let Expr::SetIndex(set) = parse_expr("a[0][1] = 2;") else {
+            panic!("expected an index assignment");
+        };
+        assert!(matches!(*set.object, Expr::Index(_)));
+        assert_eq!(*set.index, number(1.0));
+        assert_eq!(*set.value, number(2.0));
+
+        let first_error = |input| setup(input).parse().unwrap_err()[0].to_string();
+        assert_eq!(
+            first_error("[1, 2;"),
+            "[line 1] Error: Expected ']' after list elements."
+        );
+        assert_eq!(
+            first_error("a[0;"),
+            "[line 1] Error: Expected ']' after index."
+        );
+    }
+
+    #[test]
+    fn maps() {
+        // A `{` starting a statement is a block, so the map is the value of an assignment
+        let Expr::Assign(assign) = parse_expr("m = {\"a\": 1, 2: {},};") else {
+            panic!("expected an assignment");
+        };
+        let Expr::Map(map) = assign.value else {
+            panic!("expected a map");
+        };
+        assert_eq!(map.keys.len(), 2);
+        assert_eq!(map.keys[1], number(2.0));
+        assert!(matches!(map.values[1], Expr::Map(_)));
+
+        let first_error = |input| setup(input).parse().unwrap_err()[0].to_string();
+        assert_eq!(
+            first_error("print {1 2};"),
+            "[line 1] Error: Expected ':' after map key."
+        );
+        assert_eq!(
+            first_error("print {1: 2;"),
+            "[line 1] Error: Expected '}' after map entries."
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let mut out = vec![];
+        let mut parser = setup("-x");
+        parser.set_trace(Box::new(&mut out));
+        assert!(parser.parse().is_err());
+        drop(parser);
+
+        let trace = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = trace.lines().collect();
+        assert_eq!(lines[0], "[parse] declaration at '-' (line 1)");
+        assert!(lines.contains(&"[parse]                             primary at 'x' (line 1)"));
+        assert!(lines.contains(&"[parse]     expression_statement failed at the end of the file"));
+        assert_eq!(
+            lines.last(),
+            Some(&"[parse] declaration failed at the end of the file")
+        );
+    }
+
+    #[test]
+    fn recovery_in_blocks() {
+        let input = "{
+  var = 1;
+  print 2;
+  print 3
+}
+print 4 5;
+var ok = 6;";
+
+        assert_eq!(error_lines(input), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn recovery_in_class_bodies() {
+        let input = "class A {
+  first(a b) {
+    print a;
+  }
+  second() {
+    print ;
+  }
+  third() {} 4
+}";
+
+        assert_eq!(error_lines(input), vec![2, 6, 8]);
+    }
 }
@@ -0,0 +1,305 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    ast::{printer::AstPrinter, Stmt},
+    diagnostics::Renderer,
+    error::LoxError,
+    interpreter::{options::Options, value::Value},
+    session::Session,
+};
+
+const HELP: &str = ":env          list all globals, natives included
+:locals       list the globals defined in this session
+:type <expr>  evaluate the expression and print the type of its value
+:ast <code>   print the syntax tree of the code without running it
+:help         print this list
+:quit         leave, like end of input";
+
+/// A line starting with `:`, handled by the REPL itself instead of being run as Lox.
+#[derive(Debug, PartialEq)]
+pub enum Command<'s> {
+    Env,
+    Locals,
+    Type(&'s str),
+    Ast(&'s str),
+    Help,
+    Quit,
+}
+
+impl<'s> Command<'s> {
+    /// `None` if the line is Lox code. An unknown command or a missing argument is an error
+    /// message for the user.
+    pub fn parse(line: &'s str) -> Option<Result<Self, String>> {
+        let line = line.trim().strip_prefix(':')?;
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+
+        let command = match name {
+            "env" => Command::Env,
+            "locals" => Command::Locals,
+            "help" => Command::Help,
+            "quit" | "q" => Command::Quit,
+            "type" | "ast" if argument.is_empty() => {
+                return Some(Err(format!(
+                    ":{name} needs an argument, like :{name} 1 + 2"
+                )))
+            }
+            "type" => Command::Type(argument),
+            "ast" => Command::Ast(argument),
+            _ => {
+                return Some(Err(format!(
+                    "Unknown command :{name}, :help lists the commands"
+                )))
+            }
+        };
+        Some(Ok(command))
+    }
+}
+
+/// An interactive session. Each line is run like a script in the globals of the lines before it,
+/// the values of expression statements are echoed.
+pub struct Repl {
+    session: Session,
+    /// Globals that existed before the first line, the natives and the prelude
+    builtins: HashSet<&'static str>,
+    renderer: Renderer,
+}
+
+impl Repl {
+    pub fn new(renderer: Renderer) -> Self {
+        Self::with_output(Box::new(io::stdout()), renderer)
+    }
+
+    /// `out` receives what the session prints, the REPL's own responses are returned by `handle`.
+    pub fn with_output(out: Box<dyn Write>, renderer: Renderer) -> Self {
+        let session = Session::new(out);
+        let builtins = session
+            .interpreter()
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        Self {
+            session,
+            builtins,
+            renderer,
+        }
+    }
+
+    /// Options for running the lines, echoed values are truncated like printed ones.
+    pub fn set_options(&mut self, options: Options) {
+        self.session.interpreter_mut().set_options(options);
+    }
+
+    /// Runs the prelude, its globals are listed by `:env` but not by `:locals`.
+    pub fn load_prelude(&mut self, prelude: &'static [Stmt<'static>]) -> Result<(), String> {
+        self.execute(prelude)?;
+        let globals = self.session.interpreter().globals().into_iter();
+        self.builtins.extend(globals.map(|(name, _)| name));
+        Ok(())
+    }
+
+    /// Reads lines from `input` until it ends or `:quit`, prompting on `output`.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        loop {
+            write!(output, "> ")?;
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            match self.handle(&line) {
+                Some(response) if response.is_empty() => (),
+                Some(response) => writeln!(output, "{response}")?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Runs a line or command and returns what to show for it, `None` to end the session.
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        if line.trim().is_empty() {
+            return Some(String::new());
+        }
+        let command = match Command::parse(line) {
+            Some(Ok(command)) => command,
+            Some(Err(message)) => return Some(message),
+            None => return Some(self.eval(line).unwrap_or_else(|e| e)),
+        };
+
+        let response = match command {
+            Command::Env => self.list(|_| true),
+            Command::Locals => {
+                let builtins = self.builtins.clone();
+                self.list(|name| !builtins.contains(name))
+            }
+            Command::Type(source) => self.type_of(source).unwrap_or_else(|e| e),
+            Command::Ast(source) => self
+                .parse(source)
+                .map_or_else(|e| e, |stmts| AstPrinter.print(stmts)),
+            Command::Help => HELP.to_string(),
+            Command::Quit => return None,
+        };
+        Some(response)
+    }
+
+    fn list(&self, filter: impl Fn(&str) -> bool) -> String {
+        self.session
+            .interpreter()
+            .globals()
+            .into_iter()
+            .filter(|(name, _)| filter(name))
+            .map(|(name, value)| match value {
+                Some(value) => format!("{name} = {value}"),
+                None => format!("{name} (unassigned)"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn eval(&mut self, source: &str) -> Result<String, String> {
+        let stmts = self.parse(source)?;
+        let values = self.execute(stmts)?;
+        let echoed: Vec<_> = values
+            .iter()
+            .filter(|value| **value != Value::Nil)
+            .map(|value| {
+                self.session
+                    .interpreter()
+                    .options()
+                    .truncate(value.to_string())
+            })
+            .collect();
+        Ok(echoed.join("\n"))
+    }
+
+    fn type_of(&mut self, source: &str) -> Result<String, String> {
+        let stmts = self.parse(source)?;
+        let [Stmt::Expression(_)] = stmts else {
+            return Err(":type expects a single expression".to_string());
+        };
+        let value = self.execute(stmts)?.pop().unwrap_or_default();
+        Ok(value.type_name().to_string())
+    }
+
+    /// Parses a line, a missing `;` at its end is added.
+    fn parse(&mut self, source: &str) -> Result<&'static [Stmt<'static>], String> {
+        let source = source.trim();
+        let source = match source.ends_with([';', '}']) {
+            true => source.to_string(),
+            false => format!("{source};"),
+        };
+        self.session.parse(&source).map_err(|e| self.render(&e))
+    }
+
+    /// Resolves and runs the statements, returns the values of the expression statements.
+    fn execute(&mut self, stmts: &'static [Stmt<'static>]) -> Result<Vec<Value<'static>>, String> {
+        // The warnings are dropped, an unused variable is expected when typing one line at a time
+        self.session.resolve(stmts).map_err(|e| self.render(&e))?;
+
+        let mut values = vec![];
+        for result in self.session.interpreter_mut().interpret_iter(stmts) {
+            match result {
+                Ok(value) => values.extend(value),
+                Err(e) => return Err(self.renderer.error(&e, e.code())),
+            }
+        }
+        Ok(values)
+    }
+
+    fn render(&self, error: &LoxError) -> String {
+        let reports: Vec<_> = error
+            .reports()
+            .iter()
+            .map(|report| self.renderer.error(&report.message, report.code))
+            .collect();
+        reports.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn repl() -> Repl {
+        Repl::with_output(Box::new(io::sink()), Renderer::plain())
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse("print 1;"), None);
+        assert_eq!(Command::parse(" :env "), Some(Ok(Command::Env)));
+        assert_eq!(
+            Command::parse(":type  1 + 2"),
+            Some(Ok(Command::Type("1 + 2")))
+        );
+        assert_eq!(
+            Command::parse(":ast"),
+            Some(Err(":ast needs an argument, like :ast 1 + 2".to_string()))
+        );
+        assert_eq!(
+            Command::parse(":frobnicate"),
+            Some(Err(
+                "Unknown command :frobnicate, :help lists the commands".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn session() {
+        let mut repl = repl();
+        assert_eq!(repl.handle("var a = 1;"), Some(String::new()));
+        assert_eq!(repl.handle("var b;"), Some(String::new()));
+        assert_eq!(repl.handle("a + 1"), Some("2".to_string()));
+        assert_eq!(
+            repl.handle(":locals"),
+            Some("a = 1\nb (unassigned)".to_string())
+        );
+        assert!(repl.handle(":env").unwrap().contains("clock = <native fn>"));
+        assert_eq!(repl.handle(":type a"), Some("number".to_string()));
+        assert_eq!(repl.handle(":type [a]"), Some("list".to_string()));
+        assert_eq!(
+            repl.handle(":type var c = 1;"),
+            Some(":type expects a single expression".to_string())
+        );
+        assert_eq!(
+            repl.handle(":ast print a + 1"),
+            Some("(print (+ a 1))".to_string())
+        );
+        assert_eq!(
+            repl.handle("print c;"),
+            Some("[line 1] Undefined Variable 'c'. [E0304]".to_string())
+        );
+        assert_eq!(repl.handle(":quit"), None);
+    }
+
+    #[test]
+    fn echo_truncates() {
+        let mut repl = repl();
+        repl.set_options(Options {
+            max_print_len: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(
+            repl.handle("\"abcdef\""),
+            Some("abc... (3 more characters)".to_string())
+        );
+        assert_eq!(repl.handle("123"), Some("123".to_string()));
+    }
+
+    #[test]
+    fn functions_outlive_their_line() {
+        let mut repl = repl();
+        repl.handle("fun twice(x) { return x * 2; }");
+        assert_eq!(repl.handle("twice(21)"), Some("42".to_string()));
+        assert_eq!(
+            repl.handle(":locals"),
+            Some("twice = <fn twice>".to_string())
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value as Json};
+
+use crate::{
+    error::LoxError,
+    interpreter::{
+        callable::Arguments,
+        native_fun::json::{value_from_json, value_to_json},
+        value::Value,
+    },
+    session::{Session, SharedBuffer},
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SCRIPT_ERROR: i64 = -32000;
+
+/// A long-lived interpreter session driven by line-delimited JSON-RPC 2.0 requests.
+///
+/// Supported methods:
+/// - `load` with `{"source": "..."}` runs the source in the session
+/// - `call` with `{"function": "name", "args": [...]}` calls a global function
+/// - `reset` throws away all globals defined so far and frees the sources loaded before
+///
+/// Every result (and the `data` of every error) carries the `output` printed while handling the
+/// request. Errors raised by the script also carry its `error_code`.
+pub struct Server {
+    session: Session,
+    output: SharedBuffer,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        let output = SharedBuffer::default();
+        Self {
+            session: Session::new(output.clone()),
+            output,
+        }
+    }
+
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(output, "{}", self.handle(&line))?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn handle(&mut self, request: &str) -> Json {
+        let request: Json = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => return error_response(Json::Null, PARSE_ERROR, e.to_string()),
+        };
+
+        let id = request.get("id").cloned().unwrap_or(Json::Null);
+        let Some(method) = request.get("method").and_then(Json::as_str) else {
+            return error_response(id, INVALID_REQUEST, "missing method".to_string());
+        };
+        let params = request.get("params").cloned().unwrap_or(Json::Null);
+
+        let result = match method {
+            "load" => self.load(&params),
+            "call" => self.call(&params),
+            "reset" => {
+                *self = Self::new();
+                Ok(Json::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method '{method}'"),
+            )),
+        };
+
+        let output = String::from_utf8_lossy(&self.output.take()).into_owned();
+        match result {
+            Ok(value) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": { "value": value, "output": output },
+            }),
+            Err(error) => {
+                let mut response = error_response(id, error.code, error.message);
+                response["error"]["data"] = json!({ "output": output });
+                if let Some(error_code) = error.error_code {
+                    response["error"]["data"]["error_code"] = json!(error_code);
+                }
+                response
+            }
+        }
+    }
+
+    fn load(&mut self, params: &Json) -> Result<Json, RpcError> {
+        let source = params
+            .get("source")
+            .and_then(Json::as_str)
+            .ok_or(RpcError::new(INVALID_PARAMS, "expected a 'source' string"))?;
+
+        self.session.run(source)?;
+        Ok(Json::Null)
+    }
+
+    fn call(&mut self, params: &Json) -> Result<Json, RpcError> {
+        let name = params
+            .get("function")
+            .and_then(Json::as_str)
+            .ok_or(RpcError::new(
+                INVALID_PARAMS,
+                "expected a 'function' string",
+            ))?;
+        let arguments = match params.get("args") {
+            Some(Json::Array(args)) => args
+                .iter()
+                .map(|arg| value_from_json(arg.clone()))
+                .collect::<Result<Arguments, _>>()
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?,
+            None => Arguments::new(),
+            Some(_) => return Err(RpcError::new(INVALID_PARAMS, "'args' must be an array")),
+        };
+
+        let Some(Value::Callable(function)) = self.session.interpreter().get_global(name) else {
+            return Err(RpcError::new(
+                SCRIPT_ERROR,
+                format!("'{name}' is not a global function"),
+            ));
+        };
+        if !function.arity().accepts(arguments.len()) {
+            return Err(RpcError::new(
+                SCRIPT_ERROR,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+
+        let value = function
+            .call(self.session.interpreter_mut(), &arguments)
+            .map_err(|e| RpcError::script(e.to_string(), e.code()))?;
+        value_to_json(&value).map_err(|e| RpcError::script(e.to_string(), e.code()))
+    }
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+    /// Code of the Lox error that failed the request, see `rust_lox --explain`
+    error_code: Option<&'static str>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            error_code: None,
+        }
+    }
+
+    fn script(message: String, error_code: &'static str) -> Self {
+        Self {
+            code: SCRIPT_ERROR,
+            message,
+            error_code: Some(error_code),
+        }
+    }
+}
+
+/// The messages of all errors, the code is the one of the first.
+impl From<LoxError> for RpcError {
+    fn from(error: LoxError) -> Self {
+        let messages: Vec<_> = error
+            .reports()
+            .iter()
+            .map(|report| report.message.as_str())
+            .collect();
+        Self::script(messages.join("\n"), error.code())
+    }
+}
+
+fn error_response(id: Json, code: i64, message: String) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn load_and_call() {
+        let mut server = Server::new();
+
+        let response = server.handle(
+            r#"{"jsonrpc":"2.0","id":1,"method":"load","params":{"source":"fun add(a, b) { print a; return a + b; }"}}"#,
+        );
+        assert_eq!(response["result"]["value"], Json::Null);
+
+        let response = server.handle(
+            r#"{"jsonrpc":"2.0","id":2,"method":"call","params":{"function":"add","args":[1,2]}}"#,
+        );
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["result"]["value"], 3);
+        assert_eq!(response["result"]["output"], "1\n");
+    }
+
+    #[test]
+    fn reset() {
+        let mut server = Server::new();
+
+        server.handle(r#"{"id":1,"method":"load","params":{"source":"var a = 1;"}}"#);
+        server.handle(r#"{"id":2,"method":"reset"}"#);
+        let response = server.handle(r#"{"id":3,"method":"load","params":{"source":"print a;"}}"#);
+
+        assert_eq!(response["error"]["code"], SCRIPT_ERROR);
+        assert_eq!(response["error"]["data"]["error_code"], "E0304");
+    }
+
+    #[test]
+    fn errors() {
+        let mut server = Server::new();
+
+        let response = server.handle("not json");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response = server.handle(r#"{"id":1,"method":"eval"}"#);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = server.handle(r#"{"id":2,"method":"call","params":{"function":"f"}}"#);
+        assert_eq!(response["error"]["code"], SCRIPT_ERROR);
+    }
+}
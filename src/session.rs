@@ -0,0 +1,361 @@
+//! Running Lox from other Rust programs without wiring up the scanner, parser, resolver and
+//! interpreter by hand.
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+#[cfg(feature = "threads")]
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    ast::{Arena, Stmt},
+    error::LoxError,
+    interpreter::{
+        resolver::{Analysis, Resolver},
+        Interpreter,
+    },
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+/// Runs `source` as a script, what it prints goes to `out`. Warnings aren't reported.
+pub fn run_source(source: &str, out: &mut impl Write) -> Result<(), LoxError> {
+    let arena = Arena::new();
+    let stmts = parse(source, &arena)?;
+    let analysis = resolve(&stmts)?;
+    let mut interpreter = Interpreter::with_output(analysis, Box::new(out));
+    let result = interpreter.interpret(&stmts).map_err(LoxError::from);
+    result
+}
+
+fn parse<'a>(source: &'a str, arena: &'a Arena<'a>) -> Result<Vec<Stmt<'a>>, LoxError> {
+    let tokens = Scanner::new(source).scan_tokens()?;
+    Ok(Parser::new(TokenStream::new(tokens), arena).parse()?)
+}
+
+fn resolve<'a>(stmts: &'a [Stmt<'a>]) -> Result<Analysis<'a>, LoxError> {
+    let mut resolver = Resolver::new();
+    resolver.resolve(stmts)?;
+    Ok(resolver.analysis())
+}
+
+/// Runs several sources one after another in the same globals, like the files of a program or the
+/// lines of a REPL. The REPL and `--serve` are built on it.
+///
+/// Functions defined by a source keep pointing into its AST, so the session keeps every source it
+/// parsed, the arena of its AST and its statements until it is dropped. Its memory grows with each
+/// source, drop it (or start a new one) to get it back. Use `run_source` for one-off scripts.
+pub struct Session {
+    // Declared first so it is dropped before the programs it points into
+    interpreter: Interpreter<'static>,
+    programs: Vec<Program>,
+}
+
+impl Session {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self {
+            interpreter: Interpreter::with_output(Analysis::default(), Box::new(out)),
+            programs: vec![],
+        }
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<(), LoxError> {
+        let stmts = self.parse(source)?;
+        self.resolve(stmts)?;
+        Ok(self.interpreter.interpret(stmts)?)
+    }
+
+    /// Parses a source without running it. Its statements stay valid until the session is dropped,
+    /// they must not be used after that.
+    pub(crate) fn parse(&mut self, source: &str) -> Result<&'static [Stmt<'static>], LoxError> {
+        let mut program = Program::new(source);
+        // SAFETY: the source and the arena live in `program` until it is dropped
+        let (source, arena) = unsafe { (&*program.source, &*program.arena) };
+        // A source that doesn't parse is freed right away, nothing points into it
+        let stmts = Box::into_raw(parse(source, arena)?.into_boxed_slice());
+        program.stmts = Some(stmts);
+        self.programs.push(program);
+        // SAFETY: as above, the statements are only freed with the session
+        Ok(unsafe { &*stmts })
+    }
+
+    /// Resolves statements before they run in the session. Warnings aren't reported.
+    pub(crate) fn resolve(&mut self, stmts: &'static [Stmt<'static>]) -> Result<(), LoxError> {
+        self.interpreter.add_analysis(resolve(stmts)?);
+        Ok(())
+    }
+
+    pub(crate) fn interpreter(&self) -> &Interpreter<'static> {
+        &self.interpreter
+    }
+
+    pub(crate) fn interpreter_mut(&mut self) -> &mut Interpreter<'static> {
+        &mut self.interpreter
+    }
+}
+
+/// A source parsed by a session, with the arena of its AST and its statements.
+struct Program {
+    source: *mut str,
+    arena: *mut Arena<'static>,
+    stmts: Option<*mut [Stmt<'static>]>,
+}
+
+impl Program {
+    fn new(source: &str) -> Self {
+        Self {
+            source: Box::into_raw(source.into()),
+            arena: Box::into_raw(Box::default()),
+            stmts: None,
+        }
+    }
+}
+
+impl Drop for Program {
+    /// Frees the statements before the arena and the source they point into.
+    fn drop(&mut self) {
+        // SAFETY: all three came from `Box::into_raw` and are freed once, here
+        unsafe {
+            if let Some(stmts) = self.stmts {
+                drop(Box::from_raw(stmts));
+            }
+            drop(Box::from_raw(self.arena));
+            drop(Box::from_raw(self.source));
+        }
+    }
+}
+
+/// What running a source printed and how it ended.
+#[cfg(feature = "threads")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunResult {
+    pub output: String,
+    pub result: Result<(), LoxError>,
+}
+
+#[cfg(feature = "threads")]
+type Request = (String, Sender<RunResult>);
+
+/// Stack size of the threads scripts run on, the usual size of a main thread. Deep recursion in a
+/// script then overflows at about the depth it does on the command line.
+#[cfg(feature = "threads")]
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Runs each source as a script of its own, like `run_source`, spread over a thread per core.
+/// Scripts don't share globals. The results are in the order of `sources`, a panic while running
+/// one is passed on once the others are done.
+#[cfg(feature = "threads")]
+pub fn run_many(sources: &[impl AsRef<str> + Sync]) -> Vec<RunResult> {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(sources.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; sources.len()]);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(source) = sources.get(index) else {
+                        break;
+                    };
+                    let mut out = vec![];
+                    let result = run_source(source.as_ref(), &mut out);
+                    let output = String::from_utf8_lossy(&out).into_owned();
+                    results.lock().unwrap()[index] = Some(RunResult { output, result });
+                })
+                .expect("failed to spawn a thread");
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every source ran"))
+        .collect()
+}
+
+/// A `Session` on a thread of its own. The values of a session can't leave the thread they were
+/// created on, so this handle only sends sources over and gets back what they printed. It can be
+/// moved to and shared between threads, sources sent from several threads run one at a time.
+#[cfg(feature = "threads")]
+pub struct SessionThread {
+    requests: Option<Sender<Request>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "threads")]
+impl SessionThread {
+    pub fn spawn() -> Self {
+        let (requests, received) = mpsc::channel::<Request>();
+        let thread = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let out = SharedBuffer::default();
+                let mut session = Session::new(out.clone());
+                for (source, reply) in received {
+                    let result = session.run(&source);
+                    let output = String::from_utf8_lossy(&out.take()).into_owned();
+                    // The caller may have stopped waiting
+                    let _ = reply.send(RunResult { output, result });
+                }
+            })
+            .expect("failed to spawn a thread");
+        Self {
+            requests: Some(requests),
+            thread: Some(thread),
+        }
+    }
+
+    /// Runs `source` in the session and waits for it to finish.
+    pub fn run(&self, source: impl Into<String>) -> RunResult {
+        let (reply, result) = mpsc::channel();
+        let requests = self
+            .requests
+            .as_ref()
+            .expect("only dropping takes the sender");
+        requests
+            .send((source.into(), reply))
+            .expect("the session thread panicked");
+        result.recv().expect("the session thread panicked")
+    }
+}
+
+#[cfg(feature = "threads")]
+impl Drop for SessionThread {
+    /// Lets the session finish the sources it was sent and waits for its thread to end.
+    fn drop(&mut self) {
+        self.requests.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Output that stays readable after it was handed to an interpreter.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// What was written since the last call.
+    pub(crate) fn take(&self) -> Vec<u8> {
+        self.0.take()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_once() {
+        let mut out = vec![];
+        run_source("var a = 1;\nprint a + 1;", &mut out).unwrap();
+        assert_eq!(out, b"2\n");
+
+        let error = run_source("print 1;\nprint nope;", &mut out).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 2] Undefined Variable 'nope'. [E0304]"
+        );
+        assert_eq!(error.code(), "E0304");
+        assert_eq!(out, b"2\n1\n");
+
+        let error = run_source("print ;\nprint );", &mut out).unwrap_err();
+        let LoxError::Syntax(reports) = &error else {
+            panic!("expected a syntax error, got {error:?}");
+        };
+        assert_eq!(reports.len(), 2);
+    }
+
+    #[test]
+    fn session_keeps_globals() {
+        let out = SharedBuffer::default();
+        let mut session = Session::new(out.clone());
+        session
+            .run("fun greet(name) { return \"hi \" + name; }")
+            .unwrap();
+        session.run("var who = \"lox\";").unwrap();
+        session.run("print greet(who);").unwrap();
+        assert!(matches!(
+            session.run("{ var a = 1; var a = 2; }"),
+            Err(LoxError::Resolve(_))
+        ));
+        assert_eq!(out.take(), b"hi lox\n");
+    }
+
+    #[test]
+    fn session_keeps_parsed_programs() {
+        let mut session = Session::new(io::sink());
+        session.run("fun f() { return \"kept\"; }").unwrap();
+        assert!(session.run("print (;").is_err());
+        assert!(session.run("{ var a = 1; var a = 2; }").is_err());
+        // Only the source that didn't parse is freed right away
+        assert_eq!(session.programs.len(), 2);
+        session.run("print f();").unwrap();
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn session_thread() {
+        use std::sync::Arc;
+
+        fn shareable<T: Send + Sync>(_: &T) {}
+
+        let session = Arc::new(SessionThread::spawn());
+        shareable(&session);
+        let result = session.run("var n = 20; print n;");
+        assert_eq!(result.output, "20\n");
+        assert_eq!(result.result, Ok(()));
+
+        let other = Arc::clone(&session);
+        let result = thread::spawn(move || other.run("n = n + 1; print n;\nprint nope;"))
+            .join()
+            .unwrap();
+        assert_eq!(result.output, "21\n");
+        assert_eq!(result.result.unwrap_err().code(), "E0304");
+
+        assert_eq!(session.run("print n * 2;").output, "42\n");
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn run_many() {
+        let results = super::run_many(&[
+            "var a = 1; print a;",
+            // Globals of the other scripts aren't visible
+            "print 2; print a;",
+            "print (;",
+        ]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].output, "1\n");
+        assert_eq!(results[0].result, Ok(()));
+        assert_eq!(results[1].output, "2\n");
+        assert_eq!(results[1].result.as_ref().unwrap_err().code(), "E0304");
+        assert_eq!(results[2].output, "");
+        assert_eq!(results[2].result.as_ref().unwrap_err().exit_code(), 65);
+
+        assert_eq!(super::run_many(&[] as &[String]), []);
+    }
+}
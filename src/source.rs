@@ -0,0 +1,99 @@
+//! Reading script files into the source text the scanner works on.
+
+use std::{fs, io, path::Path};
+
+use thiserror::Error;
+
+/// Editors on Windows like to start UTF-8 files with it, it isn't part of the script.
+const BOM: char = '\u{feff}';
+
+#[derive(Error, Debug)]
+pub enum SourceError {
+    #[error("Can't read {path}: {source}")]
+    Io { path: String, source: io::Error },
+
+    /// `line` and `column` are 1-based, the column counts bytes
+    #[error("{path}:{line}:{column}: The file is not valid UTF-8. Pass --lossy to replace invalid bytes with U+FFFD.")]
+    InvalidUtf8 {
+        path: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl SourceError {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SourceError::Io { .. } => "E0006",
+            SourceError::InvalidUtf8 { .. } => "E0007",
+        }
+    }
+}
+
+/// Reads the script at `path` without a leading byte order mark. Invalid UTF-8 is an error, or
+/// replaced with U+FFFD if `lossy` is set.
+pub fn read(path: impl AsRef<Path>, lossy: bool) -> Result<String, SourceError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| SourceError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+    decode(&path.display().to_string(), bytes, lossy)
+}
+
+fn decode(path: &str, bytes: Vec<u8>, lossy: bool) -> Result<String, SourceError> {
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if lossy => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        Err(e) => {
+            let valid = &e.as_bytes()[..e.utf8_error().valid_up_to()];
+            let line_start = valid.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+            return Err(SourceError::InvalidUtf8 {
+                path: path.to_string(),
+                line: valid.iter().filter(|&&b| b == b'\n').count() + 1,
+                column: valid.len() - line_start + 1,
+            });
+        }
+    };
+
+    // Removed in place, a large script isn't copied just to drop its first three bytes
+    let mut text = text;
+    if text.starts_with(BOM) {
+        text.drain(..BOM.len_utf8());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_bom() {
+        let source = decode("a.lox", "\u{feff}print 1;".into(), false).unwrap();
+        assert_eq!(source, "print 1;");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let bytes = b"print 1;\nprint \"\xff\";".to_vec();
+        let error = decode("a.lox", bytes.clone(), false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "a.lox:2:8: The file is not valid UTF-8. Pass --lossy to replace invalid bytes with U+FFFD."
+        );
+        assert_eq!(error.code(), "E0007");
+
+        let source = decode("a.lox", bytes, true).unwrap();
+        assert_eq!(source, "print 1;\nprint \"\u{fffd}\";");
+    }
+
+    #[test]
+    fn missing_file() {
+        let error = read("does/not/exist.lox", false).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Can't read does/not/exist.lox: "));
+    }
+}
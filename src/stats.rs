@@ -0,0 +1,159 @@
+//! Heap usage of the phases of a run, reported by `--stats`.
+//!
+//! The numbers come from `CountingAlloc`, which only counts when the binary registers it as its
+//! `#[global_allocator]`. Without it every phase reports 0 bytes.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, keeping track of the bytes currently allocated and their peak.
+pub struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn allocated(size: usize) {
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+/// Bytes currently allocated.
+pub fn current() -> usize {
+    CURRENT.load(Ordering::Relaxed)
+}
+
+/// Heap usage of one phase. Both numbers are relative to the usage when the phase started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub name: &'static str,
+    /// Still allocated after the phase, like the tokens after scanning
+    pub retained: usize,
+    /// The most allocated at once during the phase
+    pub peak: usize,
+    /// What the phase produced, like `120 tokens`
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub phases: Vec<Phase>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` as the phase `name` and records its heap usage.
+    pub fn measure<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let before = current();
+        PEAK.store(before, Ordering::Relaxed);
+        let result = f();
+        self.phases.push(Phase {
+            name,
+            retained: current().saturating_sub(before),
+            peak: PEAK.load(Ordering::Relaxed).saturating_sub(before),
+            note: None,
+        });
+        result
+    }
+
+    /// Adds `note` to the phase measured last.
+    pub fn note(&mut self, note: String) {
+        if let Some(phase) = self.phases.last_mut() {
+            phase.note = Some(note);
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[stats] {:<8} {:>12} {:>12}",
+            "phase", "retained", "peak"
+        )?;
+        for phase in &self.phases {
+            write!(
+                f,
+                "\n[stats] {:<8} {:>12} {:>12}",
+                phase.name,
+                bytes(phase.retained),
+                bytes(phase.peak)
+            )?;
+            if let Some(note) = &phase.note {
+                write!(f, "  {note}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bytes(n: usize) -> String {
+    match n {
+        n if n < 1024 => format!("{n} B"),
+        n if n < 1024 * 1024 => format!("{:.1} KiB", n as f64 / 1024.0),
+        n => format!("{:.1} MiB", n as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report() {
+        let mut stats = Stats::new();
+        let tokens = stats.measure("scan", || 3);
+        stats.note(format!("{tokens} tokens"));
+        stats.phases.push(Phase {
+            name: "run",
+            retained: 2048,
+            peak: 3 * 1024 * 1024,
+            note: None,
+        });
+
+        let report = stats.to_string();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("[stats] scan"));
+        assert!(lines[1].ends_with("  3 tokens"));
+        assert!(lines[2].contains("2.0 KiB"));
+        assert!(lines[2].ends_with("3.0 MiB"));
+    }
+}
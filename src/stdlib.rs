@@ -0,0 +1,88 @@
+use std::error::Error;
+
+use crate::{
+    ast::{Arena, Stmt},
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+pub const SOURCES: &[(&str, &str)] = &[("core.lox", include_str!("../stdlib/core.lox"))];
+
+/// Parses the prelude. It is run before the script and its functions live as long as the program,
+/// so the arena of its AST and its statements are leaked.
+pub fn load() -> Result<&'static [Stmt<'static>], Box<dyn Error>> {
+    let arena: &'static Arena<'static> = Box::leak(Box::default());
+    let mut stmts = vec![];
+    for (name, source) in SOURCES {
+        let tokens = Scanner::new(source)
+            .scan_tokens()
+            .map_err(|errors| format!("stdlib/{name}: {}", errors[0]))?;
+        let parsed = Parser::new(TokenStream::new(tokens), arena)
+            .parse()
+            .map_err(|errors| format!("stdlib/{name}: {}", errors[0]))?;
+        stmts.extend(parsed);
+    }
+    Ok(Box::leak(stmts.into_boxed_slice()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::{Expr, NodeId},
+        interpreter::{resolver::Resolver, Interpreter},
+    };
+
+    use super::*;
+
+    #[test]
+    fn loads() {
+        let stmts = load().unwrap();
+
+        let mut resolver = Resolver::new();
+        resolver.resolve(stmts).unwrap();
+
+        let mut interpreter = Interpreter::new(resolver.analysis());
+        assert!(interpreter.interpret(stmts).is_ok());
+    }
+
+    /// A script is resolved after the prelude with the same resolver. Its variables keep apart
+    /// from the prelude's even where they sit at the same line and offset.
+    #[test]
+    fn script_doesnt_shadow_prelude() {
+        let prelude = load().unwrap();
+        let (_, core) = SOURCES[0];
+        let offset = core.find("return value;").unwrap() + "return ".len();
+        let line = core[..offset].matches('\n').count() + 1;
+        // A global `value` at the same line and offset as the parameter `identity` returns
+        let declaration = "var value = \"global\";";
+        let script = format!(
+            "{declaration}{}{}value; var result = identity(5);",
+            "\n".repeat(line - 1),
+            " ".repeat(offset - declaration.len() - (line - 1)),
+        );
+        let arena = Arena::new();
+        let tokens = Scanner::new(&script).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let (Stmt::Expression(global), Stmt::Function(identity)) = (&stmts[1], &prelude[0]) else {
+            panic!("expected `value;` and `identity`");
+        };
+        let Stmt::Return(parameter) = &identity.body[0] else {
+            panic!("expected `return value;`");
+        };
+        assert_eq!(Some(&global.expr), parameter.value.as_ref());
+
+        let mut resolver = Resolver::new();
+        resolver.resolve(prelude).unwrap();
+        resolver.resolve(&stmts).unwrap();
+        let (Expr::Variable(global), Some(Expr::Variable(parameter))) =
+            (&global.expr, &parameter.value)
+        else {
+            panic!("expected two variables");
+        };
+        let locals = resolver.analysis().locals;
+        assert_eq!(locals.get(&NodeId::of(global)), None);
+        assert_eq!(locals.get(&NodeId::of(parameter)), Some(&0));
+    }
+}
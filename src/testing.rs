@@ -0,0 +1,148 @@
+use std::io::Write;
+
+use crate::{
+    ast::{Arena, Stmt},
+    doctest::Summary,
+    interpreter::{resolver::Resolver, value::Value, Interpreter},
+    lex::Scanner,
+    parser::{Parser, TokenStream},
+};
+
+const PREFIX: &str = "test_";
+
+/// A top-level `fun test_*()` declaration.
+#[derive(Debug, PartialEq)]
+pub struct Test<'a> {
+    pub name: &'a str,
+    pub line: u32,
+}
+
+pub fn discover<'a>(stmts: &[Stmt<'a>]) -> Vec<Test<'a>> {
+    stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Function(function) if function.name.lexeme.starts_with(PREFIX) => Some(Test {
+                name: function.name.lexeme,
+                line: function.name.line,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs every test function of the script. Each test gets a fresh interpreter that ran the
+/// script first, so tests can't leak state into each other. A test passes when it returns
+/// without a runtime error, e.g. from a failed `assert_eq`.
+pub fn run_all(name: &str, script: &str, out: &mut impl Write) -> std::io::Result<Summary> {
+    let mut summary = Summary::default();
+
+    let arena = Arena::new();
+    let stmts = match load(script, &arena) {
+        Ok(stmts) => stmts,
+        Err(e) => {
+            summary.failed += 1;
+            writeln!(out, "{name}: {e}")?;
+            return Ok(summary);
+        }
+    };
+
+    for test in discover(&stmts) {
+        match run(&stmts, test.name) {
+            Ok(()) => {
+                summary.passed += 1;
+                writeln!(out, "test {name}:{} {} ... ok", test.line, test.name)?;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                writeln!(out, "test {name}:{} {} ... FAILED", test.line, test.name)?;
+                writeln!(out, "  {e}")?;
+            }
+        }
+    }
+
+    writeln!(
+        out,
+        "\ntest result: {} passed; {} failed",
+        summary.passed, summary.failed
+    )?;
+    Ok(summary)
+}
+
+fn load<'a>(script: &'a str, arena: &'a Arena<'a>) -> Result<Vec<Stmt<'a>>, String> {
+    let tokens = Scanner::new(script)
+        .scan_tokens()
+        .map_err(|errors| errors[0].to_string())?;
+    Parser::new(TokenStream::new(tokens), arena)
+        .parse()
+        .map_err(|errors| errors[0].to_string())
+}
+
+fn run(stmts: &[Stmt], test: &str) -> Result<(), String> {
+    let mut resolver = Resolver::new();
+    resolver.resolve(stmts).map_err(|e| e.to_string())?;
+
+    let mut interpreter = Interpreter::with_output(resolver.analysis(), Box::new(std::io::sink()));
+    interpreter.interpret(stmts).map_err(|e| e.to_string())?;
+
+    let Some(Value::Callable(function)) = interpreter.get_global(test) else {
+        return Err(format!("'{test}' is not a function"));
+    };
+    if !function.arity().accepts(0) {
+        return Err(format!("'{test}' must not take parameters"));
+    }
+    function
+        .call(&mut interpreter, &[])
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRIPT: &str = "fun double(n) { return n * 2; }
+
+fun test_double() {
+  assert_eq(double(2), 4);
+}
+
+fun test_broken() {
+  assert_eq(double(2), 5);
+}
+
+fun helper() {}
+
+fun test_with_parameter(n) {}
+";
+
+    #[test]
+    fn discovers_tests() {
+        let arena = Arena::new();
+        let stmts = load(SCRIPT, &arena).unwrap();
+        let names: Vec<_> = discover(&stmts).iter().map(|test| test.name).collect();
+
+        assert_eq!(
+            names,
+            vec!["test_double", "test_broken", "test_with_parameter"]
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let mut out = vec![];
+        let summary = run_all("double.lox", SCRIPT, &mut out).unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                passed: 1,
+                failed: 2
+            }
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("test double.lox:3 test_double ... ok"));
+        assert!(out.contains("test double.lox:7 test_broken ... FAILED"));
+        assert!(out.contains("[line 8] assert_eq: left: 4 (number), right: 5 (number)."));
+        assert!(out.contains("'test_with_parameter' must not take parameters"));
+    }
+}
@@ -0,0 +1,71 @@
+/// Version and build information, shown by `--version` and returned by the `version()` native.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the commit the binary was built from, `unknown` outside of a git checkout.
+pub const GIT_HASH: &str = env!("LOX_GIT_HASH");
+
+/// The language implemented: Lox from the book plus the additions listed in the README.
+pub const DIALECT: &str = "lox+extensions";
+
+/// Names of the language additions `has_feature` reports, one per entry in the README's
+/// "Language additions" list.
+pub const LANGUAGE_FEATURES: &[&str] = &[
+    "block_comments",
+    "break_continue",
+    "class_methods",
+    "compound_assignment",
+    "getters",
+    "increment",
+    "interpolation",
+    "lists",
+    "maps",
+    "modulo",
+];
+
+/// Whether the language addition or cargo feature `name` is available in this build.
+pub fn has_feature(name: &str) -> bool {
+    LANGUAGE_FEATURES.contains(&name)
+        || (FEATURES != "none" && FEATURES.split(", ").any(|feature| feature == name))
+}
+
+/// Optional cargo features the binary was built with, like `net`, or `none`.
+pub const FEATURES: &str = env!("LOX_FEATURES");
+
+/// Printed by `--version`, `concat!` can't use the constants above so `DIALECT` is spelled out.
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ngit: ",
+    env!("LOX_GIT_HASH"),
+    "\ndialect: lox+extensions",
+    "\nfeatures: ",
+    env!("LOX_FEATURES"),
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn long_version() {
+        let lines: Vec<_> = LONG_VERSION.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                VERSION.to_string(),
+                format!("git: {GIT_HASH}"),
+                format!("dialect: {DIALECT}"),
+                format!("features: {FEATURES}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn has_feature() {
+        assert!(super::has_feature("break_continue"));
+        assert_eq!(super::has_feature("net"), cfg!(feature = "net"));
+        assert!(!super::has_feature("none"));
+        assert!(super::has_feature("lists"));
+        assert!(super::has_feature("interpolation"));
+        assert!(!super::has_feature("no_such_feature"));
+    }
+}
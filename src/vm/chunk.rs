@@ -0,0 +1,232 @@
+use std::fmt::Write;
+
+use crate::{interpreter::value::Value, lex::Token};
+
+/// One instruction of the VM. Jump targets are absolute indices into `Chunk::code`, constant and
+/// name operands index `Chunk::constants` and `Chunk::names`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    Constant(usize),
+    Nil,
+    True,
+    False,
+    Pop,
+    DefineGlobal(usize),
+    GetGlobal(usize),
+    SetGlobal(usize),
+    /// Index of the local on the stack
+    GetLocal(usize),
+    SetLocal(usize),
+    /// `Pop` repeated, like at the end of a block with several locals
+    PopN(usize),
+    /// `SetLocal` followed by `Pop`, an assignment statement
+    SetLocalPop(usize),
+    SetGlobalPop(usize),
+    /// Two `GetLocal`, like the operands of `a + b`
+    GetLocals(usize, usize),
+    /// `GetLocal` followed by `Constant`, like the operands of `i < 10`
+    GetLocalConstant(usize, usize),
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Not,
+    Negate,
+    Print,
+    Jump(usize),
+    /// Jumps if the value on top of the stack is falsey, the value stays on the stack
+    JumpIfFalse(usize),
+    Return,
+}
+
+/// A compiled program. `tokens` holds the token each instruction was compiled from, runtime
+/// errors and the disassembler take the line from there.
+#[derive(Debug, Default, Clone)]
+pub struct Chunk<'a> {
+    pub code: Vec<OpCode>,
+    pub tokens: Vec<Token<'a>>,
+    pub constants: Vec<Value<'a>>,
+    pub names: Vec<&'a str>,
+}
+
+impl<'a> Chunk<'a> {
+    /// Appends an instruction and returns its index.
+    pub fn write(&mut self, op: OpCode, token: Token<'a>) -> usize {
+        self.code.push(op);
+        self.tokens.push(token);
+        self.code.len() - 1
+    }
+
+    /// Returns the index of an identical constant if there is one already.
+    pub fn add_constant(&mut self, value: Value<'a>) -> usize {
+        let existing = self
+            .constants
+            .iter()
+            .position(|constant| match (constant, &value) {
+                // Compared by bits so that `0` and `-0` stay apart
+                (Value::Number(a), Value::Number(b)) => a.to_bits() == b.to_bits(),
+                (Value::String(a), Value::String(b)) => a == b,
+                _ => false,
+            });
+        match existing {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        }
+    }
+
+    pub fn add_name(&mut self, name: &'a str) -> usize {
+        match self.names.iter().position(|n| *n == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(name);
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Lists every instruction like clox's disassembler, under a `== name ==` header.
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out = format!("== {name} ==\n");
+        for offset in 0..self.code.len() {
+            let _ = writeln!(out, "{}", self.disassemble_instruction(offset));
+        }
+        out
+    }
+
+    /// Like `0003    2 OP_GET_GLOBAL       0 'a'`: the index of the instruction, its line or `|`
+    /// if it is on the line of the one before it, the opcode and its operands.
+    pub fn disassemble_instruction(&self, offset: usize) -> String {
+        let line = self.tokens[offset].line;
+        let line = match offset > 0 && self.tokens[offset - 1].line == line {
+            true => "   |".to_string(),
+            false => format!("{line:4}"),
+        };
+        let (name, operand) = match self.code[offset] {
+            OpCode::Constant(index) => (
+                "OP_CONSTANT",
+                format!("{index:4} '{}'", self.constants[index]),
+            ),
+            OpCode::Nil => ("OP_NIL", String::new()),
+            OpCode::True => ("OP_TRUE", String::new()),
+            OpCode::False => ("OP_FALSE", String::new()),
+            OpCode::Pop => ("OP_POP", String::new()),
+            OpCode::DefineGlobal(index) => ("OP_DEFINE_GLOBAL", self.name_operand(index)),
+            OpCode::GetGlobal(index) => ("OP_GET_GLOBAL", self.name_operand(index)),
+            OpCode::SetGlobal(index) => ("OP_SET_GLOBAL", self.name_operand(index)),
+            OpCode::GetLocal(slot) => ("OP_GET_LOCAL", format!("{slot:4}")),
+            OpCode::SetLocal(slot) => ("OP_SET_LOCAL", format!("{slot:4}")),
+            OpCode::PopN(count) => ("OP_POP_N", format!("{count:4}")),
+            OpCode::SetLocalPop(slot) => ("OP_SET_LOCAL_POP", format!("{slot:4}")),
+            OpCode::SetGlobalPop(index) => ("OP_SET_GLOBAL_POP", self.name_operand(index)),
+            OpCode::GetLocals(first, second) => ("OP_GET_LOCALS", format!("{first:4} {second}")),
+            OpCode::GetLocalConstant(slot, index) => (
+                "OP_GET_LOCAL_CONSTANT",
+                format!("{slot:4} {index} '{}'", self.constants[index]),
+            ),
+            OpCode::Equal => ("OP_EQUAL", String::new()),
+            OpCode::NotEqual => ("OP_NOT_EQUAL", String::new()),
+            OpCode::Greater => ("OP_GREATER", String::new()),
+            OpCode::GreaterEqual => ("OP_GREATER_EQUAL", String::new()),
+            OpCode::Less => ("OP_LESS", String::new()),
+            OpCode::LessEqual => ("OP_LESS_EQUAL", String::new()),
+            OpCode::Add => ("OP_ADD", String::new()),
+            OpCode::Subtract => ("OP_SUBTRACT", String::new()),
+            OpCode::Multiply => ("OP_MULTIPLY", String::new()),
+            OpCode::Divide => ("OP_DIVIDE", String::new()),
+            OpCode::Modulo => ("OP_MODULO", String::new()),
+            OpCode::Not => ("OP_NOT", String::new()),
+            OpCode::Negate => ("OP_NEGATE", String::new()),
+            OpCode::Print => ("OP_PRINT", String::new()),
+            OpCode::Jump(target) => ("OP_JUMP", format!("-> {target:04}")),
+            OpCode::JumpIfFalse(target) => ("OP_JUMP_IF_FALSE", format!("-> {target:04}")),
+            OpCode::Return => ("OP_RETURN", String::new()),
+        };
+        format!("{offset:04} {line} {name:<16} {operand}")
+            .trim_end()
+            .to_string()
+    }
+
+    fn name_operand(&self, index: usize) -> String {
+        format!("{index:4} '{}'", self.names[index])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::Arena,
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+        vm::compiler::Compiler,
+    };
+
+    #[test]
+    fn disassemble() {
+        let arena = Arena::new();
+        let tokens = Scanner::new("var a = 1;\nif (a) print a + 2;")
+            .scan_tokens()
+            .unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let chunk = Compiler::new().compile(&stmts).unwrap();
+
+        assert_eq!(
+            chunk.disassemble("script"),
+            "== script ==
+0000    1 OP_CONSTANT         0 '1'
+0001    | OP_DEFINE_GLOBAL    0 'a'
+0002    2 OP_GET_GLOBAL       0 'a'
+0003    | OP_JUMP_IF_FALSE -> 0010
+0004    | OP_POP
+0005    | OP_GET_GLOBAL       0 'a'
+0006    | OP_CONSTANT         1 '2'
+0007    | OP_ADD
+0008    | OP_PRINT
+0009    | OP_JUMP          -> 0011
+0010    | OP_POP
+0011    | OP_RETURN
+"
+        );
+    }
+
+    #[test]
+    fn fused_and_deduplicated() {
+        let arena = Arena::new();
+        let tokens = Scanner::new("{ var i = 1; var j = i; while (j < 1) j = i + 1; }")
+            .scan_tokens()
+            .unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let chunk = Compiler::new().compile(&stmts).unwrap();
+
+        assert_eq!(
+            chunk.disassemble("script"),
+            "== script ==
+0000    1 OP_CONSTANT         0 '1'
+0001    | OP_GET_LOCAL        0
+0002    | OP_GET_LOCAL_CONSTANT    1 0 '1'
+0003    | OP_LESS
+0004    | OP_JUMP_IF_FALSE -> 0010
+0005    | OP_POP
+0006    | OP_GET_LOCAL_CONSTANT    0 0 '1'
+0007    | OP_ADD
+0008    | OP_SET_LOCAL_POP    1
+0009    | OP_JUMP          -> 0002
+0010    | OP_POP_N            3
+0011    | OP_RETURN
+"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use crate::{
+    ast::*,
+    lex::{Span, Token, TokenType},
+};
+
+use super::{
+    chunk::{Chunk, OpCode},
+    error::CompileError,
+};
+
+struct Local<'a> {
+    name: &'a str,
+    depth: usize,
+}
+
+struct Loop {
+    /// Scope depth outside of the loop body, locals deeper than this are popped on `break`
+    depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// Compiles a resolved program into a `Chunk`. Locals live on the VM's stack, their slots are
+/// assigned here like in clox, so the Resolver's distances aren't needed.
+pub struct Compiler<'a> {
+    chunk: Chunk<'a>,
+    locals: Vec<Local<'a>>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    /// The latest token seen, instructions compiled from nodes without a token use its line
+    token: Token<'a>,
+    /// Fuse common sequences of instructions into one, see `emit`
+    optimize: bool,
+    /// The latest index a jump goes to, the instruction there can't be fused with the one before
+    target: usize,
+}
+
+impl<'a, 'b> Compiler<'a> {
+    pub fn new() -> Self {
+        Self {
+            chunk: Chunk::default(),
+            locals: vec![],
+            scope_depth: 0,
+            loops: vec![],
+            token: Token::new(TokenType::EOF, "", 1, Span { begin: 0, end: 0 }),
+            optimize: true,
+            target: 0,
+        }
+    }
+
+    /// Turns the fusing of instructions off, to compare against or to debug the plain bytecode.
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    pub fn compile(self, stmts: &'b [Stmt<'a>]) -> Result<Chunk<'a>, CompileError<'a>> {
+        self.compile_all(&[stmts])
+    }
+
+    /// Compiles the programs one after another into a single chunk, like a prelude and a script.
+    pub fn compile_all(
+        mut self,
+        programs: &[&'b [Stmt<'a>]],
+    ) -> Result<Chunk<'a>, CompileError<'a>> {
+        for stmt in programs.iter().copied().flatten() {
+            stmt.accept(&mut self)?;
+        }
+        self.emit(OpCode::Return);
+        Ok(self.chunk)
+    }
+
+    /// Appends an instruction and returns its index. Unless it is the target of a jump, it may be
+    /// fused with the instruction before it, then the index of that one is returned.
+    fn emit(&mut self, op: OpCode) -> usize {
+        let index = self.chunk.code.len();
+        if self.optimize && index > 0 && index != self.target {
+            if let Some(fused) = Self::fuse(self.chunk.code[index - 1], op) {
+                self.chunk.code[index - 1] = fused;
+                return index - 1;
+            }
+        }
+        self.chunk.write(op, self.token)
+    }
+
+    fn fuse(previous: OpCode, op: OpCode) -> Option<OpCode> {
+        let fused = match (previous, op) {
+            (OpCode::Pop, OpCode::Pop) => OpCode::PopN(2),
+            (OpCode::PopN(count), OpCode::Pop) => OpCode::PopN(count + 1),
+            (OpCode::SetLocal(slot), OpCode::Pop) => OpCode::SetLocalPop(slot),
+            (OpCode::SetGlobal(name), OpCode::Pop) => OpCode::SetGlobalPop(name),
+            (OpCode::GetLocal(first), OpCode::GetLocal(second)) => OpCode::GetLocals(first, second),
+            (OpCode::GetLocal(slot), OpCode::Constant(index)) => {
+                OpCode::GetLocalConstant(slot, index)
+            }
+            _ => return None,
+        };
+        Some(fused)
+    }
+
+    /// The index of the next instruction, as the target of a jump back to it.
+    fn label(&mut self) -> usize {
+        self.target = self.chunk.code.len();
+        self.target
+    }
+
+    /// Points the jump at `index` to the next instruction.
+    fn patch(&mut self, index: usize) {
+        let target = self.label();
+        match &mut self.chunk.code[index] {
+            OpCode::Jump(to) | OpCode::JumpIfFalse(to) => *to = target,
+            op => unreachable!("{op:?} is not a jump"),
+        }
+    }
+
+    fn expr(&mut self, expr: &'b Expr<'a>) -> Result<(), CompileError<'a>> {
+        expr.accept(self)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+        while self
+            .locals
+            .last()
+            .is_some_and(|local| local.depth > self.scope_depth)
+        {
+            self.locals.pop();
+            self.emit(OpCode::Pop);
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local.name == name)
+    }
+
+    /// Pops the locals of the loop body before jumping out of it, returns the jump to patch.
+    fn leave_loop(&mut self, keyword: Token<'a>) -> usize {
+        self.token = keyword;
+        let depth = self.loops.last().map_or(0, |l| l.depth);
+        let pops = self.locals.iter().filter(|l| l.depth > depth).count();
+        for _ in 0..pops {
+            self.emit(OpCode::Pop);
+        }
+        self.emit(OpCode::Jump(0))
+    }
+
+    fn unsupported(token: Token<'a>, feature: &'static str) -> Result<(), CompileError<'a>> {
+        Err(CompileError::Unsupported { token, feature })
+    }
+}
+
+impl Default for Compiler<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, 'b> ExprVisitor<'a, 'b> for Compiler<'a> {
+    type Output = Result<(), CompileError<'a>>;
+
+    fn visit_literal(&mut self, node: &ExprLiteral) -> Self::Output {
+        match &node.value {
+            LiteralValue::Nil => self.emit(OpCode::Nil),
+            LiteralValue::Bool(true) => self.emit(OpCode::True),
+            LiteralValue::Bool(false) => self.emit(OpCode::False),
+            value => {
+                let index = self.chunk.add_constant(value.clone().into());
+                self.emit(OpCode::Constant(index))
+            }
+        };
+        Ok(())
+    }
+
+    fn visit_grouping(&mut self, node: &'b ExprGrouping<'a>) -> Self::Output {
+        self.expr(node.value)
+    }
+
+    fn visit_logical(&mut self, node: &'b ExprLogical<'a>) -> Self::Output {
+        self.expr(node.left)?;
+        self.token = node.operator;
+        let short_circuit = match node.operator.kind {
+            TokenType::And => self.emit(OpCode::JumpIfFalse(0)),
+            _ => {
+                let else_jump = self.emit(OpCode::JumpIfFalse(0));
+                let end_jump = self.emit(OpCode::Jump(0));
+                self.patch(else_jump);
+                end_jump
+            }
+        };
+        self.emit(OpCode::Pop);
+        self.expr(node.right)?;
+        self.patch(short_circuit);
+        Ok(())
+    }
+
+    fn visit_set(&mut self, node: &'b ExprSet<'a>) -> Self::Output {
+        Self::unsupported(node.name, "fields")
+    }
+
+    fn visit_super(&mut self, node: &'b ExprSuper<'a>) -> Self::Output {
+        Self::unsupported(node.keyword, "classes")
+    }
+
+    fn visit_this(&mut self, node: &'b ExprThis<'a>) -> Self::Output {
+        Self::unsupported(node.keyword, "classes")
+    }
+
+    fn visit_unary(&mut self, node: &'b ExprUnary<'a>) -> Self::Output {
+        self.expr(node.value)?;
+        self.token = node.operator;
+        match node.operator.kind {
+            TokenType::Minus => self.emit(OpCode::Negate),
+            _ => self.emit(OpCode::Not),
+        };
+        Ok(())
+    }
+
+    fn visit_binary(&mut self, node: &'b ExprBinary<'a>) -> Self::Output {
+        self.expr(node.left)?;
+        self.expr(node.right)?;
+        self.token = node.operator;
+        let op = match node.operator.kind {
+            TokenType::Plus => OpCode::Add,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Star => OpCode::Multiply,
+            TokenType::Slash => OpCode::Divide,
+            TokenType::Percent => OpCode::Modulo,
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::BangEqual => OpCode::NotEqual,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            _ => return Self::unsupported(node.operator, "this operator"),
+        };
+        self.emit(op);
+        Ok(())
+    }
+
+    fn visit_call(&mut self, node: &'b ExprCall<'a>) -> Self::Output {
+        Self::unsupported(node.paren, "calls")
+    }
+
+    fn visit_get(&mut self, node: &'b ExprGet<'a>) -> Self::Output {
+        Self::unsupported(node.name, "fields")
+    }
+
+    fn visit_list(&mut self, node: &'b ExprList<'a>) -> Self::Output {
+        Self::unsupported(node.bracket, "lists")
+    }
+
+    fn visit_map(&mut self, node: &'b ExprMap<'a>) -> Self::Output {
+        Self::unsupported(node.brace, "maps")
+    }
+
+    fn visit_interpolation(&mut self, node: &'b ExprInterpolation<'a>) -> Self::Output {
+        Self::unsupported(node.start, "string interpolation")
+    }
+
+    fn visit_index(&mut self, node: &'b ExprIndex<'a>) -> Self::Output {
+        Self::unsupported(node.bracket, "lists")
+    }
+
+    fn visit_set_index(&mut self, node: &'b ExprSetIndex<'a>) -> Self::Output {
+        Self::unsupported(node.bracket, "lists")
+    }
+
+    fn visit_update(&mut self, node: &'b ExprUpdate<'a>) -> Self::Output {
+        Self::unsupported(node.operator, "++ and --")
+    }
+
+    fn visit_compound(&mut self, node: &'b ExprCompound<'a>) -> Self::Output {
+        match node.target {
+            Expr::Get(get) => Self::unsupported(get.name, "fields"),
+            Expr::Index(index) => Self::unsupported(index.bracket, "lists"),
+            _ => unreachable!(
+                "the parser only builds compound assignments of properties and indexes"
+            ),
+        }
+    }
+
+    fn visit_assign(&mut self, node: &'b ExprAssign<'a>) -> Self::Output {
+        self.expr(node.value)?;
+        self.token = node.name;
+        match self.resolve_local(node.name.lexeme) {
+            Some(slot) => self.emit(OpCode::SetLocal(slot)),
+            None => {
+                let name = self.chunk.add_name(node.name.lexeme);
+                self.emit(OpCode::SetGlobal(name))
+            }
+        };
+        Ok(())
+    }
+
+    fn visit_variable(&mut self, node: &'b ExprVariable<'a>) -> Self::Output {
+        self.token = node.name;
+        match self.resolve_local(node.name.lexeme) {
+            Some(slot) => self.emit(OpCode::GetLocal(slot)),
+            None => {
+                let name = self.chunk.add_name(node.name.lexeme);
+                self.emit(OpCode::GetGlobal(name))
+            }
+        };
+        Ok(())
+    }
+}
+
+impl<'a, 'b> StmtVisitor<'a, 'b> for Compiler<'a> {
+    type Output = Result<(), CompileError<'a>>;
+
+    fn visit_block(&mut self, node: &'b StmtBlock<'a>) -> Self::Output {
+        self.begin_scope();
+        for stmt in &node.statements {
+            stmt.accept(self)?;
+        }
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_break(&mut self, node: &'b StmtBreak<'a>) -> Self::Output {
+        let jump = self.leave_loop(node.keyword);
+        if let Some(current) = self.loops.last_mut() {
+            current.breaks.push(jump);
+        }
+        Ok(())
+    }
+
+    fn visit_class(&mut self, node: &'b StmtClass<'a>) -> Self::Output {
+        Self::unsupported(node.name, "classes")
+    }
+
+    fn visit_continue(&mut self, node: &'b StmtContinue<'a>) -> Self::Output {
+        let jump = self.leave_loop(node.keyword);
+        if let Some(current) = self.loops.last_mut() {
+            current.continues.push(jump);
+        }
+        Ok(())
+    }
+
+    fn visit_expression(&mut self, node: &'b StmtExpression<'a>) -> Self::Output {
+        self.expr(&node.expr)?;
+        self.emit(OpCode::Pop);
+        Ok(())
+    }
+
+    fn visit_function(&mut self, node: &'b StmtFunction<'a>) -> Self::Output {
+        Self::unsupported(node.name, "functions")
+    }
+
+    fn visit_if(&mut self, node: &'b StmtIf<'a>) -> Self::Output {
+        self.token = node.keyword;
+        self.expr(&node.condition)?;
+        let else_jump = self.emit(OpCode::JumpIfFalse(0));
+        self.emit(OpCode::Pop);
+        node.then_branch.accept(self)?;
+        let end_jump = self.emit(OpCode::Jump(0));
+        self.patch(else_jump);
+        self.emit(OpCode::Pop);
+        if let Some(else_branch) = &node.else_branch {
+            else_branch.accept(self)?;
+        }
+        self.patch(end_jump);
+        Ok(())
+    }
+
+    fn visit_print(&mut self, node: &'b StmtPrint<'a>) -> Self::Output {
+        self.expr(&node.expr)?;
+        self.emit(OpCode::Print);
+        Ok(())
+    }
+
+    fn visit_return(&mut self, node: &'b StmtReturn<'a>) -> Self::Output {
+        Self::unsupported(node.keyword, "functions")
+    }
+
+    fn visit_var(&mut self, node: &'b StmtVar<'a>) -> Self::Output {
+        match &node.initializer {
+            Some(initializer) => self.expr(initializer)?,
+            None => {
+                self.emit(OpCode::Nil);
+            }
+        }
+        self.token = node.name;
+
+        if self.scope_depth > 0 {
+            // The value stays on the stack as the local's slot
+            self.locals.push(Local {
+                name: node.name.lexeme,
+                depth: self.scope_depth,
+            });
+        } else {
+            let name = self.chunk.add_name(node.name.lexeme);
+            self.emit(OpCode::DefineGlobal(name));
+        }
+        Ok(())
+    }
+
+    fn visit_while(&mut self, node: &'b StmtWhile<'a>) -> Self::Output {
+        self.token = node.keyword;
+        let start = self.label();
+        self.expr(&node.condition)?;
+        let exit_jump = self.emit(OpCode::JumpIfFalse(0));
+        self.emit(OpCode::Pop);
+
+        self.loops.push(Loop {
+            depth: self.scope_depth,
+            breaks: vec![],
+            continues: vec![],
+        });
+        let body = node.body.accept(self);
+        let current = self.loops.pop().expect("the loop was pushed above");
+        body?;
+
+        for jump in current.continues {
+            self.patch(jump);
+        }
+        if let Some(increment) = &node.increment {
+            self.expr(increment)?;
+            self.emit(OpCode::Pop);
+        }
+        self.token = node.keyword;
+        self.emit(OpCode::Jump(start));
+
+        self.patch(exit_jump);
+        self.emit(OpCode::Pop);
+        // `break` jumps past the `Pop` of the condition, it was popped when the body was entered
+        for jump in current.breaks {
+            self.patch(jump);
+        }
+        Ok(())
+    }
+}
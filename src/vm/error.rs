@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+use crate::lex::Token;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum CompileError<'a> {
+    /// `feature` names the construct, like `functions`
+    #[error("[line {}] The vm backend doesn't support {} yet.", token.line, feature)]
+    Unsupported {
+        token: Token<'a>,
+        feature: &'static str,
+    },
+}
+
+impl<'a> CompileError<'a> {
+    /// Stable code of this error, see `rust_lox --explain <CODE>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::Unsupported { .. } => "E0400",
+        }
+    }
+
+    /// The token the error was found at.
+    pub fn token(&self) -> Token<'a> {
+        match self {
+            CompileError::Unsupported { token, .. } => *token,
+        }
+    }
+}
@@ -0,0 +1,691 @@
+//! A register IR for `--opt 2`, lowered from a compiled `Chunk`.
+//!
+//! The stack depth before each instruction of a chunk is known when compiling, so every stack
+//! slot becomes a register: locals keep the register of their slot and temporaries use the ones
+//! above them. On registers, copy propagation and dead-store elimination are simple passes over
+//! the straight-line code of each basic block. Values only flow between blocks through the
+//! registers below the stack depth at the end of the block, those are treated as live.
+
+use std::{collections::HashMap, fmt::Write as _, io::Write};
+
+use super::{
+    chunk::{Chunk, OpCode},
+    Vm,
+};
+use crate::{
+    interpreter::{error::RuntimeError, value::Value},
+    lex::Token,
+};
+
+pub type Reg = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+/// One instruction, `name` operands index `Chunk::names` and jump targets index `Program::code`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Inst {
+    Constant {
+        dst: Reg,
+        index: usize,
+    },
+    Nil {
+        dst: Reg,
+    },
+    Bool {
+        dst: Reg,
+        value: bool,
+    },
+    Move {
+        dst: Reg,
+        src: Reg,
+    },
+    DefineGlobal {
+        name: usize,
+        src: Reg,
+    },
+    GetGlobal {
+        dst: Reg,
+        name: usize,
+    },
+    SetGlobal {
+        name: usize,
+        src: Reg,
+    },
+    Binary {
+        op: BinaryOp,
+        dst: Reg,
+        left: Reg,
+        right: Reg,
+    },
+    Not {
+        dst: Reg,
+        src: Reg,
+    },
+    Negate {
+        dst: Reg,
+        src: Reg,
+    },
+    Print {
+        src: Reg,
+    },
+    Jump {
+        target: usize,
+    },
+    JumpIfFalse {
+        cond: Reg,
+        target: usize,
+    },
+    Return,
+}
+
+impl Inst {
+    /// The register the instruction writes.
+    fn dst(&self) -> Option<Reg> {
+        match *self {
+            Inst::Constant { dst, .. }
+            | Inst::Nil { dst }
+            | Inst::Bool { dst, .. }
+            | Inst::Move { dst, .. }
+            | Inst::GetGlobal { dst, .. }
+            | Inst::Binary { dst, .. }
+            | Inst::Not { dst, .. }
+            | Inst::Negate { dst, .. } => Some(dst),
+            _ => None,
+        }
+    }
+
+    /// The registers the instruction reads.
+    fn sources_mut(&mut self) -> Vec<&mut Reg> {
+        match self {
+            Inst::Move { src, .. }
+            | Inst::DefineGlobal { src, .. }
+            | Inst::SetGlobal { src, .. }
+            | Inst::Not { src, .. }
+            | Inst::Negate { src, .. }
+            | Inst::Print { src } => vec![src],
+            Inst::Binary { left, right, .. } => vec![left, right],
+            Inst::JumpIfFalse { cond, .. } => vec![cond],
+            _ => vec![],
+        }
+    }
+
+    fn sources(&self) -> Vec<Reg> {
+        let mut inst = *self;
+        inst.sources_mut().into_iter().map(|reg| *reg).collect()
+    }
+
+    /// Whether dropping the instruction can only change registers. Arithmetic, comparisons and
+    /// reading globals may fail with a runtime error, so they are kept even if their result isn't
+    /// used.
+    fn is_pure(&self) -> bool {
+        match self {
+            Inst::Constant { .. }
+            | Inst::Nil { .. }
+            | Inst::Bool { .. }
+            | Inst::Move { .. }
+            | Inst::Not { .. } => true,
+            Inst::Binary { op, .. } => matches!(op, BinaryOp::Equal | BinaryOp::NotEqual),
+            _ => false,
+        }
+    }
+
+    fn target_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            Inst::Jump { target } | Inst::JumpIfFalse { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    fn ends_block(&self) -> bool {
+        matches!(
+            self,
+            Inst::Jump { .. } | Inst::JumpIfFalse { .. } | Inst::Return
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    pub inst: Inst,
+    /// Index of the chunk instruction it was lowered from, runtime errors take the token there
+    pub at: usize,
+    /// How many registers hold values before the instruction runs, the stack depth of the chunk
+    depth: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub code: Vec<Instruction>,
+    /// How many registers the program uses, the deepest the chunk's stack gets
+    pub registers: usize,
+}
+
+impl Program {
+    /// Turns the stack slots of `chunk` into registers.
+    pub fn lower(chunk: &Chunk) -> Self {
+        let depths = stack_depths(chunk);
+        let mut program = Program::default();
+        let mut starts = Vec::with_capacity(chunk.code.len());
+
+        for (at, op) in chunk.code.iter().enumerate() {
+            starts.push(program.code.len());
+            // Code after a `break` or `continue` can't run and has no known depth
+            let Some(d) = depths[at] else {
+                continue;
+            };
+            let after = d as isize + effect(op);
+            program.registers = program.registers.max(d).max(after as usize);
+            let mut push = |inst, depth| {
+                program.code.push(Instruction { inst, at, depth });
+            };
+            match *op {
+                OpCode::Constant(index) => push(Inst::Constant { dst: d, index }, d),
+                OpCode::Nil => push(Inst::Nil { dst: d }, d),
+                OpCode::True => push(
+                    Inst::Bool {
+                        dst: d,
+                        value: true,
+                    },
+                    d,
+                ),
+                OpCode::False => push(
+                    Inst::Bool {
+                        dst: d,
+                        value: false,
+                    },
+                    d,
+                ),
+                OpCode::Pop | OpCode::PopN(_) => (),
+                OpCode::DefineGlobal(name) => push(Inst::DefineGlobal { name, src: d - 1 }, d),
+                OpCode::GetGlobal(name) => push(Inst::GetGlobal { dst: d, name }, d),
+                OpCode::SetGlobal(name) | OpCode::SetGlobalPop(name) => {
+                    push(Inst::SetGlobal { name, src: d - 1 }, d)
+                }
+                OpCode::GetLocal(slot) => push(Inst::Move { dst: d, src: slot }, d),
+                OpCode::SetLocal(slot) | OpCode::SetLocalPop(slot) => push(
+                    Inst::Move {
+                        dst: slot,
+                        src: d - 1,
+                    },
+                    d,
+                ),
+                OpCode::GetLocals(first, second) => {
+                    push(Inst::Move { dst: d, src: first }, d);
+                    push(
+                        Inst::Move {
+                            dst: d + 1,
+                            src: second,
+                        },
+                        d + 1,
+                    );
+                }
+                OpCode::GetLocalConstant(slot, index) => {
+                    push(Inst::Move { dst: d, src: slot }, d);
+                    push(Inst::Constant { dst: d + 1, index }, d + 1);
+                }
+                OpCode::Not => push(
+                    Inst::Not {
+                        dst: d - 1,
+                        src: d - 1,
+                    },
+                    d,
+                ),
+                OpCode::Negate => push(
+                    Inst::Negate {
+                        dst: d - 1,
+                        src: d - 1,
+                    },
+                    d,
+                ),
+                OpCode::Print => push(Inst::Print { src: d - 1 }, d),
+                OpCode::Jump(target) => push(Inst::Jump { target }, d),
+                OpCode::JumpIfFalse(target) => push(
+                    Inst::JumpIfFalse {
+                        cond: d - 1,
+                        target,
+                    },
+                    d,
+                ),
+                OpCode::Return => push(Inst::Return, d),
+                op => {
+                    let op = binary_op(op).expect("every other opcode is binary");
+                    let inst = Inst::Binary {
+                        op,
+                        dst: d - 2,
+                        left: d - 2,
+                        right: d - 1,
+                    };
+                    push(inst, d)
+                }
+            }
+        }
+
+        // Jumps still hold chunk indices
+        for instruction in &mut program.code {
+            if let Some(target) = instruction.inst.target_mut() {
+                *target = starts[*target];
+            }
+        }
+        program
+    }
+
+    /// Runs copy propagation and then dead-store elimination on each basic block.
+    pub fn optimize(&mut self) {
+        let blocks = self.blocks();
+        for block in &blocks {
+            self.propagate_copies(block.clone());
+        }
+        let mut keep = vec![true; self.code.len()];
+        for block in blocks {
+            self.eliminate_dead_stores(block, &mut keep);
+        }
+        self.retain(&keep);
+    }
+
+    /// The ranges of instructions that are only entered at their start and only left at their
+    /// end.
+    fn blocks(&self) -> Vec<std::ops::Range<usize>> {
+        let mut leaders = vec![false; self.code.len() + 1];
+        leaders[0] = true;
+        for (i, instruction) in self.code.iter().enumerate() {
+            let mut inst = instruction.inst;
+            if let Some(&mut target) = inst.target_mut() {
+                leaders[target] = true;
+            }
+            if inst.ends_block() {
+                leaders[i + 1] = true;
+            }
+        }
+
+        *leaders.last_mut().unwrap() = true;
+
+        let mut blocks = vec![];
+        let mut start = 0;
+        for (i, _) in leaders
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, leader)| **leader)
+        {
+            blocks.push(start..i);
+            start = i;
+        }
+        blocks
+    }
+
+    /// Reads of a register that holds a copy of another one read the original instead, which
+    /// often leaves the copy unused.
+    fn propagate_copies(&mut self, block: std::ops::Range<usize>) {
+        // Register -> the register it is a copy of
+        let mut copies: HashMap<Reg, Reg> = HashMap::new();
+        for instruction in &mut self.code[block] {
+            for src in instruction.inst.sources_mut() {
+                if let Some(&original) = copies.get(src) {
+                    *src = original;
+                }
+            }
+            if let Some(dst) = instruction.inst.dst() {
+                copies.retain(|&copy, &mut original| copy != dst && original != dst);
+                if let Inst::Move { src, .. } = instruction.inst {
+                    if src != dst {
+                        copies.insert(dst, src);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Marks instructions whose result is overwritten or never read before the block ends.
+    /// Registers below the stack depth at the end of the block are read by the blocks after it.
+    fn eliminate_dead_stores(&self, block: std::ops::Range<usize>, keep: &mut [bool]) {
+        let Some(last) = self.code[block.clone()].last() else {
+            return;
+        };
+        let exit_depth = match last.inst {
+            Inst::Jump { .. } | Inst::JumpIfFalse { .. } => last.depth,
+            Inst::Return => 0,
+            _ => self.code.get(block.end).map_or(0, |next| next.depth),
+        };
+        let mut live: Vec<bool> = (0..self.registers).map(|reg| reg < exit_depth).collect();
+
+        for i in block.rev() {
+            let inst = self.code[i].inst;
+            if let Some(dst) = inst.dst() {
+                let no_op = matches!(inst, Inst::Move { dst, src } if dst == src);
+                if no_op || (!live[dst] && inst.is_pure()) {
+                    keep[i] = false;
+                    continue;
+                }
+                live[dst] = false;
+            }
+            for src in inst.sources() {
+                live[src] = true;
+            }
+        }
+    }
+
+    /// Drops the instructions not marked in `keep`, jumps to a dropped one go to the next kept one.
+    fn retain(&mut self, keep: &[bool]) {
+        let mut new_index = Vec::with_capacity(self.code.len() + 1);
+        let mut kept = 0;
+        for &k in keep {
+            new_index.push(kept);
+            kept += usize::from(k);
+        }
+        new_index.push(kept);
+
+        let code = std::mem::take(&mut self.code);
+        self.code = code
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, &k)| k)
+            .map(|(mut instruction, _)| {
+                if let Some(target) = instruction.inst.target_mut() {
+                    *target = new_index[*target];
+                }
+                instruction
+            })
+            .collect();
+    }
+
+    /// Lists the instructions like the chunk's disassembly, under a `== name ==` header.
+    pub fn listing(&self, chunk: &Chunk, name: &str) -> String {
+        let mut out = format!("== {name} ==\n");
+        for index in 0..self.code.len() {
+            let _ = writeln!(out, "{}", self.show(chunk, index));
+        }
+        out
+    }
+
+    /// Like `0003    2 r1 = r1 + r2`.
+    fn show(&self, chunk: &Chunk, index: usize) -> String {
+        let line = chunk.tokens[self.code[index].at].line;
+        let line = match index > 0 && chunk.tokens[self.code[index - 1].at].line == line {
+            true => "   |".to_string(),
+            false => format!("{line:4}"),
+        };
+        let text = match self.code[index].inst {
+            Inst::Constant { dst, index } => format!("r{dst} = {}", chunk.constants[index]),
+            Inst::Nil { dst } => format!("r{dst} = nil"),
+            Inst::Bool { dst, value } => format!("r{dst} = {value}"),
+            Inst::Move { dst, src } => format!("r{dst} = r{src}"),
+            Inst::DefineGlobal { name, src } => format!("var {} = r{src}", chunk.names[name]),
+            Inst::GetGlobal { dst, name } => format!("r{dst} = {}", chunk.names[name]),
+            Inst::SetGlobal { name, src } => format!("{} = r{src}", chunk.names[name]),
+            Inst::Binary {
+                op,
+                dst,
+                left,
+                right,
+            } => format!("r{dst} = r{left} {} r{right}", op.symbol()),
+            Inst::Not { dst, src } => format!("r{dst} = !r{src}"),
+            Inst::Negate { dst, src } => format!("r{dst} = -r{src}"),
+            Inst::Print { src } => format!("print r{src}"),
+            Inst::Jump { target } => format!("jump -> {target:04}"),
+            Inst::JumpIfFalse { cond, target } => format!("jump if not r{cond} -> {target:04}"),
+            Inst::Return => "return".to_string(),
+        };
+        format!("{index:04} {line} {text}")
+    }
+}
+
+impl BinaryOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+        }
+    }
+}
+
+fn binary_op(op: OpCode) -> Option<BinaryOp> {
+    let op = match op {
+        OpCode::Equal => BinaryOp::Equal,
+        OpCode::NotEqual => BinaryOp::NotEqual,
+        OpCode::Greater => BinaryOp::Greater,
+        OpCode::GreaterEqual => BinaryOp::GreaterEqual,
+        OpCode::Less => BinaryOp::Less,
+        OpCode::LessEqual => BinaryOp::LessEqual,
+        OpCode::Add => BinaryOp::Add,
+        OpCode::Subtract => BinaryOp::Subtract,
+        OpCode::Multiply => BinaryOp::Multiply,
+        OpCode::Divide => BinaryOp::Divide,
+        OpCode::Modulo => BinaryOp::Modulo,
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// How the instruction changes the depth of the stack.
+fn effect(op: &OpCode) -> isize {
+    match op {
+        OpCode::Constant(_)
+        | OpCode::Nil
+        | OpCode::True
+        | OpCode::False
+        | OpCode::GetGlobal(_)
+        | OpCode::GetLocal(_) => 1,
+        OpCode::GetLocals(..) | OpCode::GetLocalConstant(..) => 2,
+        OpCode::PopN(count) => -(*count as isize),
+        OpCode::Pop
+        | OpCode::DefineGlobal(_)
+        | OpCode::SetLocalPop(_)
+        | OpCode::SetGlobalPop(_)
+        | OpCode::Print => -1,
+        OpCode::SetGlobal(_)
+        | OpCode::SetLocal(_)
+        | OpCode::Not
+        | OpCode::Negate
+        | OpCode::Jump(_)
+        | OpCode::JumpIfFalse(_)
+        | OpCode::Return => 0,
+        _ => -1,
+    }
+}
+
+/// The stack depth before each instruction, `None` for unreachable ones. The compiler keeps the
+/// depth the same on every path into an instruction.
+fn stack_depths(chunk: &Chunk) -> Vec<Option<usize>> {
+    let mut depths = vec![None; chunk.code.len()];
+    let mut pending = vec![(0, 0)];
+    while let Some((at, depth)) = pending.pop() {
+        if at >= chunk.code.len() || depths[at].is_some() {
+            continue;
+        }
+        depths[at] = Some(depth);
+        let op = chunk.code[at];
+        let after = (depth as isize + effect(&op)) as usize;
+        match op {
+            OpCode::Jump(target) => pending.push((target, after)),
+            OpCode::JumpIfFalse(target) => {
+                pending.push((target, after));
+                pending.push((at + 1, after));
+            }
+            OpCode::Return => (),
+            _ => pending.push((at + 1, after)),
+        }
+    }
+    depths
+}
+
+impl<'a> Vm<'a> {
+    /// Runs a program lowered from `chunk`, which still provides its constants, names and tokens.
+    pub fn run_ir(&mut self, program: &Program, chunk: &Chunk<'a>) -> Result<(), RuntimeError<'a>> {
+        let mut registers = vec![Value::Nil; program.registers];
+        let mut ip = 0;
+        loop {
+            let Instruction { inst, at, .. } = program.code[ip];
+            let token = chunk.tokens[at];
+            if let Some(out) = self.trace.as_mut() {
+                let _ = writeln!(out, "{}", program.show(chunk, ip));
+            }
+            ip += 1;
+
+            match inst {
+                Inst::Constant { dst, index } => registers[dst] = chunk.constants[index].clone(),
+                Inst::Nil { dst } => registers[dst] = Value::Nil,
+                Inst::Bool { dst, value } => registers[dst] = Value::Boolean(value),
+                Inst::Move { dst, src } => registers[dst] = registers[src].clone(),
+                Inst::DefineGlobal { name, src } => {
+                    self.globals
+                        .insert(chunk.names[name], registers[src].clone());
+                }
+                Inst::GetGlobal { dst, name } => match self.globals.get(chunk.names[name]) {
+                    Some(value) => registers[dst] = value.clone(),
+                    None => return Err(RuntimeError::UndefinedVariable { name: token }),
+                },
+                Inst::SetGlobal { name, src } => {
+                    let value = registers[src].clone();
+                    match self.globals.get_mut(chunk.names[name]) {
+                        Some(global) => *global = value,
+                        None => return Err(RuntimeError::UndefinedVariable { name: token }),
+                    }
+                }
+                Inst::Binary {
+                    op,
+                    dst,
+                    left,
+                    right,
+                } => {
+                    let left = registers[left].clone();
+                    let right = registers[right].clone();
+                    registers[dst] = binary(op, left, right, token)?;
+                }
+                Inst::Not { dst, src } => {
+                    registers[dst] = Value::Boolean(!registers[src].is_truthy());
+                }
+                Inst::Negate { dst, src } => match &registers[src] {
+                    Value::Number(n) => registers[dst] = Value::Number(-n),
+                    other => {
+                        return Err(RuntimeError::NumberOperand {
+                            operator: token,
+                            operand: other.describe(),
+                        })
+                    }
+                },
+                Inst::Print { src } => {
+                    writeln!(self.out, "{}", registers[src])
+                        .map_err(|_| RuntimeError::OutputError)?;
+                }
+                Inst::Jump { target } => ip = target,
+                Inst::JumpIfFalse { cond, target } => {
+                    if !registers[cond].is_truthy() {
+                        ip = target;
+                    }
+                }
+                Inst::Return => return Ok(()),
+            }
+        }
+    }
+}
+
+fn binary<'a>(
+    op: BinaryOp,
+    left: Value<'a>,
+    right: Value<'a>,
+    operator: Token<'a>,
+) -> Result<Value<'a>, RuntimeError<'a>> {
+    let (l, r) = match (op, &left, &right) {
+        (BinaryOp::Equal, ..) => return Ok(Value::Boolean(left == right)),
+        (BinaryOp::NotEqual, ..) => return Ok(Value::Boolean(left != right)),
+        (BinaryOp::Add, Value::String(_), Value::String(_)) => return Ok(left + right),
+        (_, Value::Number(l), Value::Number(r)) => (*l, *r),
+        (BinaryOp::Add, ..) => {
+            return Err(RuntimeError::NumberOrStringOperands {
+                operator,
+                left: left.describe(),
+                right: right.describe(),
+            })
+        }
+        _ => {
+            return Err(RuntimeError::MutlipleNumberOperands {
+                operator,
+                left: left.describe(),
+                right: right.describe(),
+            })
+        }
+    };
+    let value = match op {
+        BinaryOp::Greater => Value::Boolean(l > r),
+        BinaryOp::GreaterEqual => Value::Boolean(l >= r),
+        BinaryOp::Less => Value::Boolean(l < r),
+        BinaryOp::LessEqual => Value::Boolean(l <= r),
+        BinaryOp::Add => Value::Number(l + r),
+        BinaryOp::Subtract => Value::Number(l - r),
+        BinaryOp::Multiply => Value::Number(l * r),
+        BinaryOp::Divide => Value::Number(l / r),
+        BinaryOp::Modulo => Value::Number(l % r),
+        BinaryOp::Equal | BinaryOp::NotEqual => unreachable!("handled above"),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ast::Arena,
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+        vm::compiler::Compiler,
+    };
+
+    fn listing(source: &str, optimize: bool) -> String {
+        let arena = Arena::new();
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let chunk = Compiler::new().compile(&stmts).unwrap();
+        let mut program = Program::lower(&chunk);
+        if optimize {
+            program.optimize();
+        }
+        program.listing(&chunk, "test")
+    }
+
+    #[test]
+    fn copies_and_dead_stores() {
+        let source = "{ var a = 1; var b = a; print b; }";
+        assert_eq!(
+            listing(source, false),
+            "== test ==
+0000    1 r0 = 1
+0001    | r1 = r0
+0002    | r2 = r1
+0003    | print r2
+0004    | return
+"
+        );
+        assert_eq!(
+            listing(source, true),
+            "== test ==
+0000    1 r0 = 1
+0001    | print r0
+0002    | return
+"
+        );
+    }
+}
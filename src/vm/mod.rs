@@ -0,0 +1,441 @@
+//! A bytecode backend: `Compiler` turns a resolved program into a `Chunk` that `Vm` runs on a
+//! value stack, like clox. Only expressions, variables and control flow are supported so far,
+//! anything else is a `CompileError`.
+
+pub mod chunk;
+pub mod compiler;
+pub mod error;
+pub mod ir;
+#[cfg(feature = "threaded-dispatch")]
+mod threaded;
+
+use std::{collections::HashMap, io::Write};
+
+use chunk::{Chunk, OpCode};
+
+use crate::{
+    interpreter::{error::RuntimeError, native_fun::CONSTANTS, value::Value},
+    lex::Token,
+};
+
+pub struct Vm<'a> {
+    stack: Vec<Value<'a>>,
+    globals: HashMap<&'a str, Value<'a>>,
+    out: Box<dyn Write + 'a>,
+    /// Where the stack and each instruction are logged before it runs, see `--trace-exec`
+    trace: Option<Box<dyn Write + 'a>>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    pub fn with_output(out: Box<dyn Write + 'a>) -> Self {
+        Self {
+            stack: vec![],
+            globals: CONSTANTS
+                .into_iter()
+                .map(|(name, value)| (name, Value::Number(value)))
+                .collect(),
+            out,
+            trace: None,
+        }
+    }
+
+    /// Logs the stack to `out` before each instruction, followed by the instruction, like clox's
+    /// `DEBUG_TRACE_EXECUTION`.
+    pub fn set_trace(&mut self, out: Box<dyn Write + 'a>) {
+        self.trace = Some(out);
+    }
+
+    /// Runs the chunk, dispatching with a `match` unless the `threaded-dispatch` feature is on.
+    pub fn run(&mut self, chunk: &Chunk<'a>) -> Result<(), RuntimeError<'a>> {
+        #[cfg(feature = "threaded-dispatch")]
+        return self.run_threaded(chunk);
+        #[cfg(not(feature = "threaded-dispatch"))]
+        self.run_match(chunk)
+    }
+
+    /// Runs the chunk, dispatching each instruction with a `match` on its opcode.
+    pub fn run_match(&mut self, chunk: &Chunk<'a>) -> Result<(), RuntimeError<'a>> {
+        self.stack.clear();
+        let mut ip = 0;
+        loop {
+            let op = chunk.code[ip];
+            let token = chunk.tokens[ip];
+            self.log(chunk, ip);
+            ip += 1;
+
+            match op {
+                OpCode::Constant(index) => self.stack.push(chunk.constants[index].clone()),
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Boolean(true)),
+                OpCode::False => self.stack.push(Value::Boolean(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::DefineGlobal(name) => {
+                    let value = self.pop();
+                    self.globals.insert(chunk.names[name], value);
+                }
+                OpCode::GetGlobal(name) => self.get_global(chunk.names[name], token)?,
+                OpCode::SetGlobal(name) => {
+                    let value = self.peek().clone();
+                    self.set_global(chunk.names[name], value, token)?;
+                }
+                OpCode::GetLocal(slot) => self.stack.push(self.stack[slot].clone()),
+                OpCode::SetLocal(slot) => self.stack[slot] = self.peek().clone(),
+                OpCode::PopN(count) => self.stack.truncate(self.stack.len().saturating_sub(count)),
+                OpCode::SetLocalPop(slot) => self.stack[slot] = self.pop(),
+                OpCode::SetGlobalPop(name) => {
+                    let value = self.pop();
+                    self.set_global(chunk.names[name], value, token)?;
+                }
+                OpCode::GetLocals(first, second) => {
+                    self.stack.push(self.stack[first].clone());
+                    self.stack.push(self.stack[second].clone());
+                }
+                OpCode::GetLocalConstant(slot, index) => {
+                    self.stack.push(self.stack[slot].clone());
+                    self.stack.push(chunk.constants[index].clone());
+                }
+                OpCode::Equal => {
+                    let (left, right) = self.pop_two();
+                    self.stack.push(Value::Boolean(left == right));
+                }
+                OpCode::NotEqual => {
+                    let (left, right) = self.pop_two();
+                    self.stack.push(Value::Boolean(left != right));
+                }
+                OpCode::Greater => self.compare(token, |l, r| l > r)?,
+                OpCode::GreaterEqual => self.compare(token, |l, r| l >= r)?,
+                OpCode::Less => self.compare(token, |l, r| l < r)?,
+                OpCode::LessEqual => self.compare(token, |l, r| l <= r)?,
+                OpCode::Add => self.add(token)?,
+                OpCode::Subtract => self.arithmetic(token, |l, r| l - r)?,
+                OpCode::Multiply => self.arithmetic(token, |l, r| l * r)?,
+                OpCode::Divide => self.arithmetic(token, |l, r| l / r)?,
+                OpCode::Modulo => self.arithmetic(token, |l, r| l % r)?,
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.stack.push(Value::Boolean(!value.is_truthy()));
+                }
+                OpCode::Negate => self.negate(token)?,
+                OpCode::Print => self.print()?,
+                OpCode::Jump(target) => ip = target,
+                OpCode::JumpIfFalse(target) => {
+                    if !self.peek().is_truthy() {
+                        ip = target;
+                    }
+                }
+                OpCode::Return => return Ok(()),
+            }
+        }
+    }
+
+    fn get_global(&mut self, name: &str, token: Token<'a>) -> Result<(), RuntimeError<'a>> {
+        match self.globals.get(name) {
+            Some(value) => {
+                self.stack.push(value.clone());
+                Ok(())
+            }
+            None => Err(RuntimeError::UndefinedVariable { name: token }),
+        }
+    }
+
+    fn set_global(
+        &mut self,
+        name: &str,
+        value: Value<'a>,
+        token: Token<'a>,
+    ) -> Result<(), RuntimeError<'a>> {
+        match self.globals.get_mut(name) {
+            Some(global) => {
+                *global = value;
+                Ok(())
+            }
+            None => Err(RuntimeError::UndefinedVariable { name: token }),
+        }
+    }
+
+    fn add(&mut self, operator: Token<'a>) -> Result<(), RuntimeError<'a>> {
+        let (left, right) = self.pop_two();
+        match (&left, &right) {
+            (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_)) => {
+                self.stack.push(left + right);
+                Ok(())
+            }
+            _ => Err(RuntimeError::NumberOrStringOperands {
+                operator,
+                left: left.describe(),
+                right: right.describe(),
+            }),
+        }
+    }
+
+    fn negate(&mut self, operator: Token<'a>) -> Result<(), RuntimeError<'a>> {
+        match self.pop() {
+            Value::Number(n) => {
+                self.stack.push(Value::Number(-n));
+                Ok(())
+            }
+            other => Err(RuntimeError::NumberOperand {
+                operator,
+                operand: other.describe(),
+            }),
+        }
+    }
+
+    fn print(&mut self) -> Result<(), RuntimeError<'a>> {
+        let value = self.pop();
+        writeln!(self.out, "{value}").map_err(|_| RuntimeError::OutputError)
+    }
+
+    fn log(&mut self, chunk: &Chunk<'a>, ip: usize) {
+        let Some(out) = self.trace.as_mut() else {
+            return;
+        };
+        let stack: String = self
+            .stack
+            .iter()
+            .map(|value| format!("[ {value} ]"))
+            .collect();
+        // Tracing is a debugging aid, it must not change the outcome of running
+        let _ = writeln!(out, "{}", format!("          {stack}").trim_end());
+        let _ = writeln!(out, "{}", chunk.disassemble_instruction(ip));
+    }
+
+    fn pop(&mut self) -> Value<'a> {
+        self.stack.pop().unwrap_or_default()
+    }
+
+    fn peek(&self) -> &Value<'a> {
+        self.stack.last().unwrap_or(&Value::Nil)
+    }
+
+    fn pop_two(&mut self) -> (Value<'a>, Value<'a>) {
+        let right = self.pop();
+        let left = self.pop();
+        (left, right)
+    }
+
+    fn numbers(&mut self, operator: Token<'a>) -> Result<(f64, f64), RuntimeError<'a>> {
+        // Fast path, numbers don't need to be moved off the stack to be dropped
+        if let [.., Value::Number(l), Value::Number(r)] = self.stack[..] {
+            self.stack.truncate(self.stack.len() - 2);
+            return Ok((l, r));
+        }
+        match self.pop_two() {
+            (Value::Number(l), Value::Number(r)) => Ok((l, r)),
+            (left, right) => Err(RuntimeError::MutlipleNumberOperands {
+                operator,
+                left: left.describe(),
+                right: right.describe(),
+            }),
+        }
+    }
+
+    fn arithmetic(
+        &mut self,
+        operator: Token<'a>,
+        op: fn(f64, f64) -> f64,
+    ) -> Result<(), RuntimeError<'a>> {
+        let (l, r) = self.numbers(operator)?;
+        self.stack.push(Value::Number(op(l, r)));
+        Ok(())
+    }
+
+    fn compare(
+        &mut self,
+        operator: Token<'a>,
+        op: fn(f64, f64) -> bool,
+    ) -> Result<(), RuntimeError<'a>> {
+        let (l, r) = self.numbers(operator)?;
+        self.stack.push(Value::Boolean(op(l, r)));
+        Ok(())
+    }
+}
+
+impl Default for Vm<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::Arena,
+        lex::Scanner,
+        parser::{Parser, TokenStream},
+    };
+
+    use super::{compiler::Compiler, *};
+
+    /// Runs the source at every `--opt` level, all of them must agree.
+    fn run(source: &str) -> Result<String, String> {
+        let optimized = run_compiled(source, 1);
+        assert_eq!(optimized, run_compiled(source, 0), "{source}");
+        assert_eq!(optimized, run_compiled(source, 2), "{source}");
+        optimized
+    }
+
+    fn run_compiled(source: &str, opt: u8) -> Result<String, String> {
+        let mut output = vec![];
+        let arena = Arena::new();
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let mut compiler = Compiler::new();
+        compiler.set_optimize(opt >= 1);
+        let chunk = compiler.compile(&stmts).map_err(|e| e.to_string())?;
+
+        let mut vm = Vm::with_output(Box::new(&mut output));
+        let result = match opt {
+            2 => {
+                let mut program = ir::Program::lower(&chunk);
+                program.optimize();
+                vm.run_ir(&program, &chunk)
+            }
+            _ => vm.run(&chunk),
+        };
+        let result = result.map_err(|e| e.to_string());
+        drop(vm);
+        drop(chunk);
+        result.map(|_| String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn expressions() {
+        let source = "print 1 + 2 * 3;
+print (1 + 2) * 3 / 2;
+print -(4 - 6);
+print \"a\" + \"b\";
+print 1 < 2 and 2 <= 2;
+print 1 > 2 or 3 >= 4;
+print nil or \"default\";
+print !nil == true;
+print 1 != 1;";
+
+        assert_eq!(
+            run(source),
+            Ok("7\n4.5\n2\nab\ntrue\nfalse\ndefault\ntrue\nfalse\n".to_string())
+        );
+    }
+
+    #[test]
+    fn variables() {
+        let source = "var a = 1;
+var b;
+print b;
+{
+  var c = a + 1;
+  {
+    var a = c + 10;
+    print a;
+  }
+  c = c * 2;
+  print c;
+}
+b = a = 5;
+print a + b;";
+
+        assert_eq!(run(source), Ok("nil\n12\n4\n10\n".to_string()));
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "var sum = 0;
+for (var i = 0; i < 10; i = i + 1) {
+  if (i == 7) break;
+  var odd = i / 2 != 0 and i == 1 or i == 3 or i == 5;
+  if (odd) continue;
+  else sum = sum + i;
+}
+print sum;
+var n = 0;
+while (n < 3) n = n + 1;
+print n;";
+
+        assert_eq!(run(source), Ok("12\n3\n".to_string()));
+    }
+
+    #[test]
+    fn fused_instructions() {
+        // The loop's condition starts with `GetLocal` right after one, it must not be fused
+        // into it or the jump back would land in the middle of the pair
+        let source = "{
+  var i = 0;
+  var j = i;
+  while (j < 3) {
+    var k = j;
+    j = k + 1;
+  }
+  print i + j;
+}
+var g = 1;
+g = g + 1;
+print g;";
+
+        assert_eq!(run(source), Ok("3\n2\n".to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            run("var a = 1;\nprint a + nil;"),
+            Err(
+                "[line 2] Operands must be two numbers or two strings, got number 1 and nil."
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            run("print -\"a\";"),
+            Err("[line 1] Operand must be a number, got string \"a\".".to_string())
+        );
+        assert_eq!(
+            run("print 1;\nprint b;"),
+            Err("[line 2] Undefined Variable 'b'.".to_string())
+        );
+        assert_eq!(
+            run("fun f() {}"),
+            Err("[line 1] The vm backend doesn't support functions yet.".to_string())
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let mut trace = vec![];
+        let arena = Arena::new();
+        let tokens = Scanner::new("print -(1 + 2);").scan_tokens().unwrap();
+        let stmts = Parser::new(TokenStream::new(tokens), &arena)
+            .parse()
+            .unwrap();
+        let chunk = Compiler::new().compile(&stmts).unwrap();
+
+        let mut vm = Vm::with_output(Box::new(std::io::sink()));
+        vm.set_trace(Box::new(&mut trace));
+        vm.run(&chunk).unwrap();
+        drop(vm);
+        drop(chunk);
+
+        assert_eq!(
+            String::from_utf8(trace).unwrap(),
+            "
+0000    1 OP_CONSTANT         0 '1'
+          [ 1 ]
+0001    | OP_CONSTANT         1 '2'
+          [ 1 ][ 2 ]
+0002    | OP_ADD
+          [ 3 ]
+0003    | OP_NEGATE
+          [ -3 ]
+0004    | OP_PRINT
+
+0005    | OP_RETURN
+"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Dispatch through function pointers instead of a `match`. Before running, each instruction is
+//! decoded into the handler for its opcode and its operands, the loop then calls the handlers one
+//! after another without looking at the opcode again. Rust has no computed goto, this is the
+//! closest stable equivalent.
+
+use super::{
+    chunk::{Chunk, OpCode},
+    Vm,
+};
+use crate::{
+    interpreter::{error::RuntimeError, value::Value},
+    lex::Token,
+};
+
+/// Runs one instruction with its two operands, unused ones are 0. Jumps set `ip`, `false` stops
+/// the program.
+type Handler<'a> =
+    fn(&mut Vm<'a>, &Chunk<'a>, usize, usize, &mut usize) -> Result<bool, RuntimeError<'a>>;
+
+#[derive(Clone, Copy)]
+struct Instruction<'a> {
+    handler: Handler<'a>,
+    a: usize,
+    b: usize,
+}
+
+impl<'a> Vm<'a> {
+    /// Runs the chunk, dispatching each instruction through the handler it was decoded into.
+    pub fn run_threaded(&mut self, chunk: &Chunk<'a>) -> Result<(), RuntimeError<'a>> {
+        let code: Vec<_> = chunk.code.iter().map(|&op| decode(op)).collect();
+        self.stack.clear();
+        let mut ip = 0;
+        loop {
+            let Instruction { handler, a, b } = code[ip];
+            self.log(chunk, ip);
+            ip += 1;
+            if !handler(self, chunk, a, b, &mut ip)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// The token of the instruction that is running, `ip` already points past it.
+fn token<'a>(chunk: &Chunk<'a>, ip: &usize) -> Token<'a> {
+    chunk.tokens[*ip - 1]
+}
+
+fn decode<'a>(op: OpCode) -> Instruction<'a> {
+    let (handler, a, b): (Handler<'a>, _, _) = match op {
+        OpCode::Constant(index) => (
+            |vm, chunk, index, _, _| {
+                vm.stack.push(chunk.constants[index].clone());
+                Ok(true)
+            },
+            index,
+            0,
+        ),
+        OpCode::Nil => (
+            |vm, _, _, _, _| {
+                vm.stack.push(Value::Nil);
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::True => (
+            |vm, _, _, _, _| {
+                vm.stack.push(Value::Boolean(true));
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::False => (
+            |vm, _, _, _, _| {
+                vm.stack.push(Value::Boolean(false));
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Pop => (
+            |vm, _, _, _, _| {
+                vm.pop();
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::DefineGlobal(name) => (
+            |vm, chunk, name, _, _| {
+                let value = vm.pop();
+                vm.globals.insert(chunk.names[name], value);
+                Ok(true)
+            },
+            name,
+            0,
+        ),
+        OpCode::GetGlobal(name) => (
+            |vm, chunk, name, _, ip| {
+                vm.get_global(chunk.names[name], token(chunk, ip))?;
+                Ok(true)
+            },
+            name,
+            0,
+        ),
+        OpCode::SetGlobal(name) => (
+            |vm, chunk, name, _, ip| {
+                let value = vm.peek().clone();
+                vm.set_global(chunk.names[name], value, token(chunk, ip))?;
+                Ok(true)
+            },
+            name,
+            0,
+        ),
+        OpCode::GetLocal(slot) => (
+            |vm, _, slot, _, _| {
+                vm.stack.push(vm.stack[slot].clone());
+                Ok(true)
+            },
+            slot,
+            0,
+        ),
+        OpCode::SetLocal(slot) => (
+            |vm, _, slot, _, _| {
+                vm.stack[slot] = vm.peek().clone();
+                Ok(true)
+            },
+            slot,
+            0,
+        ),
+        OpCode::PopN(count) => (
+            |vm, _, count, _, _| {
+                vm.stack.truncate(vm.stack.len().saturating_sub(count));
+                Ok(true)
+            },
+            count,
+            0,
+        ),
+        OpCode::SetLocalPop(slot) => (
+            |vm, _, slot, _, _| {
+                vm.stack[slot] = vm.pop();
+                Ok(true)
+            },
+            slot,
+            0,
+        ),
+        OpCode::SetGlobalPop(name) => (
+            |vm, chunk, name, _, ip| {
+                let value = vm.pop();
+                vm.set_global(chunk.names[name], value, token(chunk, ip))?;
+                Ok(true)
+            },
+            name,
+            0,
+        ),
+        OpCode::GetLocals(first, second) => (
+            |vm, _, first, second, _| {
+                vm.stack.push(vm.stack[first].clone());
+                vm.stack.push(vm.stack[second].clone());
+                Ok(true)
+            },
+            first,
+            second,
+        ),
+        OpCode::GetLocalConstant(slot, index) => (
+            |vm, chunk, slot, index, _| {
+                vm.stack.push(vm.stack[slot].clone());
+                vm.stack.push(chunk.constants[index].clone());
+                Ok(true)
+            },
+            slot,
+            index,
+        ),
+        OpCode::Equal => (
+            |vm, _, _, _, _| {
+                let (left, right) = vm.pop_two();
+                vm.stack.push(Value::Boolean(left == right));
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::NotEqual => (
+            |vm, _, _, _, _| {
+                let (left, right) = vm.pop_two();
+                vm.stack.push(Value::Boolean(left != right));
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Greater => (
+            |vm, chunk, _, _, ip| {
+                vm.compare(token(chunk, ip), |l, r| l > r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::GreaterEqual => (
+            |vm, chunk, _, _, ip| {
+                vm.compare(token(chunk, ip), |l, r| l >= r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Less => (
+            |vm, chunk, _, _, ip| {
+                vm.compare(token(chunk, ip), |l, r| l < r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::LessEqual => (
+            |vm, chunk, _, _, ip| {
+                vm.compare(token(chunk, ip), |l, r| l <= r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Add => (
+            |vm, chunk, _, _, ip| {
+                vm.add(token(chunk, ip))?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Subtract => (
+            |vm, chunk, _, _, ip| {
+                vm.arithmetic(token(chunk, ip), |l, r| l - r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Multiply => (
+            |vm, chunk, _, _, ip| {
+                vm.arithmetic(token(chunk, ip), |l, r| l * r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Divide => (
+            |vm, chunk, _, _, ip| {
+                vm.arithmetic(token(chunk, ip), |l, r| l / r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Modulo => (
+            |vm, chunk, _, _, ip| {
+                vm.arithmetic(token(chunk, ip), |l, r| l % r)?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Not => (
+            |vm, _, _, _, _| {
+                let value = vm.pop();
+                vm.stack.push(Value::Boolean(!value.is_truthy()));
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Negate => (
+            |vm, chunk, _, _, ip| {
+                vm.negate(token(chunk, ip))?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Print => (
+            |vm, _, _, _, _| {
+                vm.print()?;
+                Ok(true)
+            },
+            0,
+            0,
+        ),
+        OpCode::Jump(target) => (
+            |_, _, target, _, ip| {
+                *ip = target;
+                Ok(true)
+            },
+            target,
+            0,
+        ),
+        OpCode::JumpIfFalse(target) => (
+            |vm, _, target, _, ip| {
+                if !vm.peek().is_truthy() {
+                    *ip = target;
+                }
+                Ok(true)
+            },
+            target,
+            0,
+        ),
+        OpCode::Return => (|_, _, _, _, _| Ok(false), 0, 0),
+    };
+    Instruction { handler, a, b }
+}
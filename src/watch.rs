@@ -0,0 +1,74 @@
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Editors often write a file in several steps, wait until it stopped changing for this long.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Notices changes of files by polling their modification times.
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    stamps: Vec<Option<SystemTime>>,
+}
+
+impl Watcher {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let stamps = modified(&paths);
+        Self { paths, stamps }
+    }
+
+    /// Whether a file was modified, created or removed since the last call.
+    pub fn changed(&mut self) -> bool {
+        let stamps = modified(&self.paths);
+        let changed = stamps != self.stamps;
+        self.stamps = stamps;
+        changed
+    }
+
+    /// Blocks until a file changed and then didn't change for `DEBOUNCE`.
+    pub fn wait(&mut self) {
+        while !self.changed() {
+            thread::sleep(POLL_INTERVAL);
+        }
+        loop {
+            thread::sleep(DEBOUNCE);
+            if !self.changed() {
+                return;
+            }
+        }
+    }
+}
+
+fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use super::*;
+
+    #[test]
+    fn changed() {
+        let path = std::env::temp_dir().join(format!("rust_lox_watch_{}.lox", std::process::id()));
+        fs::write(&path, "print 1;").unwrap();
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        assert!(!watcher.changed());
+
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+    }
+}
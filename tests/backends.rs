@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+use std::process::Command;
+use test_generator::test_resources;
+
+/// Stdout, stderr and exit code of one run.
+type Outcome = (String, String, Option<i32>);
+
+/// Runs a script of the corpus on the tree-walker and on the VM at every `--opt` level, all of
+/// them must print the same and exit the same. Scripts using something the bytecode compiler
+/// doesn't support yet are only run on the tree-walker by `integration.rs`.
+#[test_resources("test/**/*.lox")]
+fn backends_agree(test_path: &str) {
+    let tree = run(test_path, &["--backend", "tree"]);
+    for opt in ["0", "1", "2"] {
+        let vm = run(test_path, &["--backend", "vm", "--opt", opt]);
+        if vm.1.contains("[E0400]") {
+            return;
+        }
+        assert_eq!(
+            vm, tree,
+            "{test_path} runs differently on the VM with --opt {opt}"
+        );
+    }
+}
+
+/// The VM would silently ignore these, so they are rejected together with it.
+#[test]
+fn vm_rejects_tree_only_flags() {
+    let script = "test/empty_file.lox";
+    for flags in [
+        &["--backend", "vm", "--stdlib"][..],
+        &["--opt", "0", "--max-print-len", "3"],
+        &["--disassemble", "--strict-fields"],
+        &["--trace-exec", "--trace"],
+    ] {
+        let (_, stderr, code) = run(script, flags);
+        assert_eq!(code, Some(2), "{flags:?}");
+        assert!(
+            stderr.contains("isn't supported by the VM backend"),
+            "{flags:?}: {stderr}"
+        );
+    }
+    assert_eq!(run(script, &["--backend", "tree", "--stdlib"]).2, Some(0));
+}
+
+fn run(test_path: &str, args: &[&str]) -> Outcome {
+    let interpreter_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/rust_lox");
+    let output = Command::new(&interpreter_path)
+        .arg("--color=never")
+        .args(args)
+        .arg(test_path)
+        .output()
+        .expect("Failed to execute interpreter");
+    (
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        output.status.code(),
+    )
+}
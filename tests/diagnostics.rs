@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use test_generator::test_resources;
+
+/// Runs a program from `tests/diagnostics` and compares everything it printed to stderr with the
+/// `.stderr` file next to it. A first line like `// args: --strict` passes extra arguments.
+/// Run with `BLESS=1` to write the current output to the `.stderr` files instead.
+#[test_resources("tests/diagnostics/*.lox")]
+fn diagnostics(test_path: &str) {
+    let test_file = PathBuf::from(test_path);
+    let expected_file = test_file.with_extension("stderr");
+
+    let content = fs::read_to_string(&test_file).expect("Failed to read test");
+    let args = content
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("// args:"))
+        .unwrap_or_default()
+        .split_whitespace();
+
+    let interpreter_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/rust_lox");
+
+    let output = Command::new(&interpreter_path)
+        .arg("--color=never")
+        .args(args)
+        .arg(&test_file)
+        .output()
+        .expect("Failed to execute interpreter");
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(&expected_file, &stderr).expect("Failed to write expected stderr");
+        return;
+    }
+
+    let expected = read_expected(&expected_file);
+    assert_eq!(
+        stderr,
+        expected,
+        "stderr of {} doesn't match {}, rerun with BLESS=1 if the change is intended",
+        test_file.display(),
+        expected_file.display()
+    );
+}
+
+fn read_expected(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing, run with BLESS=1 to create it",
+            path.display()
+        )
+    })
+}
@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Runs the interpreter with `flag` on a script made of `source` and returns the exit code and
+/// what it printed to stdout.
+fn dump(flag: &str, name: &str, source: &str) -> (Option<i32>, String) {
+    let script = std::env::temp_dir().join(format!("rust_lox_dump_{name}.lox"));
+    fs::write(&script, source).expect("Failed to write script");
+    let interpreter_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/rust_lox");
+    let output = Command::new(&interpreter_path)
+        .arg("--color=never")
+        .arg(flag)
+        .arg(&script)
+        .output()
+        .expect("Failed to execute interpreter");
+    fs::remove_file(&script).ok();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    )
+}
+
+#[test]
+fn tokens() {
+    let (code, stdout) = dump("--tokens", "tokens", "var a = 1;\nprint a;");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "   1 Var var null
+   1 Ident a null
+   1 Equal = null
+   1 Number 1 1
+   1 Semicolon ; null
+   2 Print print null
+   2 Ident a null
+   2 Semicolon ; null
+   2 EOF  null
+"
+    );
+
+    let (code, stdout) = dump("--tokens", "tokens_error", "var a = @;");
+    assert_eq!(code, Some(65));
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn ast() {
+    // Printed without resolving or running it, so the undefined `y` and calling a number are fine
+    let (code, stdout) = dump("--ast", "ast", "fun f(x) { return x + y; }\nf(1)(2);");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "(fun f (x) (return (+ x y)))\n(; (call (call f 1) 2))\n"
+    );
+
+    let (code, _) = dump("--ast", "ast_error", "print (1;");
+    assert_eq!(code, Some(65));
+}
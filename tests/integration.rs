@@ -1,4 +1,5 @@
 use regex::Regex;
+use rust_lox::error::{EXIT_INVALID, EXIT_RUNTIME};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -9,8 +10,7 @@ fn run_lox_test(test_path: &str) {
     let test_file = PathBuf::from(test_path);
     println!("Running test: {}", test_file.display());
 
-    let (expected_output, expected_errors) =
-        parse_expectations(&test_file).expect("Failed to parse test expectations");
+    let expectations = parse_expectations(&test_file).expect("Failed to parse test expectations");
 
     let interpreter_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/rust_lox");
 
@@ -22,7 +22,7 @@ fn run_lox_test(test_path: &str) {
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    for expected in &expected_output {
+    for expected in &expectations.output {
         assert!(
             stdout.contains(expected),
             "Expected output '{}' not found in stdout: {}",
@@ -31,7 +31,11 @@ fn run_lox_test(test_path: &str) {
         );
     }
 
-    for expected in &expected_errors {
+    for expected in expectations
+        .errors
+        .iter()
+        .chain(&expectations.runtime_error)
+    {
         assert!(
             stderr.contains(expected),
             "Expected error '{}' not found in stderr: {}",
@@ -39,16 +43,44 @@ fn run_lox_test(test_path: &str) {
             stderr
         );
     }
+
+    let expected_code = if expectations.runtime_error.is_some() {
+        EXIT_RUNTIME
+    } else if expectations.invalid {
+        EXIT_INVALID
+    } else {
+        0
+    };
+    assert_eq!(
+        output.status.code(),
+        Some(expected_code),
+        "Unexpected exit code, stderr: {}",
+        stderr
+    );
+}
+
+struct Expectations {
+    output: Vec<String>,
+    /// Errors found before the script runs
+    errors: Vec<String>,
+    /// Whether the script has errors found before it runs. Comments like `// [line 2] Error: ...`
+    /// count, but their message isn't checked.
+    invalid: bool,
+    runtime_error: Option<String>,
 }
 
-fn parse_expectations(test_file: &Path) -> Result<(Vec<String>, Vec<String>), std::io::Error> {
+fn parse_expectations(test_file: &Path) -> Result<Expectations, std::io::Error> {
     let content = fs::read_to_string(test_file)?;
 
     let mut expected_output = Vec::new();
     let mut expected_errors = Vec::new();
+    let mut invalid = false;
+    let mut runtime_error = None;
 
     let expect_regex = Regex::new(r"// expect:\s*(.+)").unwrap();
     let error_regex = Regex::new(r"// (error|Error).*:\s*(.+)").unwrap();
+    let invalid_regex = Regex::new(r"// (\[line \d+\] )?(error|Error)").unwrap();
+    let runtime_error_regex = Regex::new(r"// expect runtime error:\s*(.+)").unwrap();
 
     for line in content.lines() {
         if let Some(captures) = expect_regex.captures(line) {
@@ -62,7 +94,20 @@ fn parse_expectations(test_file: &Path) -> Result<(Vec<String>, Vec<String>), st
                 expected_errors.push(expected.as_str().trim().to_string());
             }
         }
+
+        invalid |= invalid_regex.is_match(line);
+
+        if let Some(captures) = runtime_error_regex.captures(line) {
+            if let Some(expected) = captures.get(1) {
+                runtime_error = Some(expected.as_str().trim().to_string());
+            }
+        }
     }
 
-    Ok((expected_output, expected_errors))
+    Ok(Expectations {
+        output: expected_output,
+        errors: expected_errors,
+        invalid,
+        runtime_error,
+    })
 }
@@ -0,0 +1,34 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Runs an interactive session with `args`, typing `input`, and returns what it printed to stdout.
+fn session(args: &[&str], input: &str) -> String {
+    let interpreter_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/rust_lox");
+    let mut child = Command::new(&interpreter_path)
+        .arg("--color=never")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute interpreter");
+    child
+        .stdin
+        .take()
+        .expect("Failed to open stdin")
+        .write_all(input.as_bytes())
+        .expect("Failed to write input");
+    let output = child
+        .wait_with_output()
+        .expect("Failed to wait for interpreter");
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn echo_is_truncated() {
+    let stdout = session(&["--max-print-len", "3"], "\"abcdef\"\nprint \"abcdef\";\n");
+    assert_eq!(
+        stdout,
+        "> abc... (3 more characters)\n> abc... (3 more characters)\n> "
+    );
+}